@@ -3,13 +3,31 @@
 #version 400 core
 
 in vec4 Color;
+in vec2 TexCoords;
+flat in float TexId;
 out vec4 o_color;
 
-//uniform sampler2D image;
+// slot 0 is a white texture, quads without a texture sample it
+uniform sampler2D u_Textures[8];
 
 void main()
 {
-    o_color = Color;// * texture2D(image, TexCoords);
+    // indexing sampler arrays with a value that varies inside a draw isn't allowed, so the
+    // slot is picked with a switch
+    vec4 texel;
+    switch (int(TexId))
+    {
+        case 1: texel = texture(u_Textures[1], TexCoords); break;
+        case 2: texel = texture(u_Textures[2], TexCoords); break;
+        case 3: texel = texture(u_Textures[3], TexCoords); break;
+        case 4: texel = texture(u_Textures[4], TexCoords); break;
+        case 5: texel = texture(u_Textures[5], TexCoords); break;
+        case 6: texel = texture(u_Textures[6], TexCoords); break;
+        case 7: texel = texture(u_Textures[7], TexCoords); break;
+        default: texel = texture(u_Textures[0], TexCoords); break;
+    }
+
+    o_color = Color * texel;
 } 
 
 
@@ -36,13 +54,15 @@ layout (location = 3) in float v_TexId;
 
 //uniform mat4 model; // i believe this should be gone
 uniform mat4 projection;
-//uniform sampler2D u_Textures[2];
-
 out vec4 Color;
+out vec2 TexCoords;
+flat out float TexId;
 
 void main()
 {
     Color = v_Color;
+    TexCoords = v_TexCoords;
+    TexId = v_TexId;
     vec4 modelPos =  vec4(v_Position.xy, 0.0, 1.0);
     gl_Position = projection * modelPos;
     
@@ -0,0 +1,36 @@
+!frag
+
+#version 330 core
+
+in vec3 TexCoords;
+in vec4 Color;
+
+out vec4 o_color;
+
+uniform sampler2DArray tiles;
+
+void main()
+{
+    o_color = Color * texture(tiles, TexCoords);
+} 
+
+
+!vert
+
+#version 330 core
+layout (location = 0) in vec2 v_Position; 
+layout (location = 1) in vec2 v_TexCoords; 
+layout (location = 2) in float v_Layer; 
+layout (location = 3) in vec4 v_Color; 
+
+uniform mat4 projection;
+
+out vec3 TexCoords;
+out vec4 Color;
+
+void main()
+{
+    TexCoords = vec3(v_TexCoords, v_Layer);
+    Color = v_Color;
+    gl_Position = projection * vec4(v_Position, 0.0, 1.0);
+}
@@ -28,6 +28,12 @@ fn generate_impl(ast: &syn::DeriveInput) -> quote::Tokens {
                 #(#fields_vertex_attrib_pointer)*
             }
         }
+
+        impl crate::render::data::Vertex for #ident #generics #where_clause {
+            fn vertex_attrib_pointers() {
+                <#ident #generics>::vertex_attrib_pointers()
+            }
+        }
     }
 }
 
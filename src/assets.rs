@@ -0,0 +1,240 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::ops::Deref;
+use std::rc::{Rc, Weak};
+
+use crate::render::skeleton::SkeletonData;
+use crate::render::sprite_sheet::SpriteSheet;
+use crate::render::texture::{Texture, TextureDescriptor};
+use crate::render::GlProgram;
+use crate::resources::{self, Resources};
+
+pub mod background;
+
+// something Assets can load and cache
+pub trait Asset: Sized + 'static {
+    // how to load it; loads of the same file with different settings are different assets
+    type Settings: Clone + PartialEq + Default + 'static;
+
+    // files are read through assets.resources(); assets made of other assets (e.g. the image of
+    // a sprite sheet) load those through _assets_ as well, so they are shared
+    fn load(assets: &mut Assets, name: &str, settings: &Self::Settings) -> Result<Self, String>;
+}
+
+impl Asset for Texture {
+    type Settings = TextureDescriptor;
+
+    fn load(
+        assets: &mut Assets,
+        name: &str,
+        descriptor: &TextureDescriptor,
+    ) -> Result<Texture, String> {
+        let texture = assets
+            .resources()
+            .load_texture_with(name, descriptor)
+            .map_err(|e| format!("error loading {}: {}", name, e))?;
+        texture.set_label(name);
+
+        Ok(texture)
+    }
+}
+
+impl Asset for GlProgram {
+    type Settings = ();
+
+    fn load(assets: &mut Assets, name: &str, _: &()) -> Result<GlProgram, String> {
+        GlProgram::from_res(assets.resources(), name)
+    }
+}
+
+// .aseprite/.ase files are read directly, see Resources::load_aseprite. anything else is taken
+// as the json of an exported sheet, whose image is loaded as a Texture asset with _descriptor_
+impl Asset for SpriteSheet {
+    type Settings = TextureDescriptor;
+
+    fn load(
+        assets: &mut Assets,
+        name: &str,
+        descriptor: &TextureDescriptor,
+    ) -> Result<SpriteSheet, String> {
+        if name.ends_with(".aseprite") || name.ends_with(".ase") {
+            return assets.resources().load_aseprite_with(name, descriptor);
+        }
+
+        let layout = assets.resources().load_sprite_sheet_layout(name)?;
+        let texture = assets
+            .load_with::<Texture>(&resources::relative_to(name, &layout.image), descriptor)?;
+
+        Ok(SpriteSheet::new(texture, layout))
+    }
+}
+
+impl Asset for SkeletonData {
+    type Settings = ();
+
+    fn load(assets: &mut Assets, name: &str, _: &()) -> Result<SkeletonData, String> {
+        assets.resources().load_skeleton_data(name)
+    }
+}
+
+// shared, reference counted access to a loaded asset. cloning is cheap; once the last handle to
+// an asset is dropped, so is the asset, and with it its GPU objects
+pub struct Handle<T> {
+    asset: Rc<T>,
+    name: Rc<str>,
+}
+
+impl<T> Handle<T> {
+    // a handle to an asset no store knows about, e.g. one made in code or loaded straight
+    // through Resources
+    pub fn new(asset: T, name: &str) -> Handle<T> {
+        Handle {
+            asset: Rc::new(asset),
+            name: Rc::from(name),
+        }
+    }
+
+    // the name the asset was loaded with
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn ptr_eq(a: &Handle<T>, b: &Handle<T>) -> bool {
+        Rc::ptr_eq(&a.asset, &b.asset)
+    }
+
+    // handles to this asset alive, this one included
+    pub fn count(&self) -> usize {
+        Rc::strong_count(&self.asset)
+    }
+}
+
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Handle<T> {
+        Handle {
+            asset: Rc::clone(&self.asset),
+            name: Rc::clone(&self.name),
+        }
+    }
+}
+
+impl<T> Deref for Handle<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.asset
+    }
+}
+
+struct Entry {
+    // T::Settings
+    settings: Box<dyn Any>,
+    // Weak<T>, so the cache alone doesn't keep assets alive
+    asset: Box<dyn Any>,
+    // the same Weak<T>, for checking whether it's alive without knowing T
+    alive: Box<dyn Fn() -> bool>,
+    name: Rc<str>,
+}
+
+impl Entry {
+    fn upgrade<T: Asset>(&self) -> Option<Handle<T>> {
+        let asset = self.asset.downcast_ref::<Weak<T>>()?.upgrade()?;
+
+        Some(Handle {
+            asset,
+            name: Rc::clone(&self.name),
+        })
+    }
+}
+
+// loads assets through Resources, at most once each as long as a handle to them is around:
+//
+//  let mut assets = Assets::new(res);
+//  let grass = assets.load::<Texture>("tiles/grass.png")?;
+//  let same = assets.load::<Texture>("tiles/grass.png")?; // no disk access
+//  let crisp = assets.load_with::<Texture>("tiles/grass.png", &TextureDescriptor::pixel_art())?;
+pub struct Assets {
+    res: Resources,
+    // by asset type and name; more than one when loaded with different settings
+    entries: HashMap<(TypeId, String), Vec<Entry>>,
+    background: background::BackgroundLoads,
+}
+
+impl Assets {
+    pub fn new(res: Resources) -> Assets {
+        Assets {
+            res,
+            entries: HashMap::new(),
+            background: background::BackgroundLoads::default(),
+        }
+    }
+
+    pub fn resources(&self) -> &Resources {
+        &self.res
+    }
+
+    pub fn load<T: Asset>(&mut self, name: &str) -> Result<Handle<T>, String> {
+        self.load_with(name, &T::Settings::default())
+    }
+
+    pub fn load_with<T: Asset>(
+        &mut self,
+        name: &str,
+        settings: &T::Settings,
+    ) -> Result<Handle<T>, String> {
+        if let Some(handle) = self.get_with(name, settings) {
+            return Ok(handle);
+        }
+
+        let asset = T::load(self, name, settings)?;
+
+        Ok(self.insert(name, settings, asset))
+    }
+
+    // caches an asset loaded elsewhere
+    fn insert<T: Asset>(&mut self, name: &str, settings: &T::Settings, asset: T) -> Handle<T> {
+        let asset = Rc::new(asset);
+        let name: Rc<str> = Rc::from(name);
+        let entries = self
+            .entries
+            .entry((TypeId::of::<T>(), name.to_string()))
+            .or_default();
+        entries.retain(|entry| (entry.alive)());
+
+        let weak = Rc::downgrade(&asset);
+        entries.push(Entry {
+            settings: Box::new(settings.clone()),
+            asset: Box::new(weak.clone()),
+            alive: Box::new(move || weak.strong_count() > 0),
+            name: Rc::clone(&name),
+        });
+
+        Handle { asset, name }
+    }
+
+    // the asset if it's loaded, without loading it otherwise
+    pub fn get<T: Asset>(&self, name: &str) -> Option<Handle<T>> {
+        self.get_with(name, &T::Settings::default())
+    }
+
+    pub fn get_with<T: Asset>(&self, name: &str, settings: &T::Settings) -> Option<Handle<T>> {
+        self.entries
+            .get(&(TypeId::of::<T>(), name.to_string()))?
+            .iter()
+            .filter(|entry| entry.settings.downcast_ref::<T::Settings>() == Some(settings))
+            .find_map(|entry| entry.upgrade())
+    }
+
+    pub fn is_loaded<T: Asset>(&self, name: &str) -> bool {
+        self.get::<T>(name).is_some()
+    }
+
+    // forgets assets whose handles are all gone; they are already freed, this only trims the
+    // cache
+    pub fn collect_garbage(&mut self) {
+        for entries in self.entries.values_mut() {
+            entries.retain(|entry| (entry.alive)());
+        }
+        self.entries.retain(|_, entries| !entries.is_empty());
+    }
+}
@@ -0,0 +1,430 @@
+use std::any::{Any, TypeId};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::panic::{self, AssertUnwindSafe};
+use std::rc::Rc;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use super::{Asset, Assets, Handle};
+use crate::render::aseprite::AsepriteFile;
+use crate::render::image::Image;
+use crate::render::skeleton::SkeletonData;
+use crate::render::sprite_sheet::{SpriteSheet, SpriteSheetLayout};
+use crate::render::texture::{Texture, TextureDescriptor};
+use crate::resources::{self, ImageChannels, ImageLoadOptions, Resources};
+
+// assets whose loading splits into decoding, done on a worker thread, and creating GPU objects
+// from the decoded data, done on the main thread where the GL context is current. upload gets
+// the store, for assets made of other assets the same way Asset::load does
+pub trait AsyncAsset: Asset {
+    type Decoded: Send + 'static;
+
+    fn decode(
+        res: &Resources,
+        name: &str,
+        settings: &Self::Settings,
+    ) -> Result<Self::Decoded, String>;
+
+    fn upload(
+        decoded: Self::Decoded,
+        assets: &mut Assets,
+        name: &str,
+        settings: &Self::Settings,
+    ) -> Result<Self, String>;
+}
+
+impl AsyncAsset for Texture {
+    type Decoded = Image;
+
+    fn decode(
+        res: &Resources,
+        name: &str,
+        descriptor: &TextureDescriptor,
+    ) -> Result<Image, String> {
+        let options = ImageLoadOptions {
+            channels: ImageChannels::Force(descriptor.format.channels()),
+            ..ImageLoadOptions::default()
+        };
+
+        res.load_image(name, &options)
+            .map_err(|e| format!("error loading {}: {}", name, e))
+    }
+
+    fn upload(
+        image: Image,
+        _: &mut Assets,
+        name: &str,
+        descriptor: &TextureDescriptor,
+    ) -> Result<Texture, String> {
+        let texture = resources::texture_from_image(&image, descriptor)
+            .map_err(|e| format!("error loading {}: {}", name, e))?;
+        texture.set_label(name);
+
+        Ok(texture)
+    }
+}
+
+pub enum DecodedSpriteSheet {
+    Aseprite(AsepriteFile),
+    Exported(SpriteSheetLayout, Image),
+}
+
+impl AsyncAsset for SpriteSheet {
+    type Decoded = DecodedSpriteSheet;
+
+    fn decode(
+        res: &Resources,
+        name: &str,
+        descriptor: &TextureDescriptor,
+    ) -> Result<DecodedSpriteSheet, String> {
+        if name.ends_with(".aseprite") || name.ends_with(".ase") {
+            let bytes = res
+                .load_bytes(name)
+                .map_err(|e| format!("error loading {}: {:?}", name, e))?;
+            let file = AsepriteFile::parse(&bytes)
+                .map_err(|e| format!("error loading {}: {}", name, e))?;
+
+            return Ok(DecodedSpriteSheet::Aseprite(file));
+        }
+
+        let json = res
+            .load_string(name)
+            .map_err(|e| format!("error loading {}: {:?}", name, e))?;
+        let layout = SpriteSheetLayout::from_aseprite_json(&json)
+            .map_err(|e| format!("error loading {}: {}", name, e))?;
+        let image = Texture::decode(
+            res,
+            &resources::relative_to(name, &layout.image),
+            descriptor,
+        )?;
+
+        Ok(DecodedSpriteSheet::Exported(layout, image))
+    }
+
+    fn upload(
+        decoded: DecodedSpriteSheet,
+        assets: &mut Assets,
+        name: &str,
+        descriptor: &TextureDescriptor,
+    ) -> Result<SpriteSheet, String> {
+        match decoded {
+            DecodedSpriteSheet::Aseprite(file) => file
+                .to_sprite_sheet(name, descriptor)
+                .map_err(|e| format!("error loading {}: {}", name, e)),
+            DecodedSpriteSheet::Exported(layout, image) => {
+                // the image ends up a Texture asset as with Assets::load, so sheets sharing it
+                // share the texture; one already loaded wins over the copy decoded here
+                let image_name = resources::relative_to(name, &layout.image);
+                let texture = match assets.get_with::<Texture>(&image_name, descriptor) {
+                    Some(texture) => texture,
+                    None => {
+                        let texture = Texture::upload(image, assets, &image_name, descriptor)?;
+                        assets.insert(&image_name, descriptor, texture)
+                    }
+                };
+
+                Ok(SpriteSheet::new(texture, layout))
+            }
+        }
+    }
+}
+
+// no GPU objects, the whole load happens on the worker
+impl AsyncAsset for SkeletonData {
+    type Decoded = SkeletonData;
+
+    fn decode(res: &Resources, name: &str, _: &()) -> Result<SkeletonData, String> {
+        res.load_skeleton_data(name)
+    }
+
+    fn upload(data: SkeletonData, _: &mut Assets, _: &str, _: &()) -> Result<SkeletonData, String> {
+        Ok(data)
+    }
+}
+
+pub enum LoadState<T> {
+    Loading,
+    Loaded(Handle<T>),
+    Failed(String),
+}
+
+// an asset that is still loading, see Assets::load_async. clones share the same load
+pub struct PendingHandle<T> {
+    state: Rc<RefCell<LoadState<T>>>,
+}
+
+impl<T> PendingHandle<T> {
+    // the asset once it's uploaded
+    pub fn get(&self) -> Option<Handle<T>> {
+        match &*self.state.borrow() {
+            LoadState::Loaded(handle) => Some(handle.clone()),
+            _ => None,
+        }
+    }
+
+    pub fn is_loading(&self) -> bool {
+        matches!(*self.state.borrow(), LoadState::Loading)
+    }
+
+    pub fn error(&self) -> Option<String> {
+        match &*self.state.borrow() {
+            LoadState::Failed(error) => Some(error.clone()),
+            _ => None,
+        }
+    }
+}
+
+impl<T> Clone for PendingHandle<T> {
+    fn clone(&self) -> PendingHandle<T> {
+        PendingHandle {
+            state: Rc::clone(&self.state),
+        }
+    }
+}
+
+// loads requested since nothing was loading, for loading screens
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct LoadProgress {
+    pub requested: usize,
+    pub loaded: usize,
+    pub failed: usize,
+}
+
+impl LoadProgress {
+    pub fn is_done(&self) -> bool {
+        self.loaded + self.failed == self.requested
+    }
+
+    // in 0..1, 1 when nothing is loading
+    pub fn fraction(&self) -> f32 {
+        if self.requested == 0 {
+            return 1.0;
+        }
+
+        (self.loaded + self.failed) as f32 / self.requested as f32
+    }
+}
+
+type Job = Box<dyn FnOnce() -> Box<dyn Any + Send> + Send>;
+type Upload = Box<dyn FnOnce(Box<dyn Any + Send>, &mut Assets)>;
+// by asset type and name, (settings, Rc<RefCell<LoadState<T>>>) of loads in flight
+type InFlight = HashMap<(TypeId, String), Vec<(Box<dyn Any>, Box<dyn Any>)>>;
+
+// threads decoding files; results come back tagged with the id of their job
+pub(super) struct WorkerPool {
+    jobs: Option<Sender<(u64, Job)>>,
+    results: Receiver<(u64, Box<dyn Any + Send>)>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl WorkerPool {
+    fn new() -> WorkerPool {
+        // leaves a core for the main thread
+        let threads = thread::available_parallelism()
+            .map(|threads| threads.get() - 1)
+            .unwrap_or(1)
+            .max(1);
+
+        let (jobs, job_receiver) = mpsc::channel::<(u64, Job)>();
+        let (result_sender, results) = mpsc::channel();
+        let job_receiver = Arc::new(Mutex::new(job_receiver));
+
+        let workers = (0..threads)
+            .map(|i| {
+                let job_receiver = Arc::clone(&job_receiver);
+                let result_sender = result_sender.clone();
+                thread::Builder::new()
+                    .name(format!("asset loader {}", i))
+                    .spawn(move || loop {
+                        // the lock is released before running the job
+                        let job = job_receiver.lock().unwrap().recv();
+                        match job {
+                            Ok((id, job)) => {
+                                if result_sender.send((id, job())).is_err() {
+                                    return;
+                                }
+                            }
+                            // the pool is gone
+                            Err(_) => return,
+                        }
+                    })
+                    .expect("error spawning asset loader thread")
+            })
+            .collect();
+
+        WorkerPool {
+            jobs: Some(jobs),
+            results,
+            workers,
+        }
+    }
+}
+
+impl Drop for WorkerPool {
+    fn drop(&mut self) {
+        // closing the channel stops the workers once they're done with what they're decoding
+        self.jobs.take();
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+// loads that were queued and not uploaded yet
+#[derive(Default)]
+pub(super) struct BackgroundLoads {
+    pool: Option<WorkerPool>,
+    next_id: u64,
+    uploads: HashMap<u64, Upload>,
+    // so the same asset requested twice is only loaded once
+    in_flight: InFlight,
+    progress: LoadProgress,
+}
+
+impl Assets {
+    pub fn load_async<T: AsyncAsset>(&mut self, name: &str) -> PendingHandle<T>
+    where
+        T::Settings: Send,
+    {
+        self.load_async_with(name, &T::Settings::default())
+    }
+
+    // returns right away; the file is decoded on a worker thread and uploaded by a later call
+    // to process_uploads. assets that are already loaded come back ready
+    pub fn load_async_with<T: AsyncAsset>(
+        &mut self,
+        name: &str,
+        settings: &T::Settings,
+    ) -> PendingHandle<T>
+    where
+        T::Settings: Send,
+    {
+        if let Some(handle) = self.get_with::<T>(name, settings) {
+            return PendingHandle {
+                state: Rc::new(RefCell::new(LoadState::Loaded(handle))),
+            };
+        }
+
+        let key = (TypeId::of::<T>(), name.to_string());
+        let loads = &mut self.background;
+        let in_flight = loads
+            .in_flight
+            .get(&key)
+            .into_iter()
+            .flatten()
+            .filter(|(other, _)| other.downcast_ref::<T::Settings>() == Some(settings))
+            .find_map(|(_, state)| state.downcast_ref::<Rc<RefCell<LoadState<T>>>>());
+        if let Some(state) = in_flight {
+            return PendingHandle {
+                state: Rc::clone(state),
+            };
+        }
+
+        if loads.uploads.is_empty() {
+            loads.progress = LoadProgress::default();
+        }
+        loads.progress.requested += 1;
+
+        let state = Rc::new(RefCell::new(LoadState::Loading));
+        loads
+            .in_flight
+            .entry(key.clone())
+            .or_default()
+            .push((Box::new(settings.clone()), Box::new(Rc::clone(&state))));
+
+        let id = loads.next_id;
+        loads.next_id += 1;
+
+        let res = self.res.clone();
+        let job_name = name.to_string();
+        let job_settings = settings.clone();
+        let job: Job = Box::new(move || {
+            // a panicking decoder would take the worker down and leave the load pending forever
+            let decoded: Result<T::Decoded, String> = panic::catch_unwind(AssertUnwindSafe(|| {
+                T::decode(&res, &job_name, &job_settings)
+            }))
+            .unwrap_or_else(|_| Err(format!("error loading {}: decoding panicked", job_name)));
+            Box::new(decoded)
+        });
+
+        let upload_state = Rc::clone(&state);
+        let upload_settings = settings.clone();
+        let upload: Upload = Box::new(move |decoded, assets| {
+            let decoded = *decoded
+                .downcast::<Result<T::Decoded, String>>()
+                .expect("decoded asset of the wrong type");
+            let asset =
+                decoded.and_then(|decoded| T::upload(decoded, assets, &key.1, &upload_settings));
+
+            let loads = &mut assets.background;
+            if let Some(in_flight) = loads.in_flight.get_mut(&key) {
+                in_flight.retain(|(_, state)| {
+                    !matches!(
+                        state.downcast_ref::<Rc<RefCell<LoadState<T>>>>(),
+                        Some(state) if Rc::ptr_eq(state, &upload_state)
+                    )
+                });
+                if in_flight.is_empty() {
+                    loads.in_flight.remove(&key);
+                }
+            }
+
+            let state = match asset {
+                Ok(asset) => {
+                    loads.progress.loaded += 1;
+                    LoadState::Loaded(assets.insert(&key.1, &upload_settings, asset))
+                }
+                Err(error) => {
+                    loads.progress.failed += 1;
+                    LoadState::Failed(error)
+                }
+            };
+            *upload_state.borrow_mut() = state;
+        });
+        loads.uploads.insert(id, upload);
+
+        let pool = loads.pool.get_or_insert_with(WorkerPool::new);
+        if let Some(jobs) = &pool.jobs {
+            // workers only stop once the pool is dropped, so this can't fail
+            let _ = jobs.send((id, job));
+        }
+
+        PendingHandle { state }
+    }
+
+    // uploads decoded assets until _budget_ is spent. at least one upload happens per call so
+    // loading always moves forward, however small the budget. call it once per frame
+    pub fn process_uploads(&mut self, budget: Duration) {
+        let start = Instant::now();
+
+        loop {
+            let result = match &self.background.pool {
+                Some(pool) => pool.results.try_recv(),
+                None => return,
+            };
+            let (id, decoded) = match result {
+                Ok(result) => result,
+                Err(_) => return,
+            };
+
+            if let Some(upload) = self.background.uploads.remove(&id) {
+                upload(decoded, self);
+            }
+
+            if start.elapsed() >= budget {
+                return;
+            }
+        }
+    }
+
+    pub fn progress(&self) -> LoadProgress {
+        self.background.progress
+    }
+
+    pub fn is_loading(&self) -> bool {
+        !self.background.uploads.is_empty()
+    }
+}
@@ -0,0 +1,150 @@
+use std::thread;
+use std::time::{Duration, Instant};
+
+// sleeping overshoots by up to a millisecond or so; this close to the deadline we spin instead
+const SPIN_THRESHOLD: Duration = Duration::from_millis(1);
+// ticks run at most per frame before the loop gives up on catching up
+const DEFAULT_MAX_TICKS_PER_FRAME: u32 = 8;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FrameLimit {
+    // renders as fast as it can
+    Uncapped,
+    // swapping buffers waits for the display; the window has to be set up for it, the loop
+    // itself never waits
+    VSync,
+    // sleeps at the end of each frame to render at most this many frames per second
+    TargetFps(u32),
+}
+
+// drives a loop where the game advances in fixed ticks, independently of how often it renders.
+// frame time is accumulated and spent in ticks; what is left over, less than a tick, is passed
+// to rendering as _alpha_ to interpolate between the previous and current state:
+//
+//  loop {
+//      game_loop.begin_frame();
+//      while game_loop.tick() {
+//          update(game_loop.tick_duration());
+//      }
+//      render(game_loop.alpha());
+//      swap buffers
+//      game_loop.end_frame();
+//  }
+pub struct GameLoop {
+    tick_duration: Duration,
+    max_ticks_per_frame: u32,
+    frame_limit: FrameLimit,
+    accumulator: Duration,
+    frame_start: Instant,
+    frame_delta: Duration,
+    ticks_this_frame: u32,
+    total_ticks: u64,
+    dropped_time: Duration,
+}
+
+impl GameLoop {
+    pub fn new(ticks_per_second: u32) -> GameLoop {
+        GameLoop {
+            tick_duration: Duration::from_secs(1) / ticks_per_second.max(1),
+            max_ticks_per_frame: DEFAULT_MAX_TICKS_PER_FRAME,
+            frame_limit: FrameLimit::VSync,
+            accumulator: Duration::ZERO,
+            frame_start: Instant::now(),
+            frame_delta: Duration::ZERO,
+            ticks_this_frame: 0,
+            total_ticks: 0,
+            dropped_time: Duration::ZERO,
+        }
+    }
+
+    pub fn tick_duration(&self) -> Duration {
+        self.tick_duration
+    }
+
+    // when updates take longer than the time they simulate, every frame has more ticks to catch
+    // up on than the last (the spiral of death). past this many the backlog is dropped and the
+    // game slows down instead of freezing
+    pub fn set_max_ticks_per_frame(&mut self, max_ticks: u32) {
+        self.max_ticks_per_frame = max_ticks.max(1);
+    }
+
+    pub fn frame_limit(&self) -> FrameLimit {
+        self.frame_limit
+    }
+
+    pub fn set_frame_limit(&mut self, frame_limit: FrameLimit) {
+        self.frame_limit = frame_limit;
+    }
+
+    // time between the start of the last frame and this one
+    pub fn frame_delta(&self) -> Duration {
+        self.frame_delta
+    }
+
+    pub fn ticks_this_frame(&self) -> u32 {
+        self.ticks_this_frame
+    }
+
+    pub fn total_ticks(&self) -> u64 {
+        self.total_ticks
+    }
+
+    // time never simulated because the loop fell too far behind
+    pub fn dropped_time(&self) -> Duration {
+        self.dropped_time
+    }
+
+    pub fn begin_frame(&mut self) {
+        let now = Instant::now();
+        self.frame_delta = now - self.frame_start;
+        self.frame_start = now;
+        self.ticks_this_frame = 0;
+
+        self.accumulator += self.frame_delta;
+        let max_backlog = self.tick_duration * self.max_ticks_per_frame;
+        if self.accumulator > max_backlog {
+            self.dropped_time += self.accumulator - max_backlog;
+            self.accumulator = max_backlog;
+        }
+    }
+
+    // consumes one tick of the accumulated time; false once less than a tick is left
+    pub fn tick(&mut self) -> bool {
+        if self.accumulator < self.tick_duration {
+            return false;
+        }
+
+        self.accumulator -= self.tick_duration;
+        self.ticks_this_frame += 1;
+        self.total_ticks += 1;
+        true
+    }
+
+    // how far rendering is between the last tick and the next one, in 0..1
+    pub fn alpha(&self) -> f32 {
+        self.accumulator.as_secs_f32() / self.tick_duration.as_secs_f32()
+    }
+
+    // waits out the rest of the frame when limiting to a target fps
+    pub fn end_frame(&mut self) {
+        let fps = match self.frame_limit {
+            FrameLimit::TargetFps(fps) if fps > 0 => fps,
+            _ => return,
+        };
+
+        let deadline = self.frame_start + Duration::from_secs(1) / fps;
+        loop {
+            let now = Instant::now();
+            if now >= deadline {
+                break;
+            }
+
+            let left = deadline - now;
+            if left > SPIN_THRESHOLD {
+                thread::sleep(left - SPIN_THRESHOLD);
+            } else {
+                std::hint::spin_loop();
+            }
+        }
+    }
+}
@@ -1,31 +1,48 @@
 use egui_backend::sdl2::event::Event;
 use egui_backend::{gl, sdl2};
 
-use render::animation::Animation;
+use assets::Assets;
+use game_loop::{FrameLimit, GameLoop};
+use render::animation::{Animation, PlaybackMode};
+use render::profiler::Profiler;
 use render::renderer::batch_renderer::BatchRenderer;
+use render::renderer::tile_renderer::TileRenderer;
 use render::renderer::SpriteRenderer;
-use render::subtexture::Subtexture;
+use render::stats;
+use render::virtual_screen::{ScaleMode, VirtualScreen};
+use render::texture::{Texture, TextureDescriptor};
 use resources::Resources;
 use sdl2::keyboard::Keycode;
 use std::path::Path;
+use time::Time;
+use tween::{Easing, Repeat, Timeline, Tween};
 
-use std::time::{Instant, Duration};
+use std::time::Duration;
 // Alias the backend to something less mouthful
 use egui_sdl2_gl as egui_backend;
 
 use render::data::AttributedVertex;
 use sdl2::video::SwapInterval;
 
-use render::buffer;
+use render::backend::PrimitiveMode;
+use render::capture::{FrameCapture, RecordingFormat};
+use render::mesh::Mesh;
 
 #[macro_use]
 extern crate render_derive;
 
 const SCREEN_WIDTH: u32 = 1600;
 const SCREEN_HEIGHT: u32 = 900;
+// resolution the scene is rendered at before being scaled up to the window
+const VIRTUAL_WIDTH: usize = 800;
+const VIRTUAL_HEIGHT: usize = 450;
 
+mod assets;
+mod game_loop;
 mod render;
 mod resources;
+mod time;
+mod tween;
 
 #[derive(VertexAttribPointers, Copy, Clone, Debug)]
 #[repr(C, packed)]
@@ -42,11 +59,17 @@ fn main() {
     {
         let gl_attr = video.gl_attr();
         gl_attr.set_context_profile(sdl2::video::GLProfile::Core);
-        gl_attr.set_context_version(4, 0);
+        gl_attr.set_context_version(4, 3);
+        // lets the driver report errors and warnings through render::debug
+        if cfg!(debug_assertions) {
+            gl_attr.set_context_flags().debug().set();
+        }
     }
     let mut i = 0.0;
 
     let mut separation = 0.0;
+    let mut show_profiler = false;
+    let mut show_render_stats = false;
 
     let window = video
         .window("Blue", SCREEN_WIDTH, SCREEN_HEIGHT)
@@ -55,9 +78,22 @@ fn main() {
         .build()
         .unwrap();
 
-    // Create a window context
-    let _ctx = window.gl_create_context().unwrap();
+    // Create a window context; 4.3 gives us compute shaders, but we can run on 4.0 without them
+    let _ctx = window
+        .gl_create_context()
+        .or_else(|_| {
+            video.gl_attr().set_context_version(4, 0);
+            window.gl_create_context()
+        })
+        .unwrap();
     gl::load_with(|s| video.gl_get_proc_address(s) as _);
+    if render::debug::enable_debug_output() {
+        println!("gl debug output enabled");
+    }
+    println!(
+        "compute shaders supported: {}",
+        render::compute::ComputeProgram::is_supported()
+    );
 
     let mut event_pump = sdl_context.event_pump().unwrap();
 
@@ -73,30 +109,49 @@ fn main() {
     let mut imgui_sdl2 = imgui_sdl2::ImguiSdl2::new(&mut imgui, &window);
     let renderer =
         imgui_opengl_renderer::Renderer::new(&mut imgui, |s| video.gl_get_proc_address(s) as _);
-    let mut last_frame = Instant::now();
 
     let res = Resources::from_relative_exe_path(Path::new("assets")).unwrap();
-    let shader_program = render::GlProgram::from_res(&res, "shaders/triangle.glsl")
+    let mut assets = Assets::new(res.clone());
+    let shader_program = assets
+        .load::<render::GlProgram>("shaders/triangle.glsl")
         .expect("Failed to load triangle shader asset");
 
-    let sprite_renderer =
-        SpriteRenderer::from_res(&res, glm::vec2(SCREEN_WIDTH as f32, SCREEN_HEIGHT as f32))
-            .expect("error creating sprite renderer");
-    let mut batch_renderer = BatchRenderer::from_res(
-        &res,
-        glm::vec2(SCREEN_WIDTH as f32, SCREEN_HEIGHT as f32),
-        1024,
+    let (drawable_width, drawable_height) = window.drawable_size();
+    let mut capture = FrameCapture::new(
+        drawable_width as usize,
+        drawable_height as usize,
+        "captures",
+    );
+    // the scene is drawn in virtual pixels, so every renderer projects to the virtual size
+    let mut screen = VirtualScreen::new(
+        VIRTUAL_WIDTH,
+        VIRTUAL_HEIGHT,
+        drawable_width as usize,
+        drawable_height as usize,
+        ScaleMode::Integer,
     )
-    .expect("error creating sprite renderer");
+    .expect("error creating virtual screen");
 
-    let texture = res
-        .load_texture("sprites/test.png")
+    let sprite_renderer =
+        SpriteRenderer::from_res(&res, screen.size()).expect("error creating sprite renderer");
+    let mut batch_renderer =
+        BatchRenderer::from_res(&res, screen.size(), 1024).expect("error creating sprite renderer");
+    let mut tile_renderer =
+        TileRenderer::from_res(&res, screen.size(), 1024).expect("error creating tile renderer");
+
+    // drawn scaled down and rotated, so it looks better filtered than as pixel art
+    let texture = assets
+        .load_with::<Texture>("sprites/test.png", &TextureDescriptor::smooth())
         .expect("error loading test.png to texture");
-    let map = res
-        .load_texture("tiles/grass.png")
+    let map = assets
+        .load::<Texture>("tiles/grass.png")
         .expect("error loading test.png to texture");
 
-    let first_tile = Subtexture::from_tiles(&map, 9, 6, glm::vec2(16.0, 16.0));
+    let tileset = res
+        .load_texture_array("tiles/grass.png", 16, 16)
+        .expect("error loading grass.png to texture array");
+    tileset.set_label("tiles/grass.png");
+    let first_tile = tileset.layer_of(9, 6);
 
     let vertices: Vec<Vertex> = vec![
         Vertex {
@@ -113,19 +168,7 @@ fn main() {
         }, // top
     ];
 
-    let vbo = buffer::VertexBuffer::new();
-    let vao = buffer::VertexArray::new(); // changed
-    vbo.bind();
-    vbo.upload_data_static_draw(&vertices);
-    vbo.unbind();
-
-    // set up vertex array object
-
-    vao.bind();
-    vbo.bind();
-    Vertex::vertex_attrib_pointers();
-    vbo.unbind();
-    vao.unbind();
+    let triangle: Mesh<Vertex, u8> = Mesh::new(&vertices, &[0, 1, 2], PrimitiveMode::Triangles);
 
     // set up shared state for window
 
@@ -137,22 +180,23 @@ fn main() {
         gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
     }
 
-    // megaman sprites
-
-    let first = res
-        .load_texture("sprites/1.png")
-        .expect("error loading test.png to texture");
-    
-        let second = res
-        .load_texture("sprites/2.png")
-        .expect("error loading test.png to texture");
-    
-        let third = res
-        .load_texture("sprites/3.png")
-        .expect("error loading test.png to texture");
-    
-    let megaman = [first, second, third];
-    let mut megaman = Animation::new(&megaman, Duration::from_micros(160_666));
+    // megaman sprites, handles from the store so the animation doesn't borrow the frames
+    let megaman = ["sprites/1.png", "sprites/2.png", "sprites/3.png"]
+        .map(|name| assets.load::<Texture>(name).expect("error loading megaman frame"));
+    let mut megaman = Animation::from_textures(&megaman, Duration::from_micros(160_666))
+        .expect("error creating megaman animation");
+    megaman.set_playback_mode(PlaybackMode::PingPong);
+    megaman.add_event(2, "step");
+    let mut megaman_steps = 0;
+    // megaman breathes in and out
+    let mut megaman_scale = Tween::new(4.75, 5.25, Duration::from_millis(800), Easing::SineInOut)
+        .with_repeat(Repeat::Forever)
+        .with_yoyo(true);
+    let mut profiler = Profiler::new();
+    // updates run at 60 ticks per second whatever the frame rate
+    let mut game_loop = GameLoop::new(60);
+    let mut time = Time::new();
+    let mut previous_i = i;
     'running: loop {
         for event in event_pump.poll_iter() {
             if imgui_sdl2.ignore_event(&event) {
@@ -169,49 +213,126 @@ fn main() {
                     keycode: Some(Keycode::Escape),
                     ..
                 } => break 'running,
+                Event::KeyDown {
+                    keycode: Some(Keycode::F2),
+                    repeat: false,
+                    ..
+                } => show_profiler = !show_profiler,
+                Event::KeyDown {
+                    keycode: Some(Keycode::F3),
+                    repeat: false,
+                    ..
+                } => show_render_stats = !show_render_stats,
+                Event::KeyDown {
+                    keycode: Some(Keycode::F5),
+                    repeat: false,
+                    ..
+                } => {
+                    let frame_limit = match game_loop.frame_limit() {
+                        FrameLimit::VSync => FrameLimit::Uncapped,
+                        FrameLimit::Uncapped => FrameLimit::TargetFps(30),
+                        FrameLimit::TargetFps(_) => FrameLimit::VSync,
+                    };
+                    game_loop.set_frame_limit(frame_limit);
+
+                    let swap_interval = if frame_limit == FrameLimit::VSync {
+                        SwapInterval::VSync
+                    } else {
+                        SwapInterval::Immediate
+                    };
+                    if let Err(e) = window.subsystem().gl_set_swap_interval(swap_interval) {
+                        println!("could not change swap interval: {}", e);
+                    }
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::F4),
+                    repeat: false,
+                    ..
+                } => {
+                    let scale_mode = match screen.scale_mode() {
+                        ScaleMode::Integer => ScaleMode::Fit,
+                        ScaleMode::Fit => ScaleMode::Integer,
+                    };
+                    screen.set_scale_mode(scale_mode);
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::P),
+                    repeat: false,
+                    ..
+                } => time.set_paused(!time.is_paused()),
+                Event::KeyDown {
+                    keycode: Some(Keycode::F6),
+                    repeat: false,
+                    ..
+                } => {
+                    // toggles slow motion
+                    let time_scale = if time.time_scale() < 1.0 { 1.0 } else { 0.25 };
+                    time.set_time_scale(time_scale);
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::F12),
+                    repeat: false,
+                    ..
+                } => capture.request_screenshot(),
+                Event::KeyDown {
+                    keycode: Some(keycode @ (Keycode::F10 | Keycode::F11)),
+                    repeat: false,
+                    ..
+                } => {
+                    let format = if keycode == Keycode::F10 {
+                        RecordingFormat::PngSequence
+                    } else {
+                        RecordingFormat::Gif
+                    };
+                    if let Err(e) = capture.toggle_recording(format) {
+                        println!("could not start recording: {}", e);
+                    }
+                }
                 _ => {}
             }
         }
 
         imgui_sdl2.prepare_frame(imgui.io_mut(), &window, &event_pump.mouse_state());
 
-        let now = Instant::now();
-        let delta = now - last_frame;
-        let delta_s = delta.as_secs() as f32 + delta.subsec_nanos() as f32 / 1_000_000_000.0;
-        last_frame = now;
-        imgui.io_mut().delta_time = delta_s;
+        game_loop.begin_frame();
+        while game_loop.tick() {
+            // todo: this is all hacky and hardcoded, interface needs to be decided still
+            time.advance(game_loop.tick_duration());
+            previous_i = i;
+            i += 18.0 * time.delta_seconds();
+            megaman.update(&time);
+            megaman_scale.update(&time);
+            megaman_steps += megaman.drain_events().filter(|event| event == "step").count();
+        }
+        // textures loaded in the background are uploaded a few at a time, not to stall the frame
+        assets.process_uploads(Duration::from_millis(4));
+        // the spinning sprites are drawn between the last two ticks so they move smoothly
+        // whatever the frame rate
+        let angle = previous_i + (i - previous_i) * game_loop.alpha();
+
+        // imgui asserts on a zero delta, which uncapped frames can come close to
+        imgui.io_mut().delta_time = game_loop.frame_delta().as_secs_f32().max(1.0e-6);
 
         let ui = imgui.frame();
+        profiler.begin_frame();
 
-        // test triangle vbo
-        unsafe {
-            gl::ClearColor(0.2, 0.2, 0.2, 1.0);
-            gl::Clear(gl::COLOR_BUFFER_BIT);
-        }
+        // test triangle
+        screen.begin();
+        render::clear(glm::vec4(0.2, 0.2, 0.2, 1.0));
 
         shader_program.set_used();
-        vao.bind();
-
-        unsafe {
-            gl::DrawArrays(
-                gl::TRIANGLES, // mode
-                0,             // starting index in the enabled arrays
-                3,             // number of indices to be rendered
-            );
-        }
-        vao.unbind();
+        triangle.draw();
 
-        // todo: this is all hacky and hardcoded, interface needs to be decided still
-        i += 0.3;
         //println!("quiere arrancar");
 
+        profiler.begin_scope("sprites");
         sprite_renderer.draw_quad(
             &map,
-            400.0,
-            400.0,
+            200.0,
+            200.0,
             0.0,
             glm::vec3(1.0, 1.0, 1.0),
-            10.0,
+            5.0,
             glm::vec4(
                 (16.0 / map.width_f()) * 10_f32,
                 0.0,
@@ -219,78 +340,168 @@ fn main() {
                 16.0 / map.height_f(),
             ),
         );
-        sprite_renderer.draw_subtexture(&first_tile, glm::vec2(200.0, 200.0));
-        
+        profiler.end_scope("sprites");
+
+        profiler.begin_scope("tiles");
+        tile_renderer.begin_scene();
+        for x in 0..4 {
+            tile_renderer
+                .draw_tile(
+                    &tileset,
+                    first_tile + x,
+                    glm::vec2(100.0 + x as f32 * 24.0, 100.0),
+                    1.5,
+                    glm::vec4(1.0, 1.0, 1.0, 1.0),
+                )
+                .expect("error drawing tile");
+        }
+        tile_renderer
+            .end_scene(&tileset)
+            .expect("error drawing tiles");
+        profiler.end_scope("tiles");
+
+        profiler.begin_scope("sprites overlay");
         // melee
         sprite_renderer.draw_quad(
             &texture,
-            650.0_f32,
-            30_f32,
-            i,
+            325.0_f32,
+            15_f32,
+            angle,
             glm::vec3(1.0, 1.0, 1.0),
-            0.3,
-            glm::vec4(i / 100.0, 0.0, 1.0, 1.0),
+            0.15,
+            glm::vec4(angle / 100.0, 0.0, 1.0, 1.0),
         );
 
         // sprite
+        let frame = megaman.get_frame();
         sprite_renderer.draw_quad(
-            &megaman.get_frame(),
-            650.0_f32,
-            300_f32,
+            frame.texture(),
+            325.0_f32,
+            150_f32,
             0.0,
             glm::vec3(1.0, 1.0, 1.0),
-            10.0,
-            glm::vec4(0.0, 0.0, 1.0, 1.0),
+            megaman_scale.value(),
+            frame.get_normalized_rect(),
         );
 
         sprite_renderer.draw_quad(
             &texture,
-            650.0_f32,
-            30_f32,
-            i,
+            325.0_f32,
+            15_f32,
+            angle,
             glm::vec3(1.0, 1.0, 1.0),
-            0.3,
-            glm::vec4(i / 100.0, 0.0, 1.0, 1.0),
+            0.15,
+            glm::vec4(angle / 100.0, 0.0, 1.0, 1.0),
         );
 
         sprite_renderer.draw_rect(
-            glm::vec4(20.0, 300.0, 100.0, 100.0),
+            glm::vec4(10.0, 150.0, 50.0, 50.0),
             glm::vec3(0.4, 0.3, 0.7),
         );
         sprite_renderer.draw_circle(
-            glm::vec4(0.0, 00.0, 1600.0, 900.0),
+            glm::vec4(0.0, 00.0, VIRTUAL_WIDTH as f32, VIRTUAL_HEIGHT as f32),
             glm::vec3(0.4, 0.3, 0.7),
         );
+        profiler.end_scope("sprites overlay");
 
+        profiler.begin_scope("batch");
         batch_renderer.begin_scene();
 
         for i in 0..1023 {
-            batch_renderer.draw_quad(
-                &map,
-                glm::vec3(
-                    (i / 27 * separation as i32) as f32,
-                    ((i % 27) * separation as i32) as f32,
-                    1.0,
-                ),
-                glm::vec4(0.2 + (i % 2) as f32, 0.1, 0.1, 0.4),
-                23.0,
-                glm::vec4(0.0, 0.0, 0.0, 0.0),
-            );
+            batch_renderer
+                .draw_quad(
+                    &map,
+                    glm::vec3(
+                        (i / 27 * separation as i32) as f32,
+                        ((i % 27) * separation as i32) as f32,
+                        1.0,
+                    ),
+                    glm::vec4(0.2 + (i % 2) as f32, 0.1, 0.1, 0.4),
+                    23.0,
+                    glm::vec4(0.0, 0.0, 0.0, 0.0),
+                )
+                .expect("error drawing batch");
         }
-        batch_renderer.end_scene();
+        batch_renderer.end_scene().expect("error drawing batch");
+        profiler.end_scope("batch");
+
+        screen.present();
+
+        // mouse positions come in window points, which are fewer than pixels with high dpi
+        let mouse = event_pump.mouse_state();
+        let (window_width, _) = window.size();
+        let pixels_per_point = drawable_width as f32 / window_width as f32;
+        let mouse = glm::vec2(mouse.x() as f32, mouse.y() as f32) * pixels_per_point;
 
         let a = imgui::Window::new("Separation");
         a.build(&ui, || {
             let sl = imgui::Slider::new("asd", 0.0, 50.0);
             sl.build(&ui, &mut separation);
+
+            ui.text(format!("batch streaming: {:?}", batch_renderer.streaming_mode()));
+            ui.text(format!(
+                "batch upload: {:.3} ms, end_scene: {:.3} ms",
+                batch_renderer.average_upload_ms(),
+                batch_renderer.average_end_scene_ms()
+            ));
+            ui.text(format!(
+                "batch quads: {}",
+                batch_renderer.stats().quads_rendered
+            ));
+            ui.text(format!(
+                "frame limit: {:?}, ticks this frame: {}, dropped: {:.1} s",
+                game_loop.frame_limit(),
+                game_loop.ticks_this_frame(),
+                game_loop.dropped_time().as_secs_f32()
+            ));
+            ui.text(format!(
+                "time: {:.1} s, scale {}{}",
+                time.elapsed().as_secs_f32(),
+                time.time_scale(),
+                if time.is_paused() { ", paused" } else { "" }
+            ));
+            ui.text(format!(
+                "megaman: frame {}, steps: {}",
+                megaman.current_frame_index(),
+                megaman_steps
+            ));
+            let loading = assets.progress();
+            ui.text(format!(
+                "assets loading: {}/{} ({:.0}%), failed: {}",
+                loading.loaded,
+                loading.requested,
+                loading.fraction() * 100.0,
+                loading.failed
+            ));
+            ui.text(format!(
+                "virtual screen: {:?} x{:.2}, mouse: {:?}",
+                screen.scale_mode(),
+                screen.scale(),
+                screen.window_to_virtual(mouse).map(|p| (p.x, p.y))
+            ));
         });
 
         ui.show_demo_window(&mut true);
 
+        if show_profiler {
+            profiler.draw_ui(&ui);
+        }
+
+        // imgui draws through its own renderer and isn't counted
+        stats::end_frame();
+        if show_render_stats {
+            stats::draw_overlay(&ui, &stats::last_frame());
+        }
+
+        profiler.begin_scope("imgui");
         imgui_sdl2.prepare_render(&ui, &window);
         renderer.render(ui);
+        profiler.end_scope("imgui");
+        profiler.end_frame();
 
+        capture.capture_frame();
         window.gl_swap_window();
+        game_loop.end_frame();
 
         if quit {
             break 'running;
@@ -1,23 +1,48 @@
 
+use crate::render::backend::{ShaderStage, Uniform};
 use crate::render::shader::Shader;
 use crate::resources::{self};
 
 
 
-use std::ffi::{CString};
+use std::ffi::CStr;
 use std::iter::Iterator;
 
+#[macro_use]
+pub mod debug;
+
+pub mod animation_controller;
+pub mod aseprite;
+pub mod backend;
 pub mod buffer;
+pub mod capture;
+pub mod compute;
 pub mod data;
+pub mod framebuffer;
+pub mod image;
+pub mod mesh;
+pub mod profiler;
 pub mod renderer;
 mod shader;
+pub mod skeleton;
+pub mod sprite_sheet;
+pub mod stats;
 pub mod subtexture;
 pub mod texture;
+pub mod texture_array;
 pub mod animation;
+pub mod virtual_screen;
 //    pub mod line_segment_renderer;
 
-const EXTENSIONS: [(&str, gl::types::GLenum); 2] =
-    [(".vert", gl::VERTEX_SHADER), (".frag", gl::FRAGMENT_SHADER)];
+const EXTENSIONS: [(&str, ShaderStage); 2] = [
+    (".vert", ShaderStage::Vertex),
+    (".frag", ShaderStage::Fragment),
+];
+
+// fills the color buffer of whatever the backend draws to
+pub fn clear(color: glm::Vec4) {
+    backend::with(|backend| backend.clear(color));
+}
 
 pub struct GlProgram {
     id: gl::types::GLuint,
@@ -25,49 +50,13 @@ pub struct GlProgram {
 
 impl GlProgram {
     pub fn with_shaders(shaders: &[Shader]) -> Result<GlProgram, String> {
-        let program_id = unsafe { gl::CreateProgram() };
-
-        for shader in shaders {
-            unsafe {
-                gl::AttachShader(program_id, shader.id());
-            }
-        }
-
-        unsafe {
-            gl::LinkProgram(program_id);
-        }
-
-        let mut success: gl::types::GLint = 1;
-
-        unsafe {
-            gl::GetProgramiv(program_id, gl::LINK_STATUS, &mut success);
-        }
-
-        if success == 0 {
-            let mut len: gl::types::GLint = 0;
-            unsafe {
-                gl::GetProgramiv(program_id, gl::INFO_LOG_LENGTH, &mut len);
-            }
-
-            let error = create_whitespace_cstring_with_len(len as usize);
-
-            unsafe {
-                gl::GetProgramInfoLog(
-                    program_id,
-                    len,
-                    std::ptr::null_mut(),
-                    error.as_ptr() as *mut gl::types::GLchar,
-                );
-            }
-
-            return Err(error.to_string_lossy().into_owned());
-        }
+        GlProgram::link("", shaders)
+    }
 
-        for shader in shaders {
-            unsafe {
-                gl::DetachShader(program_id, shader.id());
-            }
-        }
+    // _name_ is the asset the shaders came from, backends use it to label or identify programs
+    fn link(name: &str, shaders: &[Shader]) -> Result<GlProgram, String> {
+        let shader_ids: Vec<gl::types::GLuint> = shaders.iter().map(Shader::id).collect();
+        let program_id = backend::with(|backend| backend.link_program(name, &shader_ids))?;
 
         Ok(GlProgram { id: program_id })
     }
@@ -76,12 +65,17 @@ impl GlProgram {
         self.id
     }
 
+    // names the program in GL debug messages and debuggers
+    pub fn set_label(&self, label: &str) {
+        backend::with(|backend| backend.set_label(debug::ObjectKind::Program, self.id, label));
+    }
+
     pub fn from_res(res: &resources::Resources, name: &str) -> Result<GlProgram, String> {
         // if file extension is glsl, assume both shaders in the same file
         if name.ends_with(".glsl") {
             println!("reading shaders from single file");
             let shaders = Shader::from_single_source(res, name)?;
-            return GlProgram::with_shaders(&shaders);
+            return GlProgram::link(name, &shaders);
         }
 
         let file_extensions = EXTENSIONS.map(|x| x.0);
@@ -91,79 +85,44 @@ impl GlProgram {
             .into_iter()
             .collect::<Result<Vec<Shader>, String>>()?;
 
-        GlProgram::with_shaders(&shaders)
+        GlProgram::link(name, &shaders)
     }
 
     pub fn set_used(&self) {
-        unsafe {
-            gl::UseProgram(self.id);
-        }
+        backend::with(|backend| backend.use_program(self.id));
+        stats::record_program_use(self.id);
     }
 
     pub fn set_float(&self, name: *const u8, value: f32) {
-        unsafe {
-            gl::Uniform1f(gl::GetUniformLocation(self.id, name as *const i8), value);
-        }
+        self.set_uniform(name, Uniform::Float(value));
     }
 
     pub fn set_integer(&self, name: *const u8, value: i32) {
-        unsafe {
-            gl::Uniform1i(gl::GetUniformLocation(self.id, name as *const i8), value);
-        }
+        self.set_uniform(name, Uniform::Int(value));
     }
 
     // todo: value by ref or copy?
     pub fn set_vector3f(&self, name: *const u8, value: glm::Vec3) {
-        unsafe {
-            gl::Uniform3f(
-                gl::GetUniformLocation(self.id, name as *const i8),
-                value.x,
-                value.y,
-                value.z,
-            );
-        }
+        self.set_uniform(name, Uniform::Vec3(value));
     }
 
     pub fn set_vector4f(&self, name: *const u8, value: glm::Vec4) {
-        unsafe {
-            gl::Uniform4f(
-                gl::GetUniformLocation(self.id, name as *const i8),
-                value.x,
-                value.y,
-                value.z,
-                value.w,
-            );
-        }
+        self.set_uniform(name, Uniform::Vec4(value));
     }
 
-    // todo: arguments here should be reviewed (value_ptr overhead? how does it relate to transpose arg?)
     pub fn set_mat4(&self, name: *const u8, matrix: glm::Mat4) {
-        unsafe {
-            gl::UniformMatrix4fv(
-                gl::GetUniformLocation(self.id, name as *const i8),
-                1,
-                gl::FALSE,
-                glm::value_ptr(&matrix).as_ptr(),
-            );
-        }
+        self.set_uniform(name, Uniform::Mat4(matrix));
+    }
+
+    // _name_ must be nul terminated, e.g. "projection\0".as_ptr()
+    fn set_uniform(&self, name: *const u8, value: Uniform) {
+        let name = unsafe { CStr::from_ptr(name as *const std::os::raw::c_char) };
+        backend::with(|backend| backend.set_uniform(self.id, name, value));
     }
 }
 
 impl Drop for GlProgram {
     fn drop(&mut self) {
-        unsafe {
-            gl::DeleteProgram(self.id);
-        }
+        backend::with(|backend| backend.delete_program(self.id));
     }
 }
-
-fn create_whitespace_cstring_with_len(len: usize) -> CString {
-    // allocate buffer of correct size
-    let mut buffer: Vec<u8> = Vec::with_capacity(len + 1);
-
-    // fill it with len spaces
-    buffer.extend([b' '].iter().cycle().take(len));
-
-    // convert buffer to CString
-    unsafe { CString::from_vec_unchecked(buffer) }
-}
@@ -1,33 +1,317 @@
-use std::{time::{Duration, Instant}};
+use std::ops::Range;
+use std::time::Duration;
 
-use super::{texture::Texture};
+use super::subtexture::Subtexture;
+use super::texture::Texture;
+use crate::assets::Handle;
+use crate::time::Time;
 
-pub struct Animation<'a>
-{
-    frames: &'a [Texture],
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PlaybackMode {
+    // stops on the last frame, see Animation::is_finished
+    Once,
+    Loop,
+    // forward then backward, without showing the first and last frames twice in a row
+    PingPong,
+    // like PingPong, starting from the last frame going backward
+    PingPongReverse,
+    // loops from the last frame to the first
+    Reverse,
+}
+
+#[derive(Clone)]
+pub struct AnimationFrame {
+    pub subtexture: Subtexture,
+    pub duration: Duration,
+}
+
+// frames are regions of one or more textures (usually a single atlas), each shown for its own
+// duration. see SpriteSheet::animation for animations made from exported sprite sheet tags
+pub struct Animation {
+    frames: Vec<AnimationFrame>,
+    // (frame, name), see add_event
+    events: Vec<(usize, String)>,
+    pending_events: Vec<String>,
+    mode: PlaybackMode,
+    speed: f32,
+    paused: bool,
+    finished: bool,
+    // ping pong is on its way back to the frame it started from
+    backwards: bool,
+    // times the animation went through all its frames since the last reset
+    completed_cycles: u32,
     current_frame: usize,
-    frame_duration: Duration,
-    last_frame_time: Instant,
+    // whether the events of the current frame have been queued
+    entered_frame: bool,
+    // time spent on the current frame
+    elapsed: Duration,
 }
 
-impl<'a> Animation<'a>
-{
-    pub fn new(frames: &'a [Texture], frame_duration: Duration) -> Self {
-        Animation {
+impl Animation {
+    pub fn new(frames: Vec<AnimationFrame>) -> Result<Animation, String> {
+        if frames.is_empty() {
+            return Err("animations need at least one frame".to_string());
+        }
+
+        Ok(Animation {
             frames,
+            events: Vec::new(),
+            pending_events: Vec::new(),
+            mode: PlaybackMode::Loop,
+            speed: 1.0,
+            paused: false,
+            finished: false,
+            backwards: false,
+            completed_cycles: 0,
             current_frame: 0,
-            frame_duration,
-            last_frame_time: Instant::now(),
+            entered_frame: false,
+            elapsed: Duration::ZERO,
+        })
+    }
+
+    // every texture is a frame of its own
+    pub fn from_textures(
+        textures: &[Handle<Texture>],
+        frame_duration: Duration,
+    ) -> Result<Animation, String> {
+        let frames = textures
+            .iter()
+            .map(|texture| AnimationFrame {
+                subtexture: Subtexture::from_texture(
+                    texture,
+                    glm::vec2(0.0, 0.0),
+                    glm::vec2(1.0, 1.0),
+                ),
+                duration: frame_duration,
+            })
+            .collect();
+
+        Animation::new(frames)
+    }
+
+    // frames laid out left to right in _columns_ of _row_, on a grid of _tile_dimensions_ cells
+    pub fn from_grid(
+        texture: &Handle<Texture>,
+        tile_dimensions: glm::Vec2,
+        row: u16,
+        columns: Range<u16>,
+        frame_duration: Duration,
+    ) -> Result<Animation, String> {
+        let tiles_per_row = (texture.width_f() / tile_dimensions.x) as u16;
+        let rows = (texture.height_f() / tile_dimensions.y) as u16;
+        if row >= rows || columns.end > tiles_per_row {
+            return Err(format!(
+                "row {}, columns {:?} are outside of the {}x{} grid",
+                row, columns, tiles_per_row, rows
+            ));
+        }
+
+        let frames = columns
+            .map(|column| AnimationFrame {
+                subtexture: Subtexture::from_tiles(texture, column, row, tile_dimensions),
+                duration: frame_duration,
+            })
+            .collect();
+
+        Animation::new(frames)
+    }
+
+    pub fn frames(&self) -> &[AnimationFrame] {
+        &self.frames
+    }
+
+    pub fn set_frame_duration(&mut self, frame: usize, duration: Duration) {
+        if let Some(frame) = self.frames.get_mut(frame) {
+            frame.duration = duration;
         }
     }
 
-    pub fn get_frame(&mut self) -> &Texture {
-        let now = Instant::now();
-        if now.duration_since(self.last_frame_time) >= self.frame_duration {                
-            self.current_frame = (self.current_frame + 1) % self.frames.len();
-            self.last_frame_time = now;
+    // time it takes to go through every frame once
+    pub fn duration(&self) -> Duration {
+        self.frames.iter().map(|frame| frame.duration).sum()
+    }
+
+    pub fn playback_mode(&self) -> PlaybackMode {
+        self.mode
+    }
+
+    // takes effect from the current frame on; call reset to start over in the new mode
+    pub fn set_playback_mode(&mut self, mode: PlaybackMode) {
+        self.mode = mode;
+        self.backwards = false;
+    }
+
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+
+    // scales the time passed to update, 2.0 plays twice as fast. negative speeds are treated
+    // as 0, use PlaybackMode::Reverse to play backwards
+    pub fn set_speed(&mut self, speed: f32) {
+        self.speed = speed.max(0.0);
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    // only PlaybackMode::Once animations finish, once they have shown the last frame for its
+    // whole duration
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    // a cycle is done once the last frame shown in it has been on screen for its duration: the
+    // last frame for Loop, Once and PingPongReverse, the first for Reverse and PingPong
+    pub fn completed_cycles(&self) -> u32 {
+        self.completed_cycles
+    }
+
+    // queues _name_ every time the animation gets to _frame_
+    pub fn add_event(&mut self, frame: usize, name: &str) {
+        self.events.push((frame, name.to_string()));
+    }
+
+    // events of the frames reached since the last call, in order
+    pub fn drain_events(&mut self) -> impl Iterator<Item = String> + '_ {
+        self.pending_events.drain(..)
+    }
+
+    pub fn current_frame_index(&self) -> usize {
+        self.current_frame
+    }
+
+    // jumps to the start of _frame_, clamped to the last one
+    pub fn seek(&mut self, frame: usize) {
+        self.current_frame = frame.min(self.frames.len() - 1);
+        self.elapsed = Duration::ZERO;
+        self.finished = false;
+        self.entered_frame = false;
+    }
+
+    // goes back to the first frame the mode starts on. doesn't resume a paused animation
+    pub fn reset(&mut self) {
+        let first = match self.mode {
+            PlaybackMode::Reverse | PlaybackMode::PingPongReverse => self.frames.len() - 1,
+            _ => 0,
+        };
+
+        self.seek(first);
+        self.backwards = false;
+        self.completed_cycles = 0;
+        self.pending_events.clear();
+    }
+
+    // advances the animation by the time's delta, so it follows its scale and stops while it is
+    // paused
+    pub fn update(&mut self, time: &Time) {
+        self.advance(time.delta());
+    }
+
+    // advances the animation by _dt_ scaled by the speed, skipping frames when it spans more
+    // than one. events of every frame reached along the way are queued
+    pub fn advance(&mut self, dt: Duration) {
+        if self.paused || self.finished {
+            return;
         }
 
-        &self.frames[self.current_frame]
+        if !self.entered_frame {
+            self.enter_frame();
+        }
+
+        // there would be no way out of the loop below
+        if self.duration().is_zero() {
+            return;
+        }
+
+        self.elapsed += dt.mul_f32(self.speed);
+        while self.elapsed >= self.frames[self.current_frame].duration {
+            match self.next_frame() {
+                Some(next) => {
+                    self.elapsed -= self.frames[self.current_frame].duration;
+                    self.current_frame = next;
+                    self.enter_frame();
+                }
+                None => {
+                    self.elapsed = self.frames[self.current_frame].duration;
+                    self.finished = true;
+                    self.completed_cycles += 1;
+                    break;
+                }
+            }
+        }
     }
-}
\ No newline at end of file
+
+    pub fn get_frame(&self) -> &Subtexture {
+        &self.frames[self.current_frame].subtexture
+    }
+
+    fn enter_frame(&mut self) {
+        let current_frame = self.current_frame;
+        let events = self
+            .events
+            .iter()
+            .filter(|(frame, _)| *frame == current_frame)
+            .map(|(_, name)| name.clone());
+
+        self.pending_events.extend(events);
+        self.entered_frame = true;
+    }
+
+    // None once a PlaybackMode::Once animation is over
+    fn next_frame(&mut self) -> Option<usize> {
+        let last = self.frames.len() - 1;
+        let current = self.current_frame;
+
+        match self.mode {
+            PlaybackMode::Once if current < last => Some(current + 1),
+            PlaybackMode::Once => None,
+            PlaybackMode::Loop if current < last => Some(current + 1),
+            PlaybackMode::Loop => {
+                self.completed_cycles += 1;
+                Some(0)
+            }
+            PlaybackMode::Reverse if current > 0 => Some(current - 1),
+            PlaybackMode::Reverse => {
+                self.completed_cycles += 1;
+                Some(last)
+            }
+            PlaybackMode::PingPong => {
+                if self.backwards && current == 0 {
+                    self.backwards = false;
+                    self.completed_cycles += 1;
+                } else if !self.backwards && current == last {
+                    self.backwards = true;
+                }
+
+                if self.backwards {
+                    Some(current.saturating_sub(1))
+                } else {
+                    Some((current + 1).min(last))
+                }
+            }
+            PlaybackMode::PingPongReverse => {
+                if self.backwards && current == last {
+                    self.backwards = false;
+                    self.completed_cycles += 1;
+                } else if !self.backwards && current == 0 {
+                    self.backwards = true;
+                }
+
+                if self.backwards {
+                    Some((current + 1).min(last))
+                } else {
+                    Some(current.saturating_sub(1))
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,397 @@
+use std::collections::HashMap;
+
+use serde::Deserialize;
+
+use super::animation::{Animation, PlaybackMode};
+use super::sprite_sheet::SpriteSheet;
+use super::subtexture::Subtexture;
+use crate::time::Time;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Parameter {
+    Bool(bool),
+    Float(f32),
+    // set by the game, cleared by the first transition that uses it
+    Trigger(bool),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Condition {
+    Bool(String, bool),
+    Greater(String, f32),
+    Less(String, f32),
+    Trigger(String),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Transition {
+    // None goes from any state other than _to_
+    pub from: Option<String>,
+    pub to: String,
+    // all of them have to hold
+    pub conditions: Vec<Condition>,
+    // waits until the current clip has gone through all its frames at least once
+    pub wait_for_end: bool,
+}
+
+// picks which of a character's clips plays (idle, run, jump...) from parameters the game sets
+// every update:
+//
+//  controller.set_float("speed", velocity.x.abs());
+//  controller.update(&time);
+//  let frame = controller.get_frame();
+//
+// transitions are checked in the order they were added, after the current clip is updated; the
+// first one that can be taken restarts its target clip
+pub struct AnimationController {
+    clips: HashMap<String, Animation>,
+    parameters: HashMap<String, Parameter>,
+    transitions: Vec<Transition>,
+    current_state: String,
+    pending_events: Vec<String>,
+}
+
+impl AnimationController {
+    pub fn new(initial_state: &str, initial_clip: Animation) -> AnimationController {
+        let mut clips = HashMap::new();
+        clips.insert(initial_state.to_string(), initial_clip);
+
+        AnimationController {
+            clips,
+            parameters: HashMap::new(),
+            transitions: Vec::new(),
+            current_state: initial_state.to_string(),
+            pending_events: Vec::new(),
+        }
+    }
+
+    pub fn add_clip(&mut self, state: &str, clip: Animation) {
+        self.clips.insert(state.to_string(), clip);
+    }
+
+    pub fn add_transition(&mut self, transition: Transition) -> Result<(), String> {
+        for state in transition.from.iter().chain(Some(&transition.to)) {
+            if !self.clips.contains_key(state) {
+                return Err(format!("transition uses unknown state {}", state));
+            }
+        }
+
+        self.transitions.push(transition);
+        Ok(())
+    }
+
+    pub fn current_state(&self) -> &str {
+        &self.current_state
+    }
+
+    pub fn current_clip(&self) -> &Animation {
+        &self.clips[&self.current_state]
+    }
+
+    pub fn current_clip_mut(&mut self) -> &mut Animation {
+        self.clips.get_mut(&self.current_state).unwrap()
+    }
+
+    pub fn parameter(&self, name: &str) -> Option<Parameter> {
+        self.parameters.get(name).copied()
+    }
+
+    pub fn set_bool(&mut self, name: &str, value: bool) {
+        self.parameters
+            .insert(name.to_string(), Parameter::Bool(value));
+    }
+
+    pub fn set_float(&mut self, name: &str, value: f32) {
+        self.parameters
+            .insert(name.to_string(), Parameter::Float(value));
+    }
+
+    pub fn set_trigger(&mut self, name: &str) {
+        self.parameters
+            .insert(name.to_string(), Parameter::Trigger(true));
+    }
+
+    pub fn reset_trigger(&mut self, name: &str) {
+        self.parameters
+            .insert(name.to_string(), Parameter::Trigger(false));
+    }
+
+    // switches to _state_ right away, ignoring transitions
+    pub fn play(&mut self, state: &str) -> Result<(), String> {
+        let clip = self
+            .clips
+            .get_mut(state)
+            .ok_or_else(|| format!("unknown animation state {}", state))?;
+
+        clip.reset();
+        self.current_state = state.to_string();
+        Ok(())
+    }
+
+    pub fn update(&mut self, time: &Time) {
+        let clip = self.clips.get_mut(&self.current_state).unwrap();
+        clip.update(time);
+        self.pending_events.extend(clip.drain_events());
+
+        let transition = self
+            .transitions
+            .iter()
+            .position(|transition| self.can_take(transition));
+        if let Some(transition) = transition {
+            let transition = self.transitions[transition].clone();
+            for condition in &transition.conditions {
+                if let Condition::Trigger(name) = condition {
+                    self.reset_trigger(name);
+                }
+            }
+
+            // the state is known to exist, add_transition checked it
+            let _ = self.play(&transition.to);
+        }
+    }
+
+    // events of the frames reached by any clip since the last call, see Animation::add_event
+    pub fn drain_events(&mut self) -> impl Iterator<Item = String> + '_ {
+        self.pending_events.drain(..)
+    }
+
+    pub fn get_frame(&self) -> &Subtexture {
+        self.current_clip().get_frame()
+    }
+
+    fn can_take(&self, transition: &Transition) -> bool {
+        let from_current = match &transition.from {
+            Some(from) => *from == self.current_state,
+            None => transition.to != self.current_state,
+        };
+        if !from_current {
+            return false;
+        }
+
+        if transition.wait_for_end && self.current_clip().completed_cycles() == 0 {
+            return false;
+        }
+
+        transition
+            .conditions
+            .iter()
+            .all(|condition| self.holds(condition))
+    }
+
+    fn holds(&self, condition: &Condition) -> bool {
+        match (condition, self.parameters.get(condition.parameter())) {
+            (Condition::Bool(_, expected), Some(Parameter::Bool(value))) => value == expected,
+            (Condition::Greater(_, threshold), Some(Parameter::Float(value))) => value > threshold,
+            (Condition::Less(_, threshold), Some(Parameter::Float(value))) => value < threshold,
+            (Condition::Trigger(_), Some(Parameter::Trigger(set))) => *set,
+            _ => false,
+        }
+    }
+}
+
+impl Condition {
+    pub fn parameter(&self) -> &str {
+        match self {
+            Condition::Bool(name, _)
+            | Condition::Greater(name, _)
+            | Condition::Less(name, _)
+            | Condition::Trigger(name) => name,
+        }
+    }
+}
+
+// json description of a controller whose clips are tags of a sprite sheet:
+//
+//  {
+//      "initial": "idle",
+//      "parameters": [
+//          { "name": "speed", "type": "float" },
+//          { "name": "grounded", "type": "bool", "default": true },
+//          { "name": "attack", "type": "trigger" }
+//      ],
+//      "states": [
+//          { "name": "idle", "tag": "idle" },
+//          { "name": "run", "tag": "run" },
+//          { "name": "attack", "tag": "attack", "mode": "once", "speed": 1.5 }
+//      ],
+//      "transitions": [
+//          { "from": "idle", "to": "run", "conditions": [{ "parameter": "speed", "greater": 0.1 }] },
+//          { "from": "run", "to": "idle", "conditions": [{ "parameter": "speed", "less": 0.1 }] },
+//          { "to": "attack", "conditions": [{ "trigger": "attack" }] },
+//          { "from": "attack", "to": "idle", "wait_for_end": true }
+//      ]
+//  }
+//
+// states use the tag's direction unless they set a "mode" (once, loop, pingpong,
+// pingpong_reverse or reverse)
+#[derive(Deserialize)]
+pub struct AnimationControllerDefinition {
+    initial: String,
+    #[serde(default)]
+    parameters: Vec<ParameterDefinition>,
+    states: Vec<StateDefinition>,
+    #[serde(default)]
+    transitions: Vec<TransitionDefinition>,
+}
+
+#[derive(Deserialize)]
+struct ParameterDefinition {
+    name: String,
+    #[serde(rename = "type")]
+    kind: String,
+    default: Option<serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+struct StateDefinition {
+    name: String,
+    tag: String,
+    mode: Option<String>,
+    speed: Option<f32>,
+}
+
+#[derive(Deserialize)]
+struct TransitionDefinition {
+    from: Option<String>,
+    to: String,
+    #[serde(default)]
+    conditions: Vec<ConditionDefinition>,
+    #[serde(default)]
+    wait_for_end: bool,
+}
+
+#[derive(Deserialize)]
+struct ConditionDefinition {
+    parameter: Option<String>,
+    equals: Option<bool>,
+    greater: Option<f32>,
+    less: Option<f32>,
+    trigger: Option<String>,
+}
+
+impl AnimationControllerDefinition {
+    pub fn from_json(json: &str) -> Result<AnimationControllerDefinition, String> {
+        serde_json::from_str(json).map_err(|e| format!("invalid animation controller: {}", e))
+    }
+
+    pub fn build(&self, sheet: &SpriteSheet) -> Result<AnimationController, String> {
+        let mut clips = HashMap::new();
+        for state in &self.states {
+            let mut clip = sheet.animation(&state.tag)?;
+            if let Some(mode) = &state.mode {
+                clip.set_playback_mode(parse_playback_mode(mode)?);
+                clip.reset();
+            }
+            if let Some(speed) = state.speed {
+                clip.set_speed(speed);
+            }
+
+            if clips.insert(state.name.clone(), clip).is_some() {
+                return Err(format!("state {} is defined twice", state.name));
+            }
+        }
+
+        let initial_clip = clips
+            .remove(&self.initial)
+            .ok_or_else(|| format!("initial state {} is not defined", self.initial))?;
+        let mut controller = AnimationController::new(&self.initial, initial_clip);
+        for (state, clip) in clips {
+            controller.add_clip(&state, clip);
+        }
+
+        for parameter in &self.parameters {
+            let default = parameter.default.as_ref();
+            let value = match (parameter.kind.as_str(), default) {
+                ("bool", None) => Parameter::Bool(false),
+                ("bool", Some(serde_json::Value::Bool(value))) => Parameter::Bool(*value),
+                ("float", None) => Parameter::Float(0.0),
+                ("float", Some(serde_json::Value::Number(value))) => {
+                    Parameter::Float(value.as_f64().unwrap_or(0.0) as f32)
+                }
+                ("trigger", None) => Parameter::Trigger(false),
+                _ => {
+                    return Err(format!(
+                        "parameter {} has an invalid type or default",
+                        parameter.name
+                    ))
+                }
+            };
+            controller.parameters.insert(parameter.name.clone(), value);
+        }
+
+        for transition in &self.transitions {
+            let conditions = transition
+                .conditions
+                .iter()
+                .map(|condition| self.condition(condition))
+                .collect::<Result<_, _>>()?;
+
+            controller.add_transition(Transition {
+                from: transition.from.clone(),
+                to: transition.to.clone(),
+                conditions,
+                wait_for_end: transition.wait_for_end,
+            })?;
+        }
+
+        Ok(controller)
+    }
+
+    fn condition(&self, condition: &ConditionDefinition) -> Result<Condition, String> {
+        let condition = match condition {
+            ConditionDefinition {
+                trigger: Some(name),
+                parameter: None,
+                ..
+            } => Condition::Trigger(name.clone()),
+            ConditionDefinition {
+                parameter: Some(name),
+                equals: Some(value),
+                ..
+            } => Condition::Bool(name.clone(), *value),
+            ConditionDefinition {
+                parameter: Some(name),
+                greater: Some(value),
+                ..
+            } => Condition::Greater(name.clone(), *value),
+            ConditionDefinition {
+                parameter: Some(name),
+                less: Some(value),
+                ..
+            } => Condition::Less(name.clone(), *value),
+            _ => return Err("conditions need a trigger, or a parameter and a comparison".into()),
+        };
+
+        // catches typos in parameter names, which would otherwise never let the transition happen
+        let kind = self
+            .parameters
+            .iter()
+            .find(|parameter| parameter.name == condition.parameter())
+            .map(|parameter| parameter.kind.as_str());
+        let expected_kind = match condition {
+            Condition::Bool(..) => "bool",
+            Condition::Greater(..) | Condition::Less(..) => "float",
+            Condition::Trigger(..) => "trigger",
+        };
+        if kind != Some(expected_kind) {
+            return Err(format!(
+                "condition needs a {} parameter named {}",
+                expected_kind,
+                condition.parameter()
+            ));
+        }
+
+        Ok(condition)
+    }
+}
+
+fn parse_playback_mode(mode: &str) -> Result<PlaybackMode, String> {
+    match mode {
+        "once" => Ok(PlaybackMode::Once),
+        "loop" => Ok(PlaybackMode::Loop),
+        "pingpong" => Ok(PlaybackMode::PingPong),
+        "pingpong_reverse" => Ok(PlaybackMode::PingPongReverse),
+        "reverse" => Ok(PlaybackMode::Reverse),
+        _ => Err(format!("unknown playback mode {}", mode)),
+    }
+}
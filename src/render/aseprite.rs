@@ -0,0 +1,496 @@
+use std::io::Read;
+use std::time::Duration;
+
+use flate2::read::ZlibDecoder;
+
+use super::animation::PlaybackMode;
+use super::image::{Image, ImagePixels};
+use super::sprite_sheet::{SheetFrame, SheetTag, SpriteSheet, SpriteSheetLayout};
+use super::texture::{PixelRect, Texture, TextureDescriptor};
+use crate::assets::Handle;
+
+// https://github.com/aseprite/aseprite/blob/main/docs/ase-file-specs.md
+const FILE_MAGIC: u16 = 0xA5E0;
+const FRAME_MAGIC: u16 = 0xF1FA;
+const HEADER_SIZE: usize = 128;
+const FRAME_HEADER_SIZE: usize = 16;
+const CHUNK_HEADER_SIZE: usize = 6;
+
+const OLD_PALETTE_CHUNK: u16 = 0x0004;
+const LAYER_CHUNK: u16 = 0x2004;
+const CEL_CHUNK: u16 = 0x2005;
+const TAGS_CHUNK: u16 = 0x2018;
+const PALETTE_CHUNK: u16 = 0x2019;
+
+const LAYER_VISIBLE: u16 = 1;
+const LAYER_TYPE_TILEMAP: u16 = 2;
+const HEADER_LAYER_OPACITY_VALID: u32 = 1;
+
+const CEL_RAW: u16 = 0;
+const CEL_LINKED: u16 = 1;
+const CEL_COMPRESSED: u16 = 2;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum ColorDepth {
+    Rgba,
+    Grayscale,
+    Indexed,
+}
+
+impl ColorDepth {
+    fn bytes_per_pixel(self) -> usize {
+        match self {
+            ColorDepth::Rgba => 4,
+            ColorDepth::Grayscale => 2,
+            ColorDepth::Indexed => 1,
+        }
+    }
+}
+
+struct Layer {
+    // hidden if the layer or any group it's in is hidden
+    visible: bool,
+    opacity: u8,
+}
+
+#[derive(Clone)]
+struct Cel {
+    x: i32,
+    y: i32,
+    width: usize,
+    height: usize,
+    opacity: u8,
+    // in the file's color depth, converted once the palette is known
+    pixels: Vec<u8>,
+}
+
+// a decoded .aseprite/.ase file with the visible layers of every frame flattened to RGBA.
+// layers are composited with normal blending whatever their blend mode; tilemap layers are
+// not supported
+pub struct AsepriteFile {
+    pub width: usize,
+    pub height: usize,
+    // RGBA8, top row first
+    pub frames: Vec<Image>,
+    pub durations: Vec<Duration>,
+    pub tags: Vec<SheetTag>,
+}
+
+impl AsepriteFile {
+    pub fn parse(bytes: &[u8]) -> Result<AsepriteFile, String> {
+        let mut header = Reader::new(bytes);
+        let _file_size = header.dword()?;
+        if header.word()? != FILE_MAGIC {
+            return Err("not an aseprite file".to_string());
+        }
+        let frame_count = header.word()? as usize;
+        let width = header.word()? as usize;
+        let height = header.word()? as usize;
+        let depth = match header.word()? {
+            32 => ColorDepth::Rgba,
+            16 => ColorDepth::Grayscale,
+            8 => ColorDepth::Indexed,
+            depth => return Err(format!("unsupported color depth {}", depth)),
+        };
+        let flags = header.dword()?;
+        // speed (deprecated) and two reserved dwords
+        header.skip(10)?;
+        let transparent_index = header.byte()?;
+
+        let mut layers: Vec<Layer> = Vec::new();
+        // visibility of the groups enclosing the next layer, by child level
+        let mut group_visible: Vec<bool> = Vec::new();
+        let mut palette = vec![[0u8; 4]; 256];
+        let mut durations = Vec::with_capacity(frame_count);
+        let mut tags = Vec::new();
+        // per frame, the cel of each layer
+        let mut cels: Vec<Vec<Option<Cel>>> = Vec::with_capacity(frame_count);
+
+        let mut frame_start = HEADER_SIZE;
+        for frame in 0..frame_count {
+            let mut frame_header = Reader::new(bytes.get(frame_start..).unwrap_or_default());
+            let frame_size = frame_header.dword()? as usize;
+            if frame_header.word()? != FRAME_MAGIC {
+                return Err(format!("frame {} is corrupt", frame));
+            }
+            let old_chunk_count = frame_header.word()? as usize;
+            durations.push(Duration::from_millis(frame_header.word()? as u64));
+            frame_header.skip(2)?;
+            let chunk_count = match frame_header.dword()? as usize {
+                0 => old_chunk_count,
+                count => count,
+            };
+
+            let frame_bytes = bytes
+                .get(frame_start..frame_start + frame_size)
+                .ok_or_else(|| format!("frame {} is truncated", frame))?;
+            let mut frame_cels = Vec::new();
+            let mut chunk_start = FRAME_HEADER_SIZE;
+            for _ in 0..chunk_count {
+                let mut chunk_header =
+                    Reader::new(frame_bytes.get(chunk_start..).unwrap_or_default());
+                let chunk_size = chunk_header.dword()? as usize;
+                let chunk_type = chunk_header.word()?;
+                let chunk = frame_bytes
+                    .get(chunk_start + CHUNK_HEADER_SIZE..chunk_start + chunk_size)
+                    .ok_or_else(|| format!("chunk in frame {} is truncated", frame))?;
+                let mut chunk = Reader::new(chunk);
+
+                match chunk_type {
+                    LAYER_CHUNK => {
+                        let layer_flags = chunk.word()?;
+                        let layer_type = chunk.word()?;
+                        let child_level = chunk.word()? as usize;
+                        // default size and blend mode
+                        chunk.skip(6)?;
+                        let opacity = chunk.byte()?;
+                        if layer_type == LAYER_TYPE_TILEMAP {
+                            return Err("tilemap layers are not supported".to_string());
+                        }
+
+                        group_visible.truncate(child_level);
+                        let parent_visible = group_visible.iter().all(|visible| *visible);
+                        let visible = parent_visible && layer_flags & LAYER_VISIBLE != 0;
+                        group_visible.push(layer_flags & LAYER_VISIBLE != 0);
+
+                        layers.push(Layer {
+                            visible,
+                            opacity: if flags & HEADER_LAYER_OPACITY_VALID != 0 {
+                                opacity
+                            } else {
+                                255
+                            },
+                        });
+                    }
+                    CEL_CHUNK => {
+                        let layer = chunk.word()? as usize;
+                        let x = chunk.short()? as i32;
+                        let y = chunk.short()? as i32;
+                        let opacity = chunk.byte()?;
+                        let cel_type = chunk.word()?;
+                        // z-index and reserved
+                        chunk.skip(7)?;
+
+                        let cel = match cel_type {
+                            CEL_RAW | CEL_COMPRESSED => {
+                                let width = chunk.word()? as usize;
+                                let height = chunk.word()? as usize;
+                                let size = width * height * depth.bytes_per_pixel();
+                                let pixels = if cel_type == CEL_RAW {
+                                    chunk.bytes(size)?.to_vec()
+                                } else {
+                                    inflate(chunk.rest(), size)?
+                                };
+
+                                Cel {
+                                    x,
+                                    y,
+                                    width,
+                                    height,
+                                    opacity,
+                                    pixels,
+                                }
+                            }
+                            CEL_LINKED => {
+                                let linked_frame = chunk.word()? as usize;
+                                let linked = cels
+                                    .get(linked_frame)
+                                    .and_then(|cels: &Vec<Option<Cel>>| cels.get(layer))
+                                    .and_then(|cel| cel.as_ref())
+                                    .ok_or_else(|| {
+                                        format!("frame {} links to a missing cel", frame)
+                                    })?;
+
+                                Cel {
+                                    x,
+                                    y,
+                                    opacity,
+                                    ..linked.clone()
+                                }
+                            }
+                            _ => return Err("tilemap cels are not supported".to_string()),
+                        };
+
+                        if frame_cels.len() <= layer {
+                            frame_cels.resize(layer + 1, None);
+                        }
+                        frame_cels[layer] = Some(cel);
+                    }
+                    TAGS_CHUNK => {
+                        let count = chunk.word()?;
+                        chunk.skip(8)?;
+                        for _ in 0..count {
+                            let from = chunk.word()? as usize;
+                            let to = chunk.word()? as usize;
+                            let mode = match chunk.byte()? {
+                                1 => PlaybackMode::Reverse,
+                                2 => PlaybackMode::PingPong,
+                                3 => PlaybackMode::PingPongReverse,
+                                _ => PlaybackMode::Loop,
+                            };
+                            // repeat, reserved, deprecated color and extra byte
+                            chunk.skip(12)?;
+                            let name = chunk.string()?;
+                            if from > to || to >= frame_count {
+                                return Err(format!("tag {} is out of range", name));
+                            }
+
+                            tags.push(SheetTag {
+                                name,
+                                frames: from..=to,
+                                mode,
+                            });
+                        }
+                    }
+                    PALETTE_CHUNK => {
+                        let size = chunk.dword()? as usize;
+                        let first = chunk.dword()? as usize;
+                        let last = chunk.dword()? as usize;
+                        chunk.skip(8)?;
+                        if palette.len() < size {
+                            palette.resize(size, [0; 4]);
+                        }
+                        for entry in first..=last {
+                            let entry_flags = chunk.word()?;
+                            let color = chunk.bytes(4)?;
+                            if let Some(slot) = palette.get_mut(entry) {
+                                slot.copy_from_slice(color);
+                            }
+                            if entry_flags & 1 != 0 {
+                                chunk.string()?;
+                            }
+                        }
+                    }
+                    // only written by old versions, newer ones also write the chunk above
+                    OLD_PALETTE_CHUNK => {
+                        let packets = chunk.word()?;
+                        let mut entry = 0;
+                        for _ in 0..packets {
+                            entry += chunk.byte()? as usize;
+                            let count = match chunk.byte()? {
+                                0 => 256,
+                                count => count as usize,
+                            };
+                            for _ in 0..count {
+                                let color = chunk.bytes(3)?;
+                                if let Some(slot) = palette.get_mut(entry) {
+                                    *slot = [color[0], color[1], color[2], 255];
+                                }
+                                entry += 1;
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+
+                chunk_start += chunk_size;
+            }
+
+            cels.push(frame_cels);
+            frame_start += frame_size;
+        }
+
+        let frames = cels
+            .iter()
+            .map(|frame_cels| {
+                let mut pixels = vec![0u8; width * height * 4];
+                for (layer, cel) in layers.iter().zip(frame_cels) {
+                    if let (true, Some(cel)) = (layer.visible, cel) {
+                        let opacity = cel.opacity as u32 * layer.opacity as u32 / 255;
+                        let to_rgba = |pixel: &[u8]| match depth {
+                            ColorDepth::Rgba => [pixel[0], pixel[1], pixel[2], pixel[3]],
+                            ColorDepth::Grayscale => [pixel[0], pixel[0], pixel[0], pixel[1]],
+                            ColorDepth::Indexed if pixel[0] == transparent_index => [0; 4],
+                            ColorDepth::Indexed => palette[pixel[0] as usize],
+                        };
+                        draw_cel(&mut pixels, width, height, cel, depth, opacity, to_rgba);
+                    }
+                }
+
+                Image {
+                    width,
+                    height,
+                    channels: 4,
+                    pixels: ImagePixels::U8(pixels),
+                }
+            })
+            .collect();
+
+        Ok(AsepriteFile {
+            width,
+            height,
+            frames,
+            durations,
+            tags,
+        })
+    }
+
+    // packs the frames on a grid as square as possible in one atlas texture
+    pub fn to_sprite_sheet(
+        &self,
+        name: &str,
+        descriptor: &TextureDescriptor,
+    ) -> Result<SpriteSheet, String> {
+        let columns = (self.frames.len() as f32).sqrt().ceil().max(1.0) as usize;
+        let rows = self.frames.len().div_ceil(columns);
+        let atlas_width = columns * self.width;
+        let atlas_height = rows.max(1) * self.height;
+        let mut atlas = vec![0u8; atlas_width * atlas_height * 4];
+
+        let mut frames = Vec::with_capacity(self.frames.len());
+        for (i, (image, duration)) in self.frames.iter().zip(&self.durations).enumerate() {
+            let rect = PixelRect {
+                x: (i % columns) * self.width,
+                y: (i / columns) * self.height,
+                width: self.width,
+                height: self.height,
+            };
+
+            if let ImagePixels::U8(pixels) = &image.pixels {
+                let row_len = self.width * 4;
+                for row in 0..self.height {
+                    let start = ((rect.y + row) * atlas_width + rect.x) * 4;
+                    atlas[start..start + row_len]
+                        .copy_from_slice(&pixels[row * row_len..(row + 1) * row_len]);
+                }
+            }
+
+            frames.push(SheetFrame {
+                rect,
+                duration: *duration,
+            });
+        }
+
+        let image = Image {
+            width: atlas_width,
+            height: atlas_height,
+            channels: 4,
+            pixels: ImagePixels::U8(atlas),
+        };
+        let texture = Texture::from_image(&image, descriptor)?;
+        texture.set_label(name);
+
+        Ok(SpriteSheet::new(
+            Handle::new(texture, name),
+            SpriteSheetLayout {
+                image: name.to_string(),
+                frames,
+                tags: self.tags.clone(),
+            },
+        ))
+    }
+}
+
+fn inflate(compressed: &[u8], size: usize) -> Result<Vec<u8>, String> {
+    // one byte past the expected size is enough to tell the cel is too big, so a small
+    // stream that inflates to gigabytes is never decompressed in full
+    let mut pixels = Vec::with_capacity(size);
+    ZlibDecoder::new(compressed)
+        .take(size as u64 + 1)
+        .read_to_end(&mut pixels)
+        .map_err(|e| format!("corrupt compressed cel: {}", e))?;
+
+    if pixels.len() != size {
+        return Err("compressed cel has the wrong size".to_string());
+    }
+
+    Ok(pixels)
+}
+
+// blends _cel_ over _pixels_ where it overlaps the sprite
+fn draw_cel(
+    pixels: &mut [u8],
+    width: usize,
+    height: usize,
+    cel: &Cel,
+    depth: ColorDepth,
+    opacity: u32,
+    to_rgba: impl Fn(&[u8]) -> [u8; 4],
+) {
+    let bytes_per_pixel = depth.bytes_per_pixel();
+
+    for cel_y in 0..cel.height {
+        let y = cel.y + cel_y as i32;
+        if y < 0 || y >= height as i32 {
+            continue;
+        }
+
+        for cel_x in 0..cel.width {
+            let x = cel.x + cel_x as i32;
+            if x < 0 || x >= width as i32 {
+                continue;
+            }
+
+            let source = (cel_y * cel.width + cel_x) * bytes_per_pixel;
+            let [r, g, b, a] = to_rgba(&cel.pixels[source..source + bytes_per_pixel]);
+            let source_alpha = a as u32 * opacity / 255;
+            if source_alpha == 0 {
+                continue;
+            }
+
+            let destination = (y as usize * width + x as usize) * 4;
+            let destination = &mut pixels[destination..destination + 4];
+            let destination_alpha = destination[3] as u32 * (255 - source_alpha) / 255;
+            let alpha = source_alpha + destination_alpha;
+            for (channel, source) in destination[..3].iter_mut().zip([r, g, b]) {
+                *channel = ((source as u32 * source_alpha + *channel as u32 * destination_alpha)
+                    / alpha) as u8;
+            }
+            destination[3] = alpha as u8;
+        }
+    }
+}
+
+// little endian reads that fail instead of panicking on truncated files
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8]) -> Reader<'a> {
+        Reader { bytes, position: 0 }
+    }
+
+    fn bytes(&mut self, count: usize) -> Result<&'a [u8], String> {
+        let bytes = self
+            .bytes
+            .get(self.position..self.position + count)
+            .ok_or_else(|| "unexpected end of aseprite file".to_string())?;
+        self.position += count;
+        Ok(bytes)
+    }
+
+    fn rest(&mut self) -> &'a [u8] {
+        let rest = &self.bytes[self.position.min(self.bytes.len())..];
+        self.position = self.bytes.len();
+        rest
+    }
+
+    fn skip(&mut self, count: usize) -> Result<(), String> {
+        self.bytes(count).map(|_| ())
+    }
+
+    fn byte(&mut self) -> Result<u8, String> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn word(&mut self) -> Result<u16, String> {
+        let bytes = self.bytes(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn short(&mut self) -> Result<i16, String> {
+        Ok(self.word()? as i16)
+    }
+
+    fn dword(&mut self) -> Result<u32, String> {
+        let bytes = self.bytes(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        let length = self.word()? as usize;
+        Ok(String::from_utf8_lossy(self.bytes(length)?).into_owned())
+    }
+}
@@ -0,0 +1,256 @@
+use std::cell::RefCell;
+use std::ffi::CStr;
+use std::time::Duration;
+
+use super::debug::ObjectKind;
+use super::image::ImagePixels;
+use super::texture::{PixelRect, TextureDescriptor, TextureFilter, TextureFormat};
+
+pub mod opengl;
+pub mod recording;
+pub mod software;
+
+pub use opengl::OpenGlBackend;
+pub use recording::{Command, CommandLog, RecordingBackend};
+pub use software::{SoftwareBackend, SoftwareFramebuffer};
+
+// names handed out by the backend for buffers, textures, programs, ... 0 is never a valid object
+pub type ObjectId = gl::types::GLuint;
+// opaque handle of a fence created with RenderBackend::fence
+pub type FenceId = usize;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BufferUsage {
+    StaticDraw,
+    DynamicDraw,
+    StreamDraw,
+    DynamicCopy,
+    StreamRead,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum TextureTarget {
+    Texture2D,
+    Texture2DArray,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ShaderStage {
+    Vertex,
+    Fragment,
+    Compute,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PrimitiveMode {
+    Triangles,
+    TriangleStrip,
+    TriangleFan,
+    Lines,
+    LineStrip,
+    LineLoop,
+}
+
+// type of the indices draw_elements reads from the bound ELEMENT_ARRAY_BUFFER
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum IndexType {
+    U8,
+    U16,
+    U32,
+}
+
+impl IndexType {
+    pub fn size(self) -> usize {
+        match self {
+            IndexType::U8 => 1,
+            IndexType::U16 => 2,
+            IndexType::U32 => 4,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FenceStatus {
+    Signaled,
+    TimedOut,
+    Failed,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Uniform {
+    Float(f32),
+    Int(i32),
+    Vec3(glm::Vec3),
+    Vec4(glm::Vec4),
+    Mat4(glm::Mat4),
+}
+
+// pixels handed to texture uploads, laid out as format.channels() components per pixel
+#[derive(Copy, Clone, Debug)]
+pub enum PixelData<'a> {
+    // leaves the contents uninitialized
+    None,
+    U8(&'a [u8]),
+    F32(&'a [f32]),
+}
+
+// everything render needs from the graphics API. calls act on whatever is bound, the same way GL
+// does, so the types in render (Buffer, Texture, GlProgram, ...) map one to one onto it.
+// buffer targets are the GL binding points (ARRAY_BUFFER, ELEMENT_ARRAY_BUFFER, ...)
+pub trait RenderBackend {
+    fn name(&self) -> &'static str;
+
+    fn set_label(&mut self, kind: ObjectKind, id: ObjectId, label: &str);
+    // fills the whole color buffer with _color_
+    fn clear(&mut self, color: glm::Vec4);
+    // RGBA8 pixels of the window rectangle (0, 0, width, height), bottom row first like GL
+    fn read_pixels(&mut self, width: usize, height: usize) -> Vec<u8>;
+    // the same pixels written to the start of the bound PIXEL_PACK_BUFFER. it returns without
+    // waiting for rendering to finish; a fence tells when the buffer can be read
+    fn read_pixels_to_pack_buffer(&mut self, width: usize, height: usize);
+
+    // buffers
+    fn create_buffer(&mut self) -> ObjectId;
+    fn delete_buffer(&mut self, buffer: ObjectId);
+    fn bind_buffer(&mut self, target: gl::types::GLenum, buffer: ObjectId);
+    fn bind_buffer_base(&mut self, target: gl::types::GLenum, index: u32, buffer: ObjectId);
+    // (re)allocates _size_ bytes, filled with _data_ if there is any
+    fn buffer_data(
+        &mut self,
+        target: gl::types::GLenum,
+        size: usize,
+        data: Option<&[u8]>,
+        usage: BufferUsage,
+    );
+    fn buffer_sub_data(&mut self, target: gl::types::GLenum, offset: usize, data: &[u8]);
+    // writes without waiting for draws still reading the buffer; callers fence to stay safe
+    fn write_buffer_unsynchronized(
+        &mut self,
+        target: gl::types::GLenum,
+        offset: usize,
+        data: &[u8],
+    ) -> Result<(), String>;
+    fn read_buffer(&mut self, target: gl::types::GLenum, offset: usize, data: &mut [u8]);
+    fn fence(&mut self) -> FenceId;
+    fn wait_fence(&mut self, fence: FenceId, timeout: Duration) -> FenceStatus;
+    fn delete_fence(&mut self, fence: FenceId);
+
+    // vertex arrays
+    fn create_vertex_array(&mut self) -> ObjectId;
+    fn delete_vertex_array(&mut self, vertex_array: ObjectId);
+    fn bind_vertex_array(&mut self, vertex_array: ObjectId);
+    // float attribute at _location_ read from the bound ARRAY_BUFFER
+    fn vertex_attrib_pointer(
+        &mut self,
+        location: u32,
+        components: i32,
+        stride: usize,
+        offset: usize,
+    );
+
+    // textures
+    fn create_texture(&mut self) -> ObjectId;
+    fn delete_texture(&mut self, texture: ObjectId);
+    fn active_texture(&mut self, unit: u32);
+    fn bind_texture(&mut self, target: TextureTarget, texture: ObjectId);
+    // (re)specifies level 0 of the texture bound to Texture2D
+    fn texture_image_2d(
+        &mut self,
+        width: usize,
+        height: usize,
+        format: TextureFormat,
+        data: PixelData,
+    );
+    fn texture_sub_image_2d(&mut self, rect: PixelRect, format: TextureFormat, data: PixelData);
+    // specifies every layer of the texture bound to Texture2DArray, _data_ holds them in order
+    fn texture_image_3d(
+        &mut self,
+        width: usize,
+        height: usize,
+        layers: usize,
+        format: TextureFormat,
+        data: PixelData,
+    );
+    // wrap/filter modes of the texture bound to _target_; builds mipmaps when the descriptor asks
+    fn set_sampling(&mut self, target: TextureTarget, descriptor: &TextureDescriptor);
+    fn generate_mipmaps(&mut self, target: TextureTarget);
+    // level 0 of the texture bound to Texture2D
+    fn read_texture(&mut self, width: usize, height: usize, format: TextureFormat) -> ImagePixels;
+
+    // framebuffers; 0 is the window's own. rectangles here are in window coordinates, which
+    // start at the bottom left corner
+    fn create_framebuffer(&mut self) -> ObjectId;
+    fn delete_framebuffer(&mut self, framebuffer: ObjectId);
+    fn bind_framebuffer(&mut self, framebuffer: ObjectId);
+    // makes level 0 of the 2D _texture_ the color buffer of the bound framebuffer
+    fn attach_texture(&mut self, texture: ObjectId) -> Result<(), String>;
+    // where clip space lands in the bound framebuffer
+    fn viewport(&mut self, rect: PixelRect);
+    // copies _from_ of _source_ into _to_ of _destination_, scaling with _filter_;
+    // leaves _destination_ bound
+    fn blit_framebuffer(
+        &mut self,
+        source: ObjectId,
+        from: PixelRect,
+        destination: ObjectId,
+        to: PixelRect,
+        filter: TextureFilter,
+    );
+
+    // programs
+    fn compile_shader(&mut self, stage: ShaderStage, source: &CStr) -> Result<ObjectId, String>;
+    fn delete_shader(&mut self, shader: ObjectId);
+    // _name_ is the asset the shaders were loaded from, empty when they didn't come from one
+    fn link_program(&mut self, name: &str, shaders: &[ObjectId]) -> Result<ObjectId, String>;
+    fn delete_program(&mut self, program: ObjectId);
+    fn use_program(&mut self, program: ObjectId);
+    fn set_uniform(&mut self, program: ObjectId, name: &CStr, value: Uniform);
+    fn supports_compute(&mut self) -> bool;
+    fn compute_work_group_size(&mut self, program: ObjectId) -> [u32; 3];
+
+    // draws and dispatches
+    fn draw_arrays(&mut self, mode: PrimitiveMode, first: usize, count: usize);
+    // draws _count_ indices starting at index _first_ of the bound ELEMENT_ARRAY_BUFFER,
+    // each added to _base_vertex_
+    fn draw_elements(
+        &mut self,
+        mode: PrimitiveMode,
+        index_type: IndexType,
+        first: usize,
+        count: usize,
+        base_vertex: i32,
+    );
+    fn dispatch_compute(&mut self, groups: [u32; 3]);
+    fn memory_barrier(&mut self, barriers: gl::types::GLbitfield);
+
+    // timer queries measure how long the GPU takes to run the commands between begin and end,
+    // one query at a time. the others are only called when supports_timer_queries is true
+    fn supports_timer_queries(&mut self) -> bool;
+    fn create_timer_query(&mut self) -> ObjectId;
+    fn delete_timer_query(&mut self, query: ObjectId);
+    fn begin_timer_query(&mut self, query: ObjectId);
+    fn end_timer_query(&mut self);
+    // whether the GPU is done with _query_, so reading its result won't stall
+    fn timer_query_available(&mut self, query: ObjectId) -> bool;
+    // nanoseconds measured by _query_
+    fn timer_query_result(&mut self, query: ObjectId) -> u64;
+}
+
+thread_local! {
+    static BACKEND: RefCell<Box<dyn RenderBackend>> = RefCell::new(Box::new(OpenGlBackend));
+}
+
+// runs _f_ with the backend of this thread, OpenGL unless another one was installed
+pub fn with<R>(f: impl FnOnce(&mut dyn RenderBackend) -> R) -> R {
+    BACKEND.with(|backend| f(backend.borrow_mut().as_mut()))
+}
+
+// replaces the backend of this thread and returns the previous one. objects are not carried
+// over, so this has to happen before creating any buffer, texture or program
+pub fn set_backend(backend: Box<dyn RenderBackend>) -> Box<dyn RenderBackend> {
+    BACKEND.with(|current| current.replace(backend))
+}
+
+pub fn backend_name() -> &'static str {
+    with(|backend| backend.name())
+}
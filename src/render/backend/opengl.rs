@@ -0,0 +1,831 @@
+use std::ffi::{CStr, CString};
+use std::time::Duration;
+
+use gl::types::{GLenum, GLint, GLuint};
+
+use super::{
+    BufferUsage, FenceId, FenceStatus, IndexType, ObjectId, PixelData, PrimitiveMode,
+    RenderBackend, ShaderStage, TextureTarget, Uniform,
+};
+use crate::render::debug::{self, ObjectKind};
+use crate::render::image::ImagePixels;
+use crate::render::texture::{
+    PixelRect, TextureDescriptor, TextureFilter, TextureFormat, TextureWrap,
+};
+
+// compute shaders are core since GL 4.3
+const MIN_COMPUTE_GL_VERSION: (i32, i32) = (4, 3);
+
+// talks to the GL context current on this thread; function pointers must have been loaded
+// with gl::load_with before any call
+pub struct OpenGlBackend;
+
+impl RenderBackend for OpenGlBackend {
+    fn name(&self) -> &'static str {
+        "opengl"
+    }
+
+    fn set_label(&mut self, kind: ObjectKind, id: ObjectId, label: &str) {
+        debug::label_object(kind, id, label);
+    }
+
+    fn clear(&mut self, color: glm::Vec4) {
+        unsafe {
+            gl_call!(gl::ClearColor(color.x, color.y, color.z, color.w));
+            gl_call!(gl::Clear(gl::COLOR_BUFFER_BIT));
+        }
+    }
+
+    fn read_pixels(&mut self, width: usize, height: usize) -> Vec<u8> {
+        let mut data = vec![0u8; width * height * 4];
+        unsafe {
+            gl_call!(gl::PixelStorei(gl::PACK_ALIGNMENT, 1));
+            gl_call!(gl::ReadPixels(
+                0,
+                0,
+                width as i32,
+                height as i32,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                data.as_mut_ptr() as *mut gl::types::GLvoid,
+            ));
+        }
+        data
+    }
+
+    fn read_pixels_to_pack_buffer(&mut self, width: usize, height: usize) {
+        unsafe {
+            gl_call!(gl::PixelStorei(gl::PACK_ALIGNMENT, 1));
+            // with a pack buffer bound the pointer is a byte offset into it
+            gl_call!(gl::ReadPixels(
+                0,
+                0,
+                width as i32,
+                height as i32,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                std::ptr::null_mut(),
+            ));
+        }
+    }
+
+    fn create_buffer(&mut self) -> ObjectId {
+        let mut buffer: GLuint = 0;
+        unsafe {
+            gl_call!(gl::GenBuffers(1, &mut buffer));
+        }
+        buffer
+    }
+
+    fn delete_buffer(&mut self, buffer: ObjectId) {
+        unsafe {
+            gl_call!(gl::DeleteBuffers(1, &buffer));
+        }
+    }
+
+    fn bind_buffer(&mut self, target: GLenum, buffer: ObjectId) {
+        unsafe {
+            gl_call!(gl::BindBuffer(target, buffer));
+        }
+    }
+
+    fn bind_buffer_base(&mut self, target: GLenum, index: u32, buffer: ObjectId) {
+        unsafe {
+            gl_call!(gl::BindBufferBase(target, index, buffer));
+        }
+    }
+
+    fn buffer_data(
+        &mut self,
+        target: GLenum,
+        size: usize,
+        data: Option<&[u8]>,
+        usage: BufferUsage,
+    ) {
+        let data = data.map_or(std::ptr::null(), |data| {
+            data.as_ptr() as *const gl::types::GLvoid
+        });
+        unsafe {
+            gl_call!(gl::BufferData(
+                target,
+                size as gl::types::GLsizeiptr,
+                data,
+                gl_usage(usage),
+            ));
+        }
+    }
+
+    fn buffer_sub_data(&mut self, target: GLenum, offset: usize, data: &[u8]) {
+        unsafe {
+            gl_call!(gl::BufferSubData(
+                target,
+                offset as gl::types::GLintptr,
+                data.len() as gl::types::GLsizeiptr,
+                data.as_ptr() as *const gl::types::GLvoid,
+            ));
+        }
+    }
+
+    fn write_buffer_unsynchronized(
+        &mut self,
+        target: GLenum,
+        offset: usize,
+        data: &[u8],
+    ) -> Result<(), String> {
+        unsafe {
+            let destination = gl_call!(gl::MapBufferRange(
+                target,
+                offset as gl::types::GLintptr,
+                data.len() as gl::types::GLsizeiptr,
+                gl::MAP_WRITE_BIT | gl::MAP_UNSYNCHRONIZED_BIT | gl::MAP_INVALIDATE_RANGE_BIT,
+            ));
+            if destination.is_null() {
+                return Err(format!(
+                    "could not map {} bytes at offset {}",
+                    data.len(),
+                    offset
+                ));
+            }
+
+            // mapped memory gives no alignment guarantees, so copy it as plain bytes
+            std::ptr::copy_nonoverlapping(data.as_ptr(), destination as *mut u8, data.len());
+            gl_call!(gl::UnmapBuffer(target));
+        }
+
+        Ok(())
+    }
+
+    fn read_buffer(&mut self, target: GLenum, offset: usize, data: &mut [u8]) {
+        unsafe {
+            gl_call!(gl::GetBufferSubData(
+                target,
+                offset as gl::types::GLintptr,
+                data.len() as gl::types::GLsizeiptr,
+                data.as_mut_ptr() as *mut gl::types::GLvoid,
+            ));
+        }
+    }
+
+    fn fence(&mut self) -> FenceId {
+        unsafe { gl_call!(gl::FenceSync(gl::SYNC_GPU_COMMANDS_COMPLETE, 0)) as FenceId }
+    }
+
+    fn wait_fence(&mut self, fence: FenceId, timeout: Duration) -> FenceStatus {
+        let result = unsafe {
+            gl_call!(gl::ClientWaitSync(
+                fence as gl::types::GLsync,
+                gl::SYNC_FLUSH_COMMANDS_BIT,
+                timeout.as_nanos() as gl::types::GLuint64,
+            ))
+        };
+
+        match result {
+            gl::ALREADY_SIGNALED | gl::CONDITION_SATISFIED => FenceStatus::Signaled,
+            gl::TIMEOUT_EXPIRED => FenceStatus::TimedOut,
+            _ => FenceStatus::Failed,
+        }
+    }
+
+    fn delete_fence(&mut self, fence: FenceId) {
+        unsafe {
+            gl_call!(gl::DeleteSync(fence as gl::types::GLsync));
+        }
+    }
+
+    fn create_vertex_array(&mut self) -> ObjectId {
+        let mut vao: GLuint = 0;
+        unsafe {
+            gl_call!(gl::GenVertexArrays(1, &mut vao));
+        }
+        vao
+    }
+
+    fn delete_vertex_array(&mut self, vertex_array: ObjectId) {
+        unsafe {
+            gl_call!(gl::DeleteVertexArrays(1, &vertex_array));
+        }
+    }
+
+    fn bind_vertex_array(&mut self, vertex_array: ObjectId) {
+        unsafe {
+            gl_call!(gl::BindVertexArray(vertex_array));
+        }
+    }
+
+    fn vertex_attrib_pointer(
+        &mut self,
+        location: u32,
+        components: i32,
+        stride: usize,
+        offset: usize,
+    ) {
+        unsafe {
+            gl_call!(gl::EnableVertexAttribArray(location));
+            gl_call!(gl::VertexAttribPointer(
+                location,
+                components, // the number of components per generic vertex attribute
+                gl::FLOAT,  // data type
+                gl::FALSE,  // normalized (int-to-float conversion)
+                stride as GLint,
+                offset as *const gl::types::GLvoid,
+            ));
+        }
+    }
+
+    fn create_texture(&mut self) -> ObjectId {
+        let mut texture: GLuint = 0;
+        unsafe {
+            gl_call!(gl::GenTextures(1, &mut texture));
+        }
+        texture
+    }
+
+    fn delete_texture(&mut self, texture: ObjectId) {
+        unsafe {
+            gl_call!(gl::DeleteTextures(1, &texture));
+        }
+    }
+
+    fn active_texture(&mut self, unit: u32) {
+        unsafe {
+            gl_call!(gl::ActiveTexture(gl::TEXTURE0 + unit));
+        }
+    }
+
+    fn bind_texture(&mut self, target: TextureTarget, texture: ObjectId) {
+        unsafe {
+            gl_call!(gl::BindTexture(gl_texture_target(target), texture));
+        }
+    }
+
+    fn texture_image_2d(
+        &mut self,
+        width: usize,
+        height: usize,
+        format: TextureFormat,
+        data: PixelData,
+    ) {
+        let (pixels, pixel_type) = gl_pixels(data);
+        unsafe {
+            // rows of 1, 2 or 3 channel images are not necessarily 4 byte aligned
+            gl_call!(gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1));
+            //https://www.khronos.org/registry/OpenGL-Refpages/gl4/html/glTexParameter.xhtml#:~:text=to%20GL_REPEAT.-,Notes,-Suppose%20that%20a
+            gl_call!(gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                format.internal_format() as GLint,
+                width as i32,
+                height as i32,
+                0,
+                format.pixel_format(),
+                pixel_type,
+                pixels,
+            ));
+            gl_call!(gl::PixelStorei(gl::UNPACK_ALIGNMENT, 4));
+        }
+    }
+
+    fn texture_sub_image_2d(&mut self, rect: PixelRect, format: TextureFormat, data: PixelData) {
+        let (pixels, pixel_type) = gl_pixels(data);
+        unsafe {
+            gl_call!(gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1));
+            gl_call!(gl::TexSubImage2D(
+                gl::TEXTURE_2D,
+                0,
+                rect.x as i32,
+                rect.y as i32,
+                rect.width as i32,
+                rect.height as i32,
+                format.pixel_format(),
+                pixel_type,
+                pixels,
+            ));
+            gl_call!(gl::PixelStorei(gl::UNPACK_ALIGNMENT, 4));
+        }
+    }
+
+    fn texture_image_3d(
+        &mut self,
+        width: usize,
+        height: usize,
+        layers: usize,
+        format: TextureFormat,
+        data: PixelData,
+    ) {
+        let (pixels, pixel_type) = gl_pixels(data);
+        unsafe {
+            gl_call!(gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1));
+            gl_call!(gl::TexImage3D(
+                gl::TEXTURE_2D_ARRAY,
+                0,
+                format.internal_format() as GLint,
+                width as i32,
+                height as i32,
+                layers as i32,
+                0,
+                format.pixel_format(),
+                pixel_type,
+                pixels,
+            ));
+            gl_call!(gl::PixelStorei(gl::UNPACK_ALIGNMENT, 4));
+        }
+    }
+
+    fn set_sampling(&mut self, target: TextureTarget, descriptor: &TextureDescriptor) {
+        let target = gl_texture_target(target);
+        unsafe {
+            // set Texture wrap and filter modes
+            gl_call!(gl::TexParameteri(
+                target,
+                gl::TEXTURE_WRAP_S,
+                gl_wrap(descriptor.wrap_s) as GLint,
+            ));
+            gl_call!(gl::TexParameteri(
+                target,
+                gl::TEXTURE_WRAP_T,
+                gl_wrap(descriptor.wrap_t) as GLint,
+            ));
+
+            gl_call!(gl::TexParameteri(
+                target,
+                gl::TEXTURE_MIN_FILTER,
+                gl_min_filter(descriptor) as GLint,
+            ));
+            gl_call!(gl::TexParameteri(
+                target,
+                gl::TEXTURE_MAG_FILTER,
+                gl_filter(descriptor.mag_filter) as GLint,
+            ));
+
+            match descriptor.mipmaps {
+                Some(_) => gl_call!(gl::GenerateMipmap(target)),
+                None => gl_call!(gl::TexParameteri(target, gl::TEXTURE_MAX_LEVEL, 0 as GLint)),
+            }
+        }
+    }
+
+    fn generate_mipmaps(&mut self, target: TextureTarget) {
+        unsafe {
+            gl_call!(gl::GenerateMipmap(gl_texture_target(target)));
+        }
+    }
+
+    fn read_texture(&mut self, width: usize, height: usize, format: TextureFormat) -> ImagePixels {
+        let len = width * height * format.channels();
+
+        unsafe {
+            gl_call!(gl::PixelStorei(gl::PACK_ALIGNMENT, 1));
+
+            let pixels = if format.is_float() {
+                let mut data: Vec<f32> = vec![0.0; len];
+                gl_call!(gl::GetTexImage(
+                    gl::TEXTURE_2D,
+                    0,
+                    format.pixel_format(),
+                    gl::FLOAT,
+                    data.as_mut_ptr() as *mut gl::types::GLvoid,
+                ));
+                ImagePixels::F32(data)
+            } else {
+                let mut data: Vec<u8> = vec![0; len];
+                gl_call!(gl::GetTexImage(
+                    gl::TEXTURE_2D,
+                    0,
+                    format.pixel_format(),
+                    gl::UNSIGNED_BYTE,
+                    data.as_mut_ptr() as *mut gl::types::GLvoid,
+                ));
+                ImagePixels::U8(data)
+            };
+
+            gl_call!(gl::PixelStorei(gl::PACK_ALIGNMENT, 4));
+            pixels
+        }
+    }
+
+    fn create_framebuffer(&mut self) -> ObjectId {
+        let mut framebuffer: GLuint = 0;
+        unsafe {
+            gl_call!(gl::GenFramebuffers(1, &mut framebuffer));
+        }
+        framebuffer
+    }
+
+    fn delete_framebuffer(&mut self, framebuffer: ObjectId) {
+        unsafe {
+            gl_call!(gl::DeleteFramebuffers(1, &framebuffer));
+        }
+    }
+
+    fn bind_framebuffer(&mut self, framebuffer: ObjectId) {
+        unsafe {
+            gl_call!(gl::BindFramebuffer(gl::FRAMEBUFFER, framebuffer));
+        }
+    }
+
+    fn attach_texture(&mut self, texture: ObjectId) -> Result<(), String> {
+        let status = unsafe {
+            gl_call!(gl::FramebufferTexture2D(
+                gl::FRAMEBUFFER,
+                gl::COLOR_ATTACHMENT0,
+                gl::TEXTURE_2D,
+                texture,
+                0,
+            ));
+            gl_call!(gl::CheckFramebufferStatus(gl::FRAMEBUFFER))
+        };
+
+        if status != gl::FRAMEBUFFER_COMPLETE {
+            return Err(format!(
+                "framebuffer with texture {} is incomplete: {:#x}",
+                texture, status
+            ));
+        }
+        Ok(())
+    }
+
+    fn viewport(&mut self, rect: PixelRect) {
+        unsafe {
+            gl_call!(gl::Viewport(
+                rect.x as GLint,
+                rect.y as GLint,
+                rect.width as GLint,
+                rect.height as GLint,
+            ));
+        }
+    }
+
+    fn blit_framebuffer(
+        &mut self,
+        source: ObjectId,
+        from: PixelRect,
+        destination: ObjectId,
+        to: PixelRect,
+        filter: TextureFilter,
+    ) {
+        unsafe {
+            gl_call!(gl::BindFramebuffer(gl::READ_FRAMEBUFFER, source));
+            gl_call!(gl::BindFramebuffer(gl::DRAW_FRAMEBUFFER, destination));
+            gl_call!(gl::BlitFramebuffer(
+                from.x as GLint,
+                from.y as GLint,
+                (from.x + from.width) as GLint,
+                (from.y + from.height) as GLint,
+                to.x as GLint,
+                to.y as GLint,
+                (to.x + to.width) as GLint,
+                (to.y + to.height) as GLint,
+                gl::COLOR_BUFFER_BIT,
+                gl_filter(filter),
+            ));
+            gl_call!(gl::BindFramebuffer(gl::FRAMEBUFFER, destination));
+        }
+    }
+
+    fn compile_shader(&mut self, stage: ShaderStage, source: &CStr) -> Result<ObjectId, String> {
+        let id = unsafe { gl_call!(gl::CreateShader(gl_shader_stage(stage))) };
+        unsafe {
+            gl_call!(gl::ShaderSource(id, 1, &source.as_ptr(), std::ptr::null()));
+            gl_call!(gl::CompileShader(id));
+        }
+
+        let mut success: GLint = 1;
+        unsafe {
+            gl_call!(gl::GetShaderiv(id, gl::COMPILE_STATUS, &mut success));
+        }
+
+        if success == 0 {
+            let mut len: GLint = 0;
+            unsafe {
+                gl_call!(gl::GetShaderiv(id, gl::INFO_LOG_LENGTH, &mut len));
+            }
+
+            // todo: review this, looks pretty dumb
+            let error: CString = create_whitespace_cstring_with_len(len as usize);
+
+            unsafe {
+                gl_call!(gl::GetShaderInfoLog(
+                    id,
+                    len,
+                    std::ptr::null_mut(),
+                    error.as_ptr() as *mut gl::types::GLchar,
+                ));
+                gl_call!(gl::DeleteShader(id));
+            }
+
+            return Err(error.to_string_lossy().into_owned());
+        }
+
+        Ok(id)
+    }
+
+    fn delete_shader(&mut self, shader: ObjectId) {
+        unsafe {
+            gl_call!(gl::DeleteShader(shader));
+        }
+    }
+
+    fn link_program(&mut self, name: &str, shaders: &[ObjectId]) -> Result<ObjectId, String> {
+        let program_id = unsafe { gl_call!(gl::CreateProgram()) };
+
+        for &shader in shaders {
+            unsafe {
+                gl_call!(gl::AttachShader(program_id, shader));
+            }
+        }
+
+        unsafe {
+            gl_call!(gl::LinkProgram(program_id));
+        }
+
+        let mut success: GLint = 1;
+
+        unsafe {
+            gl_call!(gl::GetProgramiv(program_id, gl::LINK_STATUS, &mut success));
+        }
+
+        if success == 0 {
+            let mut len: GLint = 0;
+            unsafe {
+                gl_call!(gl::GetProgramiv(program_id, gl::INFO_LOG_LENGTH, &mut len));
+            }
+
+            let error = create_whitespace_cstring_with_len(len as usize);
+
+            unsafe {
+                gl_call!(gl::GetProgramInfoLog(
+                    program_id,
+                    len,
+                    std::ptr::null_mut(),
+                    error.as_ptr() as *mut gl::types::GLchar,
+                ));
+                gl_call!(gl::DeleteProgram(program_id));
+            }
+
+            return Err(error.to_string_lossy().into_owned());
+        }
+
+        for &shader in shaders {
+            unsafe {
+                gl_call!(gl::DetachShader(program_id, shader));
+            }
+        }
+        // until given a better label, debuggers show the asset the program came from
+        if !name.is_empty() {
+            debug::label_object(ObjectKind::Program, program_id, name);
+        }
+
+        Ok(program_id)
+    }
+
+    fn delete_program(&mut self, program: ObjectId) {
+        unsafe {
+            gl_call!(gl::DeleteProgram(program));
+        }
+    }
+
+    fn use_program(&mut self, program: ObjectId) {
+        unsafe {
+            gl_call!(gl::UseProgram(program));
+        }
+    }
+
+    // the program has to be in use
+    fn set_uniform(&mut self, program: ObjectId, name: &CStr, value: Uniform) {
+        unsafe {
+            let location = gl_call!(gl::GetUniformLocation(program, name.as_ptr()));
+            match value {
+                Uniform::Float(value) => gl_call!(gl::Uniform1f(location, value)),
+                Uniform::Int(value) => gl_call!(gl::Uniform1i(location, value)),
+                Uniform::Vec3(value) => {
+                    gl_call!(gl::Uniform3f(location, value.x, value.y, value.z))
+                }
+                Uniform::Vec4(value) => {
+                    gl_call!(gl::Uniform4f(location, value.x, value.y, value.z, value.w))
+                }
+                // todo: arguments here should be reviewed (value_ptr overhead? how does it relate to transpose arg?)
+                Uniform::Mat4(matrix) => gl_call!(gl::UniformMatrix4fv(
+                    location,
+                    1,
+                    gl::FALSE,
+                    glm::value_ptr(&matrix).as_ptr(),
+                )),
+            }
+        }
+    }
+
+    // compute shaders need a 4.3 context (or the function pointers loaded through an extension)
+    fn supports_compute(&mut self) -> bool {
+        if !gl::DispatchCompute::is_loaded() || !gl::MemoryBarrier::is_loaded() {
+            return false;
+        }
+
+        let mut major = 0;
+        let mut minor = 0;
+        unsafe {
+            gl_call!(gl::GetIntegerv(gl::MAJOR_VERSION, &mut major));
+            gl_call!(gl::GetIntegerv(gl::MINOR_VERSION, &mut minor));
+        }
+
+        (major, minor) >= MIN_COMPUTE_GL_VERSION
+    }
+
+    fn compute_work_group_size(&mut self, program: ObjectId) -> [u32; 3] {
+        let mut local_size: [GLint; 3] = [1, 1, 1];
+        unsafe {
+            gl_call!(gl::GetProgramiv(
+                program,
+                gl::COMPUTE_WORK_GROUP_SIZE,
+                local_size.as_mut_ptr(),
+            ));
+        }
+
+        local_size.map(|x| x as u32)
+    }
+
+    fn draw_arrays(&mut self, mode: PrimitiveMode, first: usize, count: usize) {
+        unsafe {
+            gl_call!(gl::DrawArrays(
+                gl_primitive_mode(mode),
+                first as i32,
+                count as i32
+            ));
+        }
+    }
+
+    fn draw_elements(
+        &mut self,
+        mode: PrimitiveMode,
+        index_type: IndexType,
+        first: usize,
+        count: usize,
+        base_vertex: i32,
+    ) {
+        unsafe {
+            gl_call!(gl::DrawElementsBaseVertex(
+                gl_primitive_mode(mode),
+                count as i32,
+                gl_index_type(index_type),
+                // with an element buffer bound the pointer is a byte offset into it
+                (first * index_type.size()) as *const gl::types::GLvoid,
+                base_vertex,
+            ));
+        }
+    }
+
+    fn dispatch_compute(&mut self, groups: [u32; 3]) {
+        unsafe {
+            gl_call!(gl::DispatchCompute(groups[0], groups[1], groups[2]));
+        }
+    }
+
+    fn memory_barrier(&mut self, barriers: gl::types::GLbitfield) {
+        unsafe {
+            gl_call!(gl::MemoryBarrier(barriers));
+        }
+    }
+
+    // GL_TIME_ELAPSED needs 3.3 or ARB_timer_query
+    fn supports_timer_queries(&mut self) -> bool {
+        gl::GetQueryObjectui64v::is_loaded()
+    }
+
+    fn create_timer_query(&mut self) -> ObjectId {
+        let mut query = 0;
+        unsafe {
+            gl_call!(gl::GenQueries(1, &mut query));
+        }
+        query
+    }
+
+    fn delete_timer_query(&mut self, query: ObjectId) {
+        unsafe {
+            gl_call!(gl::DeleteQueries(1, &query));
+        }
+    }
+
+    fn begin_timer_query(&mut self, query: ObjectId) {
+        unsafe {
+            gl_call!(gl::BeginQuery(gl::TIME_ELAPSED, query));
+        }
+    }
+
+    fn end_timer_query(&mut self) {
+        unsafe {
+            gl_call!(gl::EndQuery(gl::TIME_ELAPSED));
+        }
+    }
+
+    fn timer_query_available(&mut self, query: ObjectId) -> bool {
+        let mut available: GLuint = 0;
+        unsafe {
+            gl_call!(gl::GetQueryObjectuiv(
+                query,
+                gl::QUERY_RESULT_AVAILABLE,
+                &mut available
+            ));
+        }
+        available != 0
+    }
+
+    fn timer_query_result(&mut self, query: ObjectId) -> u64 {
+        let mut nanoseconds: u64 = 0;
+        unsafe {
+            gl_call!(gl::GetQueryObjectui64v(
+                query,
+                gl::QUERY_RESULT,
+                &mut nanoseconds
+            ));
+        }
+        nanoseconds
+    }
+}
+
+fn gl_usage(usage: BufferUsage) -> GLenum {
+    match usage {
+        BufferUsage::StaticDraw => gl::STATIC_DRAW,
+        BufferUsage::DynamicDraw => gl::DYNAMIC_DRAW,
+        BufferUsage::StreamDraw => gl::STREAM_DRAW,
+        BufferUsage::DynamicCopy => gl::DYNAMIC_COPY,
+        BufferUsage::StreamRead => gl::STREAM_READ,
+    }
+}
+
+fn gl_texture_target(target: TextureTarget) -> GLenum {
+    match target {
+        TextureTarget::Texture2D => gl::TEXTURE_2D,
+        TextureTarget::Texture2DArray => gl::TEXTURE_2D_ARRAY,
+    }
+}
+
+fn gl_shader_stage(stage: ShaderStage) -> GLenum {
+    match stage {
+        ShaderStage::Vertex => gl::VERTEX_SHADER,
+        ShaderStage::Fragment => gl::FRAGMENT_SHADER,
+        ShaderStage::Compute => gl::COMPUTE_SHADER,
+    }
+}
+
+fn gl_primitive_mode(mode: PrimitiveMode) -> GLenum {
+    match mode {
+        PrimitiveMode::Triangles => gl::TRIANGLES,
+        PrimitiveMode::TriangleStrip => gl::TRIANGLE_STRIP,
+        PrimitiveMode::TriangleFan => gl::TRIANGLE_FAN,
+        PrimitiveMode::Lines => gl::LINES,
+        PrimitiveMode::LineStrip => gl::LINE_STRIP,
+        PrimitiveMode::LineLoop => gl::LINE_LOOP,
+    }
+}
+
+fn gl_index_type(index_type: IndexType) -> GLenum {
+    match index_type {
+        IndexType::U8 => gl::UNSIGNED_BYTE,
+        IndexType::U16 => gl::UNSIGNED_SHORT,
+        IndexType::U32 => gl::UNSIGNED_INT,
+    }
+}
+
+fn gl_pixels(data: PixelData) -> (*const gl::types::GLvoid, GLenum) {
+    match data {
+        PixelData::None => (std::ptr::null(), gl::UNSIGNED_BYTE),
+        PixelData::U8(data) => (data.as_ptr() as *const gl::types::GLvoid, gl::UNSIGNED_BYTE),
+        PixelData::F32(data) => (data.as_ptr() as *const gl::types::GLvoid, gl::FLOAT),
+    }
+}
+
+fn gl_wrap(wrap: TextureWrap) -> GLenum {
+    match wrap {
+        TextureWrap::Repeat => gl::REPEAT,
+        TextureWrap::MirroredRepeat => gl::MIRRORED_REPEAT,
+        TextureWrap::ClampToEdge => gl::CLAMP_TO_EDGE,
+        TextureWrap::ClampToBorder => gl::CLAMP_TO_BORDER,
+    }
+}
+
+fn gl_filter(filter: TextureFilter) -> GLenum {
+    match filter {
+        TextureFilter::Nearest => gl::NEAREST,
+        TextureFilter::Linear => gl::LINEAR,
+    }
+}
+
+fn gl_min_filter(descriptor: &TextureDescriptor) -> GLenum {
+    match (descriptor.min_filter, descriptor.mipmaps) {
+        (filter, None) => gl_filter(filter),
+        (TextureFilter::Nearest, Some(TextureFilter::Nearest)) => gl::NEAREST_MIPMAP_NEAREST,
+        (TextureFilter::Nearest, Some(TextureFilter::Linear)) => gl::NEAREST_MIPMAP_LINEAR,
+        (TextureFilter::Linear, Some(TextureFilter::Nearest)) => gl::LINEAR_MIPMAP_NEAREST,
+        (TextureFilter::Linear, Some(TextureFilter::Linear)) => gl::LINEAR_MIPMAP_LINEAR,
+    }
+}
+
+fn create_whitespace_cstring_with_len(len: usize) -> CString {
+    // allocate buffer of correct size
+    let mut buffer: Vec<u8> = Vec::with_capacity(len + 1);
+
+    // fill it with len spaces
+    buffer.extend([b' '].iter().cycle().take(len));
+
+    // convert buffer to CString
+    unsafe { CString::from_vec_unchecked(buffer) }
+}
@@ -0,0 +1,617 @@
+use std::cell::RefCell;
+use std::ffi::CStr;
+use std::rc::Rc;
+use std::time::Duration;
+
+use super::{
+    BufferUsage, FenceId, FenceStatus, IndexType, ObjectId, PixelData, PrimitiveMode,
+    RenderBackend, ShaderStage, TextureTarget, Uniform,
+};
+use crate::render::debug::ObjectKind;
+use crate::render::image::ImagePixels;
+use crate::render::texture::{PixelRect, TextureDescriptor, TextureFilter, TextureFormat};
+
+// one call made to the backend. uploads only keep their size, the data itself is not copied
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    SetLabel {
+        kind: ObjectKind,
+        id: ObjectId,
+        label: String,
+    },
+    Clear(glm::Vec4),
+    CreateFramebuffer(ObjectId),
+    DeleteFramebuffer(ObjectId),
+    BindFramebuffer(ObjectId),
+    AttachTexture(ObjectId),
+    Viewport(PixelRect),
+    BlitFramebuffer {
+        source: ObjectId,
+        from: PixelRect,
+        destination: ObjectId,
+        to: PixelRect,
+        filter: TextureFilter,
+    },
+    ReadPixels {
+        width: usize,
+        height: usize,
+        to_pack_buffer: bool,
+    },
+    CreateBuffer(ObjectId),
+    DeleteBuffer(ObjectId),
+    BindBuffer {
+        target: gl::types::GLenum,
+        buffer: ObjectId,
+    },
+    BindBufferBase {
+        target: gl::types::GLenum,
+        index: u32,
+        buffer: ObjectId,
+    },
+    BufferData {
+        target: gl::types::GLenum,
+        size: usize,
+        initialized: bool,
+        usage: BufferUsage,
+    },
+    BufferSubData {
+        target: gl::types::GLenum,
+        offset: usize,
+        size: usize,
+    },
+    WriteBufferUnsynchronized {
+        target: gl::types::GLenum,
+        offset: usize,
+        size: usize,
+    },
+    ReadBuffer {
+        target: gl::types::GLenum,
+        offset: usize,
+        size: usize,
+    },
+    Fence(FenceId),
+    WaitFence(FenceId),
+    DeleteFence(FenceId),
+    CreateVertexArray(ObjectId),
+    DeleteVertexArray(ObjectId),
+    BindVertexArray(ObjectId),
+    VertexAttribPointer {
+        location: u32,
+        components: i32,
+        stride: usize,
+        offset: usize,
+    },
+    CreateTexture(ObjectId),
+    DeleteTexture(ObjectId),
+    ActiveTexture(u32),
+    BindTexture {
+        target: TextureTarget,
+        texture: ObjectId,
+    },
+    TextureImage2D {
+        width: usize,
+        height: usize,
+        format: TextureFormat,
+    },
+    TextureSubImage2D {
+        rect: PixelRect,
+        format: TextureFormat,
+    },
+    TextureImage3D {
+        width: usize,
+        height: usize,
+        layers: usize,
+        format: TextureFormat,
+    },
+    SetSampling {
+        target: TextureTarget,
+        descriptor: TextureDescriptor,
+    },
+    GenerateMipmaps(TextureTarget),
+    ReadTexture {
+        width: usize,
+        height: usize,
+        format: TextureFormat,
+    },
+    CompileShader {
+        stage: ShaderStage,
+        shader: ObjectId,
+    },
+    DeleteShader(ObjectId),
+    LinkProgram {
+        program: ObjectId,
+        name: String,
+        shaders: Vec<ObjectId>,
+    },
+    DeleteProgram(ObjectId),
+    UseProgram(ObjectId),
+    SetUniform {
+        program: ObjectId,
+        name: String,
+        value: Uniform,
+    },
+    DrawArrays {
+        mode: PrimitiveMode,
+        first: usize,
+        count: usize,
+    },
+    DrawElements {
+        mode: PrimitiveMode,
+        index_type: IndexType,
+        first: usize,
+        count: usize,
+        base_vertex: i32,
+    },
+    DispatchCompute([u32; 3]),
+    MemoryBarrier(gl::types::GLbitfield),
+    CreateTimerQuery(ObjectId),
+    DeleteTimerQuery(ObjectId),
+    BeginTimerQuery(ObjectId),
+    EndTimerQuery,
+}
+
+impl Command {
+    pub fn is_draw(&self) -> bool {
+        matches!(
+            self,
+            Command::DrawArrays { .. } | Command::DrawElements { .. }
+        )
+    }
+}
+
+// shared view of the commands recorded by a RecordingBackend, kept after the backend itself
+// was handed over to set_backend
+#[derive(Clone, Default)]
+pub struct CommandLog {
+    commands: Rc<RefCell<Vec<Command>>>,
+}
+
+impl CommandLog {
+    pub fn commands(&self) -> Vec<Command> {
+        self.commands.borrow().clone()
+    }
+
+    pub fn len(&self) -> usize {
+        self.commands.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commands.borrow().is_empty()
+    }
+
+    pub fn count(&self, predicate: impl Fn(&Command) -> bool) -> usize {
+        self.commands
+            .borrow()
+            .iter()
+            .filter(|c| predicate(c))
+            .count()
+    }
+
+    pub fn draw_calls(&self) -> usize {
+        self.count(Command::is_draw)
+    }
+
+    // forgets what was recorded so far, e.g. everything done while setting up a renderer
+    pub fn clear(&self) {
+        self.commands.borrow_mut().clear();
+    }
+
+    fn push(&self, command: Command) {
+        self.commands.borrow_mut().push(command);
+    }
+}
+
+// backend that doesn't draw anything and just records the command stream, so code in render
+// can run and be checked without a GL context:
+//
+//  let log = RecordingBackend::install();
+//  ... create a renderer, draw ...
+//  assert_eq!(log.draw_calls(), 1);
+//
+// ids are handed out in sequence, shaders always compile, fences are always signaled, reads
+// return zeroes and there is no GPU to time
+pub struct RecordingBackend {
+    log: CommandLog,
+    next_id: ObjectId,
+}
+
+impl Default for RecordingBackend {
+    fn default() -> Self {
+        RecordingBackend::new()
+    }
+}
+
+impl RecordingBackend {
+    pub fn new() -> RecordingBackend {
+        RecordingBackend {
+            log: CommandLog::default(),
+            next_id: 1,
+        }
+    }
+
+    // makes a new recording backend the backend of this thread and returns its log
+    pub fn install() -> CommandLog {
+        let backend = RecordingBackend::new();
+        let log = backend.log();
+        super::set_backend(Box::new(backend));
+        log
+    }
+
+    pub fn log(&self) -> CommandLog {
+        self.log.clone()
+    }
+
+    fn next_id(&mut self) -> ObjectId {
+        let id = self.next_id;
+        self.next_id += 1;
+        id
+    }
+}
+
+impl RenderBackend for RecordingBackend {
+    fn name(&self) -> &'static str {
+        "recording"
+    }
+
+    fn set_label(&mut self, kind: ObjectKind, id: ObjectId, label: &str) {
+        self.log.push(Command::SetLabel {
+            kind,
+            id,
+            label: label.to_string(),
+        });
+    }
+
+    fn clear(&mut self, color: glm::Vec4) {
+        self.log.push(Command::Clear(color));
+    }
+
+    fn read_pixels(&mut self, width: usize, height: usize) -> Vec<u8> {
+        self.log.push(Command::ReadPixels {
+            width,
+            height,
+            to_pack_buffer: false,
+        });
+        vec![0; width * height * 4]
+    }
+
+    fn read_pixels_to_pack_buffer(&mut self, width: usize, height: usize) {
+        self.log.push(Command::ReadPixels {
+            width,
+            height,
+            to_pack_buffer: true,
+        });
+    }
+
+    fn create_buffer(&mut self) -> ObjectId {
+        let buffer = self.next_id();
+        self.log.push(Command::CreateBuffer(buffer));
+        buffer
+    }
+
+    fn delete_buffer(&mut self, buffer: ObjectId) {
+        self.log.push(Command::DeleteBuffer(buffer));
+    }
+
+    fn bind_buffer(&mut self, target: gl::types::GLenum, buffer: ObjectId) {
+        self.log.push(Command::BindBuffer { target, buffer });
+    }
+
+    fn bind_buffer_base(&mut self, target: gl::types::GLenum, index: u32, buffer: ObjectId) {
+        self.log.push(Command::BindBufferBase {
+            target,
+            index,
+            buffer,
+        });
+    }
+
+    fn buffer_data(
+        &mut self,
+        target: gl::types::GLenum,
+        size: usize,
+        data: Option<&[u8]>,
+        usage: BufferUsage,
+    ) {
+        self.log.push(Command::BufferData {
+            target,
+            size,
+            initialized: data.is_some(),
+            usage,
+        });
+    }
+
+    fn buffer_sub_data(&mut self, target: gl::types::GLenum, offset: usize, data: &[u8]) {
+        self.log.push(Command::BufferSubData {
+            target,
+            offset,
+            size: data.len(),
+        });
+    }
+
+    fn write_buffer_unsynchronized(
+        &mut self,
+        target: gl::types::GLenum,
+        offset: usize,
+        data: &[u8],
+    ) -> Result<(), String> {
+        self.log.push(Command::WriteBufferUnsynchronized {
+            target,
+            offset,
+            size: data.len(),
+        });
+        Ok(())
+    }
+
+    fn read_buffer(&mut self, target: gl::types::GLenum, offset: usize, data: &mut [u8]) {
+        data.fill(0);
+        self.log.push(Command::ReadBuffer {
+            target,
+            offset,
+            size: data.len(),
+        });
+    }
+
+    fn fence(&mut self) -> FenceId {
+        let fence = self.next_id() as FenceId;
+        self.log.push(Command::Fence(fence));
+        fence
+    }
+
+    fn wait_fence(&mut self, fence: FenceId, _timeout: Duration) -> FenceStatus {
+        self.log.push(Command::WaitFence(fence));
+        FenceStatus::Signaled
+    }
+
+    fn delete_fence(&mut self, fence: FenceId) {
+        self.log.push(Command::DeleteFence(fence));
+    }
+
+    fn create_vertex_array(&mut self) -> ObjectId {
+        let vertex_array = self.next_id();
+        self.log.push(Command::CreateVertexArray(vertex_array));
+        vertex_array
+    }
+
+    fn delete_vertex_array(&mut self, vertex_array: ObjectId) {
+        self.log.push(Command::DeleteVertexArray(vertex_array));
+    }
+
+    fn bind_vertex_array(&mut self, vertex_array: ObjectId) {
+        self.log.push(Command::BindVertexArray(vertex_array));
+    }
+
+    fn vertex_attrib_pointer(
+        &mut self,
+        location: u32,
+        components: i32,
+        stride: usize,
+        offset: usize,
+    ) {
+        self.log.push(Command::VertexAttribPointer {
+            location,
+            components,
+            stride,
+            offset,
+        });
+    }
+
+    fn create_texture(&mut self) -> ObjectId {
+        let texture = self.next_id();
+        self.log.push(Command::CreateTexture(texture));
+        texture
+    }
+
+    fn delete_texture(&mut self, texture: ObjectId) {
+        self.log.push(Command::DeleteTexture(texture));
+    }
+
+    fn active_texture(&mut self, unit: u32) {
+        self.log.push(Command::ActiveTexture(unit));
+    }
+
+    fn bind_texture(&mut self, target: TextureTarget, texture: ObjectId) {
+        self.log.push(Command::BindTexture { target, texture });
+    }
+
+    fn texture_image_2d(
+        &mut self,
+        width: usize,
+        height: usize,
+        format: TextureFormat,
+        _data: PixelData,
+    ) {
+        self.log.push(Command::TextureImage2D {
+            width,
+            height,
+            format,
+        });
+    }
+
+    fn texture_sub_image_2d(&mut self, rect: PixelRect, format: TextureFormat, _data: PixelData) {
+        self.log.push(Command::TextureSubImage2D { rect, format });
+    }
+
+    fn texture_image_3d(
+        &mut self,
+        width: usize,
+        height: usize,
+        layers: usize,
+        format: TextureFormat,
+        _data: PixelData,
+    ) {
+        self.log.push(Command::TextureImage3D {
+            width,
+            height,
+            layers,
+            format,
+        });
+    }
+
+    fn set_sampling(&mut self, target: TextureTarget, descriptor: &TextureDescriptor) {
+        self.log.push(Command::SetSampling {
+            target,
+            descriptor: *descriptor,
+        });
+    }
+
+    fn generate_mipmaps(&mut self, target: TextureTarget) {
+        self.log.push(Command::GenerateMipmaps(target));
+    }
+
+    fn read_texture(&mut self, width: usize, height: usize, format: TextureFormat) -> ImagePixels {
+        self.log.push(Command::ReadTexture {
+            width,
+            height,
+            format,
+        });
+
+        let len = width * height * format.channels();
+        if format.is_float() {
+            ImagePixels::F32(vec![0.0; len])
+        } else {
+            ImagePixels::U8(vec![0; len])
+        }
+    }
+
+    fn create_framebuffer(&mut self) -> ObjectId {
+        let framebuffer = self.next_id();
+        self.log.push(Command::CreateFramebuffer(framebuffer));
+        framebuffer
+    }
+
+    fn delete_framebuffer(&mut self, framebuffer: ObjectId) {
+        self.log.push(Command::DeleteFramebuffer(framebuffer));
+    }
+
+    fn bind_framebuffer(&mut self, framebuffer: ObjectId) {
+        self.log.push(Command::BindFramebuffer(framebuffer));
+    }
+
+    fn attach_texture(&mut self, texture: ObjectId) -> Result<(), String> {
+        self.log.push(Command::AttachTexture(texture));
+        Ok(())
+    }
+
+    fn viewport(&mut self, rect: PixelRect) {
+        self.log.push(Command::Viewport(rect));
+    }
+
+    fn blit_framebuffer(
+        &mut self,
+        source: ObjectId,
+        from: PixelRect,
+        destination: ObjectId,
+        to: PixelRect,
+        filter: TextureFilter,
+    ) {
+        self.log.push(Command::BlitFramebuffer {
+            source,
+            from,
+            destination,
+            to,
+            filter,
+        });
+    }
+
+    fn compile_shader(&mut self, stage: ShaderStage, _source: &CStr) -> Result<ObjectId, String> {
+        let shader = self.next_id();
+        self.log.push(Command::CompileShader { stage, shader });
+        Ok(shader)
+    }
+
+    fn delete_shader(&mut self, shader: ObjectId) {
+        self.log.push(Command::DeleteShader(shader));
+    }
+
+    fn link_program(&mut self, name: &str, shaders: &[ObjectId]) -> Result<ObjectId, String> {
+        let program = self.next_id();
+        self.log.push(Command::LinkProgram {
+            program,
+            name: name.to_string(),
+            shaders: shaders.to_vec(),
+        });
+        Ok(program)
+    }
+
+    fn delete_program(&mut self, program: ObjectId) {
+        self.log.push(Command::DeleteProgram(program));
+    }
+
+    fn use_program(&mut self, program: ObjectId) {
+        self.log.push(Command::UseProgram(program));
+    }
+
+    fn set_uniform(&mut self, program: ObjectId, name: &CStr, value: Uniform) {
+        self.log.push(Command::SetUniform {
+            program,
+            name: name.to_string_lossy().into_owned(),
+            value,
+        });
+    }
+
+    fn supports_compute(&mut self) -> bool {
+        true
+    }
+
+    fn compute_work_group_size(&mut self, _program: ObjectId) -> [u32; 3] {
+        [1, 1, 1]
+    }
+
+    fn draw_arrays(&mut self, mode: PrimitiveMode, first: usize, count: usize) {
+        self.log.push(Command::DrawArrays { mode, first, count });
+    }
+
+    fn draw_elements(
+        &mut self,
+        mode: PrimitiveMode,
+        index_type: IndexType,
+        first: usize,
+        count: usize,
+        base_vertex: i32,
+    ) {
+        self.log.push(Command::DrawElements {
+            mode,
+            index_type,
+            first,
+            count,
+            base_vertex,
+        });
+    }
+
+    fn dispatch_compute(&mut self, groups: [u32; 3]) {
+        self.log.push(Command::DispatchCompute(groups));
+    }
+
+    fn memory_barrier(&mut self, barriers: gl::types::GLbitfield) {
+        self.log.push(Command::MemoryBarrier(barriers));
+    }
+
+    fn supports_timer_queries(&mut self) -> bool {
+        false
+    }
+
+    fn create_timer_query(&mut self) -> ObjectId {
+        let query = self.next_id();
+        self.log.push(Command::CreateTimerQuery(query));
+        query
+    }
+
+    fn delete_timer_query(&mut self, query: ObjectId) {
+        self.log.push(Command::DeleteTimerQuery(query));
+    }
+
+    fn begin_timer_query(&mut self, query: ObjectId) {
+        self.log.push(Command::BeginTimerQuery(query));
+    }
+
+    fn end_timer_query(&mut self) {
+        self.log.push(Command::EndTimerQuery);
+    }
+
+    fn timer_query_available(&mut self, _query: ObjectId) -> bool {
+        true
+    }
+
+    fn timer_query_result(&mut self, _query: ObjectId) -> u64 {
+        0
+    }
+}
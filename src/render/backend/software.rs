@@ -0,0 +1,813 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ffi::CStr;
+use std::rc::Rc;
+use std::time::Duration;
+
+use super::{
+    BufferUsage, FenceId, FenceStatus, IndexType, ObjectId, PixelData, PrimitiveMode,
+    RenderBackend, ShaderStage, TextureTarget, Uniform,
+};
+use crate::render::debug::ObjectKind;
+use crate::render::image::{Image, ImagePixels};
+use crate::render::texture::{PixelRect, TextureDescriptor, TextureFilter, TextureFormat};
+
+mod raster;
+mod shaders;
+mod texture;
+
+use raster::{Framebuffer, RenderTarget, WindowVertex};
+use shaders::{Attributes, CpuProgram, Samplers, Uniforms, Varyings, MAX_ATTRIBUTES};
+use texture::SoftwareTexture;
+
+// float attribute layout set with vertex_attrib_pointer
+#[derive(Copy, Clone, Debug)]
+struct AttributeFormat {
+    buffer: ObjectId,
+    components: usize,
+    stride: usize,
+    offset: usize,
+}
+
+// like in GL, the element buffer binding belongs to the vertex array
+#[derive(Default)]
+struct VertexArrayState {
+    element_buffer: ObjectId,
+    attributes: [Option<AttributeFormat>; MAX_ATTRIBUTES],
+}
+
+// what a vertex array id that was deleted or never created reads as, nothing gets drawn from it
+static NO_VERTEX_ARRAY: VertexArrayState = VertexArrayState {
+    element_buffer: 0,
+    attributes: [None; MAX_ATTRIBUTES],
+};
+
+struct SoftwareProgram {
+    cpu: CpuProgram,
+    uniforms: Uniforms,
+}
+
+// the textures bound to each unit, handed to fragment shading
+struct BoundTextures<'a> {
+    textures: &'a HashMap<ObjectId, SoftwareTexture>,
+    bound: &'a HashMap<(u32, TextureTarget), ObjectId>,
+}
+
+impl<'a> BoundTextures<'a> {
+    fn get(&self, unit: u32, target: TextureTarget) -> Option<&'a SoftwareTexture> {
+        self.bound
+            .get(&(unit, target))
+            .and_then(|texture| self.textures.get(texture))
+    }
+}
+
+impl<'a> Samplers for BoundTextures<'a> {
+    fn sample_2d(&self, unit: u32, uv: glm::Vec2) -> glm::Vec4 {
+        self.get(unit, TextureTarget::Texture2D)
+            .map_or(glm::vec4(0.0, 0.0, 0.0, 1.0), |texture| {
+                texture.sample(uv, 0.0)
+            })
+    }
+
+    fn sample_array(&self, unit: u32, uv: glm::Vec2, layer: f32) -> glm::Vec4 {
+        self.get(unit, TextureTarget::Texture2DArray)
+            .map_or(glm::vec4(0.0, 0.0, 0.0, 1.0), |texture| {
+                texture.sample(uv, layer)
+            })
+    }
+}
+
+// shared view of the color buffer a SoftwareBackend draws into, kept after the backend itself
+// was handed over to set_backend
+#[derive(Clone)]
+pub struct SoftwareFramebuffer {
+    framebuffer: Rc<RefCell<Framebuffer>>,
+}
+
+impl SoftwareFramebuffer {
+    pub fn width(&self) -> usize {
+        self.framebuffer.borrow().width
+    }
+
+    pub fn height(&self) -> usize {
+        self.framebuffer.borrow().height
+    }
+
+    pub fn clear(&self, color: glm::Vec4) {
+        self.framebuffer.borrow_mut().clear(color);
+    }
+
+    // RGBA bytes, top row first
+    pub fn to_image(&self) -> Image {
+        let framebuffer = self.framebuffer.borrow();
+        let data = framebuffer
+            .pixels
+            .iter()
+            .flat_map(|pixel| pixel.iter().map(|&c| to_u8(c)))
+            .collect();
+
+        Image {
+            width: framebuffer.width,
+            height: framebuffer.height,
+            channels: 4,
+            pixels: ImagePixels::U8(data),
+        }
+    }
+}
+
+// renders on the cpu into an RGBA framebuffer, for tooling and machines without a GL driver:
+//
+//  let framebuffer = SoftwareBackend::install(320, 180);
+//  ... create renderers, draw the scene as usual ...
+//  let image = framebuffer.to_image();
+//
+// it keeps GL's bound state model (buffers, vertex arrays, texture units) so the renderers run
+// unchanged. programs are executed by the rust versions of the shaders in assets/shaders,
+// loading any other shader fails. no depth testing, mipmaps or compute
+pub struct SoftwareBackend {
+    framebuffer: Rc<RefCell<Framebuffer>>,
+    next_id: ObjectId,
+    buffers: HashMap<ObjectId, Vec<u8>>,
+    bound_buffers: HashMap<gl::types::GLenum, ObjectId>,
+    vertex_arrays: HashMap<ObjectId, VertexArrayState>,
+    bound_vertex_array: ObjectId,
+    textures: HashMap<ObjectId, SoftwareTexture>,
+    active_unit: u32,
+    bound_textures: HashMap<(u32, TextureTarget), ObjectId>,
+    programs: HashMap<ObjectId, SoftwareProgram>,
+    used_program: ObjectId,
+    // framebuffer objects and the texture attached to each, 0 until one is
+    framebuffers: HashMap<ObjectId, ObjectId>,
+    bound_framebuffer: ObjectId,
+    viewport: PixelRect,
+}
+
+impl SoftwareBackend {
+    pub fn new(width: usize, height: usize) -> SoftwareBackend {
+        let mut vertex_arrays = HashMap::new();
+        // GL's default vertex array, so state set before binding one isn't lost
+        vertex_arrays.insert(0, VertexArrayState::default());
+
+        SoftwareBackend {
+            framebuffer: Rc::new(RefCell::new(Framebuffer::new(width, height))),
+            next_id: 1,
+            buffers: HashMap::new(),
+            bound_buffers: HashMap::new(),
+            vertex_arrays,
+            bound_vertex_array: 0,
+            textures: HashMap::new(),
+            active_unit: 0,
+            bound_textures: HashMap::new(),
+            programs: HashMap::new(),
+            used_program: 0,
+            framebuffers: HashMap::new(),
+            bound_framebuffer: 0,
+            viewport: PixelRect {
+                x: 0,
+                y: 0,
+                width,
+                height,
+            },
+        }
+    }
+
+    // makes a new software backend the backend of this thread and returns its framebuffer
+    pub fn install(width: usize, height: usize) -> SoftwareFramebuffer {
+        let backend = SoftwareBackend::new(width, height);
+        let framebuffer = backend.framebuffer();
+        super::set_backend(Box::new(backend));
+        framebuffer
+    }
+
+    pub fn framebuffer(&self) -> SoftwareFramebuffer {
+        SoftwareFramebuffer {
+            framebuffer: self.framebuffer.clone(),
+        }
+    }
+
+    fn next_id(&mut self) -> ObjectId {
+        let id = self.next_id;
+        self.next_id += 1;
+        id
+    }
+
+    fn bound_buffer_mut(&mut self, target: gl::types::GLenum) -> Option<&mut Vec<u8>> {
+        let buffer = match target {
+            gl::ELEMENT_ARRAY_BUFFER => self.vertex_array().element_buffer,
+            _ => self.bound_buffers.get(&target).copied().unwrap_or(0),
+        };
+        self.buffers.get_mut(&buffer)
+    }
+
+    fn vertex_array(&self) -> &VertexArrayState {
+        self.vertex_arrays
+            .get(&self.bound_vertex_array)
+            .unwrap_or(&NO_VERTEX_ARRAY)
+    }
+
+    fn vertex_array_mut(&mut self) -> &mut VertexArrayState {
+        self.vertex_arrays
+            .entry(self.bound_vertex_array)
+            .or_default()
+    }
+
+    fn bound_texture_mut(&mut self, target: TextureTarget) -> Option<&mut SoftwareTexture> {
+        let texture = self.bound_textures.get(&(self.active_unit, target))?;
+        self.textures.get_mut(texture)
+    }
+
+    // reads vertex _index_ as the bound vertex array describes it; attributes without data read
+    // as (0, 0, 0, 1) like in GL
+    fn fetch(&self, index: usize) -> Attributes {
+        let mut attributes = [glm::vec4(0.0, 0.0, 0.0, 1.0); MAX_ATTRIBUTES];
+
+        for (attribute, format) in attributes.iter_mut().zip(&self.vertex_array().attributes) {
+            let format = match format {
+                Some(format) => format,
+                None => continue,
+            };
+            let data = match self.buffers.get(&format.buffer) {
+                Some(data) => data,
+                None => continue,
+            };
+
+            let stride = if format.stride == 0 {
+                format.components * 4
+            } else {
+                format.stride
+            };
+            let start = index * stride + format.offset;
+            for component in 0..format.components.min(4) {
+                let at = start + component * 4;
+                if let Some(bytes) = data.get(at..at + 4) {
+                    attribute[component] =
+                        f32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+                }
+            }
+        }
+
+        attributes
+    }
+
+    // runs _f_ with what _framebuffer_ draws into: the window for 0, otherwise the attached
+    // texture. that one is taken out of the texture map meanwhile, which is fine as sampling a
+    // texture while drawing into it is undefined in GL too. None when nothing is attached
+    fn with_target<R>(
+        &mut self,
+        framebuffer: ObjectId,
+        f: impl FnOnce(&Self, &mut dyn RenderTarget) -> R,
+    ) -> Option<R> {
+        if framebuffer == 0 {
+            let window = self.framebuffer.clone();
+            let mut window = window.borrow_mut();
+            return Some(f(self, &mut *window));
+        }
+
+        let texture = *self.framebuffers.get(&framebuffer)?;
+        let mut target = self.textures.remove(&texture)?;
+        let result = f(self, &mut target);
+        self.textures.insert(texture, target);
+
+        Some(result)
+    }
+
+    fn draw(&mut self, mode: PrimitiveMode, indices: impl Iterator<Item = usize>) {
+        self.with_target(self.bound_framebuffer, |backend, target| {
+            backend.draw_into(target, mode, indices)
+        });
+    }
+
+    fn draw_into(
+        &self,
+        target: &mut dyn RenderTarget,
+        mode: PrimitiveMode,
+        indices: impl Iterator<Item = usize>,
+    ) {
+        let program = match self.programs.get(&self.used_program) {
+            Some(program) => program,
+            None => return,
+        };
+
+        let clip = raster::clip_rect(target, &self.viewport);
+        let vertices: Vec<WindowVertex> = indices
+            .map(|index| {
+                let (clip, varyings) = program.cpu.vertex(&program.uniforms, &self.fetch(index));
+                WindowVertex::from_clip(clip, varyings, &self.viewport)
+            })
+            .collect();
+
+        let samplers = BoundTextures {
+            textures: &self.textures,
+            bound: &self.bound_textures,
+        };
+        let mut shade =
+            |varyings: &Varyings| program.cpu.fragment(&program.uniforms, &samplers, varyings);
+
+        match mode {
+            PrimitiveMode::Triangles => {
+                for triangle in vertices.chunks_exact(3) {
+                    raster::triangle(
+                        target,
+                        &clip,
+                        [triangle[0], triangle[1], triangle[2]],
+                        &mut shade,
+                    );
+                }
+            }
+            PrimitiveMode::TriangleStrip => {
+                for triangle in vertices.windows(3) {
+                    raster::triangle(
+                        target,
+                        &clip,
+                        [triangle[0], triangle[1], triangle[2]],
+                        &mut shade,
+                    );
+                }
+            }
+            PrimitiveMode::TriangleFan => {
+                for edge in vertices.windows(2).skip(1) {
+                    raster::triangle(target, &clip, [vertices[0], edge[0], edge[1]], &mut shade);
+                }
+            }
+            PrimitiveMode::Lines => {
+                for line in vertices.chunks_exact(2) {
+                    raster::line(target, &clip, line[0], line[1], &mut shade);
+                }
+            }
+            PrimitiveMode::LineStrip => {
+                for line in vertices.windows(2) {
+                    raster::line(target, &clip, line[0], line[1], &mut shade);
+                }
+            }
+            PrimitiveMode::LineLoop => {
+                for (i, from) in vertices.iter().enumerate() {
+                    let to = vertices[(i + 1) % vertices.len()];
+                    raster::line(target, &clip, *from, to, &mut shade);
+                }
+            }
+        }
+    }
+}
+
+// how the framebuffer's linear floats are stored in RGBA8 images
+fn to_u8(c: f32) -> u8 {
+    (c * 255.0).round() as u8
+}
+
+impl RenderBackend for SoftwareBackend {
+    fn name(&self) -> &'static str {
+        "software"
+    }
+
+    fn set_label(&mut self, _kind: ObjectKind, _id: ObjectId, _label: &str) {}
+
+    fn clear(&mut self, color: glm::Vec4) {
+        self.with_target(self.bound_framebuffer, |_, target| target.clear(color));
+    }
+
+    fn read_pixels(&mut self, width: usize, height: usize) -> Vec<u8> {
+        self.with_target(self.bound_framebuffer, |_, target| {
+            raster::read_rgba8(target, width, height)
+        })
+        .unwrap_or_else(|| vec![0; width * height * 4])
+    }
+
+    fn read_pixels_to_pack_buffer(&mut self, width: usize, height: usize) {
+        let data = self.read_pixels(width, height);
+        if let Some(buffer) = self.bound_buffer_mut(gl::PIXEL_PACK_BUFFER) {
+            let len = data.len().min(buffer.len());
+            buffer[..len].copy_from_slice(&data[..len]);
+        }
+    }
+
+    fn create_buffer(&mut self) -> ObjectId {
+        let buffer = self.next_id();
+        self.buffers.insert(buffer, Vec::new());
+        buffer
+    }
+
+    fn delete_buffer(&mut self, buffer: ObjectId) {
+        self.buffers.remove(&buffer);
+    }
+
+    fn bind_buffer(&mut self, target: gl::types::GLenum, buffer: ObjectId) {
+        match target {
+            gl::ELEMENT_ARRAY_BUFFER => self.vertex_array_mut().element_buffer = buffer,
+            _ => {
+                self.bound_buffers.insert(target, buffer);
+            }
+        }
+    }
+
+    fn bind_buffer_base(&mut self, target: gl::types::GLenum, _index: u32, buffer: ObjectId) {
+        self.bind_buffer(target, buffer);
+    }
+
+    fn buffer_data(
+        &mut self,
+        target: gl::types::GLenum,
+        size: usize,
+        data: Option<&[u8]>,
+        _usage: BufferUsage,
+    ) {
+        if let Some(buffer) = self.bound_buffer_mut(target) {
+            *buffer = vec![0; size];
+            if let Some(data) = data {
+                let len = data.len().min(size);
+                buffer[..len].copy_from_slice(&data[..len]);
+            }
+        }
+    }
+
+    fn buffer_sub_data(&mut self, target: gl::types::GLenum, offset: usize, data: &[u8]) {
+        if let Some(buffer) = self.bound_buffer_mut(target) {
+            if let Some(destination) = buffer.get_mut(offset..offset + data.len()) {
+                destination.copy_from_slice(data);
+            }
+        }
+    }
+
+    fn write_buffer_unsynchronized(
+        &mut self,
+        target: gl::types::GLenum,
+        offset: usize,
+        data: &[u8],
+    ) -> Result<(), String> {
+        let buffer = self
+            .bound_buffer_mut(target)
+            .ok_or_else(|| "no buffer bound to write to".to_string())?;
+        let destination = buffer.get_mut(offset..offset + data.len()).ok_or(format!(
+            "could not map {} bytes at offset {}",
+            data.len(),
+            offset
+        ))?;
+        destination.copy_from_slice(data);
+
+        Ok(())
+    }
+
+    fn read_buffer(&mut self, target: gl::types::GLenum, offset: usize, data: &mut [u8]) {
+        data.fill(0);
+        if let Some(buffer) = self.bound_buffer_mut(target) {
+            if let Some(source) = buffer.get(offset..offset + data.len()) {
+                data.copy_from_slice(source);
+            }
+        }
+    }
+
+    // every draw is finished by the time it returns, so fences are signaled right away
+    fn fence(&mut self) -> FenceId {
+        self.next_id() as FenceId
+    }
+
+    fn wait_fence(&mut self, _fence: FenceId, _timeout: Duration) -> FenceStatus {
+        FenceStatus::Signaled
+    }
+
+    fn delete_fence(&mut self, _fence: FenceId) {}
+
+    fn create_vertex_array(&mut self) -> ObjectId {
+        let vertex_array = self.next_id();
+        self.vertex_arrays
+            .insert(vertex_array, VertexArrayState::default());
+        vertex_array
+    }
+
+    fn delete_vertex_array(&mut self, vertex_array: ObjectId) {
+        if vertex_array == 0 {
+            return;
+        }
+        self.vertex_arrays.remove(&vertex_array);
+        if self.bound_vertex_array == vertex_array {
+            self.bound_vertex_array = 0;
+        }
+    }
+
+    fn bind_vertex_array(&mut self, vertex_array: ObjectId) {
+        self.bound_vertex_array = vertex_array;
+    }
+
+    fn vertex_attrib_pointer(
+        &mut self,
+        location: u32,
+        components: i32,
+        stride: usize,
+        offset: usize,
+    ) {
+        let buffer = self
+            .bound_buffers
+            .get(&gl::ARRAY_BUFFER)
+            .copied()
+            .unwrap_or(0);
+        if let Some(attribute) = self
+            .vertex_array_mut()
+            .attributes
+            .get_mut(location as usize)
+        {
+            *attribute = Some(AttributeFormat {
+                buffer,
+                components: components as usize,
+                stride,
+                offset,
+            });
+        }
+    }
+
+    fn create_texture(&mut self) -> ObjectId {
+        let texture = self.next_id();
+        self.textures.insert(texture, SoftwareTexture::default());
+        texture
+    }
+
+    fn delete_texture(&mut self, texture: ObjectId) {
+        self.textures.remove(&texture);
+    }
+
+    fn active_texture(&mut self, unit: u32) {
+        self.active_unit = unit;
+    }
+
+    fn bind_texture(&mut self, target: TextureTarget, texture: ObjectId) {
+        self.bound_textures
+            .insert((self.active_unit, target), texture);
+    }
+
+    fn texture_image_2d(
+        &mut self,
+        width: usize,
+        height: usize,
+        format: TextureFormat,
+        data: PixelData,
+    ) {
+        if let Some(texture) = self.bound_texture_mut(TextureTarget::Texture2D) {
+            texture.specify(width, height, 1, format, data);
+        }
+    }
+
+    fn texture_sub_image_2d(&mut self, rect: PixelRect, _format: TextureFormat, data: PixelData) {
+        if let Some(texture) = self.bound_texture_mut(TextureTarget::Texture2D) {
+            texture.write(rect, data);
+        }
+    }
+
+    fn texture_image_3d(
+        &mut self,
+        width: usize,
+        height: usize,
+        layers: usize,
+        format: TextureFormat,
+        data: PixelData,
+    ) {
+        if let Some(texture) = self.bound_texture_mut(TextureTarget::Texture2DArray) {
+            texture.specify(width, height, layers, format, data);
+        }
+    }
+
+    fn set_sampling(&mut self, target: TextureTarget, descriptor: &TextureDescriptor) {
+        if let Some(texture) = self.bound_texture_mut(target) {
+            texture.set_descriptor(descriptor);
+        }
+    }
+
+    fn generate_mipmaps(&mut self, _target: TextureTarget) {}
+
+    fn read_texture(&mut self, width: usize, height: usize, format: TextureFormat) -> ImagePixels {
+        match self.bound_texture_mut(TextureTarget::Texture2D) {
+            Some(texture) => texture.read(),
+            None => {
+                let len = width * height * format.channels();
+                if format.is_float() {
+                    ImagePixels::F32(vec![0.0; len])
+                } else {
+                    ImagePixels::U8(vec![0; len])
+                }
+            }
+        }
+    }
+
+    fn create_framebuffer(&mut self) -> ObjectId {
+        let framebuffer = self.next_id();
+        self.framebuffers.insert(framebuffer, 0);
+        framebuffer
+    }
+
+    fn delete_framebuffer(&mut self, framebuffer: ObjectId) {
+        self.framebuffers.remove(&framebuffer);
+        if self.bound_framebuffer == framebuffer {
+            self.bound_framebuffer = 0;
+        }
+    }
+
+    fn bind_framebuffer(&mut self, framebuffer: ObjectId) {
+        self.bound_framebuffer = framebuffer;
+    }
+
+    fn attach_texture(&mut self, texture: ObjectId) -> Result<(), String> {
+        if !self.textures.contains_key(&texture) {
+            return Err(format!("texture {} does not exist", texture));
+        }
+        match self.framebuffers.get_mut(&self.bound_framebuffer) {
+            Some(attached) => {
+                *attached = texture;
+                Ok(())
+            }
+            None => Err("textures can't be attached to the window".to_string()),
+        }
+    }
+
+    fn viewport(&mut self, rect: PixelRect) {
+        self.viewport = rect;
+    }
+
+    // always filters with nearest, which is what scaling pixel art up asks for anyway
+    fn blit_framebuffer(
+        &mut self,
+        source: ObjectId,
+        from: PixelRect,
+        destination: ObjectId,
+        to: PixelRect,
+        _filter: TextureFilter,
+    ) {
+        let pixels = self.with_target(source, |_, target| {
+            let mut pixels = Vec::with_capacity(from.width * from.height);
+            for y in from.y..from.y + from.height {
+                for x in from.x..from.x + from.width {
+                    let inside = x < target.width() && y < target.height();
+                    pixels.push(if inside {
+                        target.pixel(x, y)
+                    } else {
+                        glm::vec4(0.0, 0.0, 0.0, 0.0)
+                    });
+                }
+            }
+            pixels
+        });
+
+        if let Some(pixels) = pixels {
+            self.with_target(destination, |_, target| {
+                raster::blit(&pixels, from, target, to)
+            });
+        }
+        self.bound_framebuffer = destination;
+    }
+
+    fn compile_shader(&mut self, _stage: ShaderStage, _source: &CStr) -> Result<ObjectId, String> {
+        Ok(self.next_id())
+    }
+
+    fn delete_shader(&mut self, _shader: ObjectId) {}
+
+    fn link_program(&mut self, name: &str, _shaders: &[ObjectId]) -> Result<ObjectId, String> {
+        let cpu = CpuProgram::for_asset(name).ok_or(format!(
+            "the software backend has no cpu version of program {}",
+            name
+        ))?;
+
+        let program = self.next_id();
+        self.programs.insert(
+            program,
+            SoftwareProgram {
+                cpu,
+                uniforms: Uniforms::default(),
+            },
+        );
+        Ok(program)
+    }
+
+    fn delete_program(&mut self, program: ObjectId) {
+        self.programs.remove(&program);
+    }
+
+    fn use_program(&mut self, program: ObjectId) {
+        self.used_program = program;
+    }
+
+    fn set_uniform(&mut self, program: ObjectId, name: &CStr, value: Uniform) {
+        if let Some(program) = self.programs.get_mut(&program) {
+            program
+                .uniforms
+                .set(name.to_string_lossy().into_owned(), value);
+        }
+    }
+
+    fn supports_compute(&mut self) -> bool {
+        false
+    }
+
+    fn compute_work_group_size(&mut self, _program: ObjectId) -> [u32; 3] {
+        [1, 1, 1]
+    }
+
+    fn draw_arrays(&mut self, mode: PrimitiveMode, first: usize, count: usize) {
+        self.draw(mode, first..first + count);
+    }
+
+    fn draw_elements(
+        &mut self,
+        mode: PrimitiveMode,
+        index_type: IndexType,
+        first: usize,
+        count: usize,
+        base_vertex: i32,
+    ) {
+        let element_buffer = self.vertex_array().element_buffer;
+        let indices: Vec<usize> = match self.buffers.get(&element_buffer) {
+            Some(data) => data
+                .chunks_exact(index_type.size())
+                .skip(first)
+                .take(count)
+                .map(|bytes| {
+                    let index = match index_type {
+                        IndexType::U8 => bytes[0] as u32,
+                        IndexType::U16 => u16::from_ne_bytes([bytes[0], bytes[1]]) as u32,
+                        IndexType::U32 => {
+                            u32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+                        }
+                    };
+                    (index as i64 + base_vertex as i64).max(0) as usize
+                })
+                .collect(),
+            None => return,
+        };
+
+        self.draw(mode, indices.into_iter());
+    }
+
+    fn dispatch_compute(&mut self, _groups: [u32; 3]) {}
+
+    fn memory_barrier(&mut self, _barriers: gl::types::GLbitfield) {}
+
+    // draws finish as they are issued, there is nothing to time on a GPU
+    fn supports_timer_queries(&mut self) -> bool {
+        false
+    }
+
+    fn create_timer_query(&mut self) -> ObjectId {
+        self.next_id()
+    }
+
+    fn delete_timer_query(&mut self, _query: ObjectId) {}
+
+    fn begin_timer_query(&mut self, _query: ObjectId) {}
+
+    fn end_timer_query(&mut self) {}
+
+    fn timer_query_available(&mut self, _query: ObjectId) -> bool {
+        true
+    }
+
+    fn timer_query_result(&mut self, _query: ObjectId) -> u64 {
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::render::renderer::{batch_renderer::BatchRenderer, SpriteRenderer};
+    use crate::render::texture::Texture;
+    use crate::resources::Resources;
+
+    fn pixel(image: &Image, x: usize, y: usize) -> [u8; 4] {
+        let ImagePixels::U8(data) = &image.pixels else {
+            panic!("software framebuffer images are 8 bit");
+        };
+        let start = (y * image.width + x) * 4;
+        data[start..start + 4].try_into().unwrap()
+    }
+
+    #[test]
+    fn tinted_sprite_under_translucent_quad() {
+        let framebuffer = SoftwareBackend::install(32, 32);
+        // test binaries run from target/<profile>/deps, build.rs copies the assets one level up
+        let res = Resources::from_relative_exe_path(Path::new("../assets")).unwrap();
+        let screen = glm::vec2(32.0, 32.0);
+        let sprites = SpriteRenderer::from_res(&res, screen).unwrap();
+        let mut batch = BatchRenderer::from_res(&res, screen, 16).unwrap();
+        let white = Texture::from_data(vec![255; 4 * 4 * 4], 4, 4).unwrap();
+        framebuffer.clear(glm::vec4(0.0, 0.0, 0.0, 1.0));
+
+        // red sprite over (4, 4)..(12, 12), then a half transparent blue quad over (8, 8)..(16, 16)
+        let full = glm::vec4(0.0, 0.0, 1.0, 1.0);
+        sprites.draw_quad(&white, 4.0, 4.0, 0.0, glm::vec3(1.0, 0.0, 0.0), 2.0, full);
+        batch.begin_scene();
+        batch
+            .draw_quad(
+                &white,
+                glm::vec3(8.0, 8.0, 0.0),
+                glm::vec4(0.0, 0.0, 1.0, 0.5),
+                8.0,
+                full,
+            )
+            .unwrap();
+        batch.end_scene().unwrap();
+
+        let image = framebuffer.to_image();
+        assert_eq!(pixel(&image, 5, 5), [255, 0, 0, 255]);
+        assert_eq!(pixel(&image, 10, 10), [128, 0, 128, 191]);
+        assert_eq!(pixel(&image, 14, 14), [0, 0, 128, 191]);
+        assert_eq!(pixel(&image, 20, 20), [0, 0, 0, 255]);
+    }
+}
@@ -0,0 +1,239 @@
+use super::shaders::{Varyings, MAX_VARYINGS};
+
+use crate::render::texture::PixelRect;
+
+// something primitives can be drawn into, addressed in GL window coordinates (origin at the
+// bottom left): the window's color buffer or a texture attached to a framebuffer
+pub(super) trait RenderTarget {
+    fn width(&self) -> usize;
+    fn height(&self) -> usize;
+    fn pixel(&self, x: usize, y: usize) -> glm::Vec4;
+    fn set_pixel(&mut self, x: usize, y: usize, color: glm::Vec4);
+
+    fn clear(&mut self, color: glm::Vec4) {
+        for y in 0..self.height() {
+            for x in 0..self.width() {
+                self.set_pixel(x, y, color);
+            }
+        }
+    }
+
+    // src alpha / one minus src alpha, the blending the engine sets up for GL
+    fn blend(&mut self, x: usize, y: usize, color: glm::Vec4) {
+        let color = color.map(|c| c.clamp(0.0, 1.0));
+        let blended = color * color.w + self.pixel(x, y) * (1.0 - color.w);
+        self.set_pixel(x, y, blended);
+    }
+}
+
+// RGBA color buffer of the window in linear floats, rows stored top row first like images
+pub(super) struct Framebuffer {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<glm::Vec4>,
+}
+
+impl Framebuffer {
+    pub fn new(width: usize, height: usize) -> Framebuffer {
+        Framebuffer {
+            width,
+            height,
+            pixels: vec![glm::vec4(0.0, 0.0, 0.0, 0.0); width * height],
+        }
+    }
+}
+
+impl RenderTarget for Framebuffer {
+    fn width(&self) -> usize {
+        self.width
+    }
+
+    fn height(&self) -> usize {
+        self.height
+    }
+
+    fn pixel(&self, x: usize, y: usize) -> glm::Vec4 {
+        self.pixels[(self.height - 1 - y) * self.width + x]
+    }
+
+    fn set_pixel(&mut self, x: usize, y: usize, color: glm::Vec4) {
+        self.pixels[(self.height - 1 - y) * self.width + x] = color;
+    }
+
+    fn clear(&mut self, color: glm::Vec4) {
+        self.pixels.fill(color);
+    }
+}
+
+// RGBA8 bytes of the (0, 0, width, height) rectangle of _target_, bottom row first like
+// glReadPixels; what lies outside of it reads as 0
+pub(super) fn read_rgba8(target: &dyn RenderTarget, width: usize, height: usize) -> Vec<u8> {
+    let mut data = vec![0u8; width * height * 4];
+    for y in 0..height.min(target.height()) {
+        for x in 0..width.min(target.width()) {
+            let at = (y * width + x) * 4;
+            for (destination, c) in data[at..at + 4].iter_mut().zip(target.pixel(x, y).iter()) {
+                *destination = super::to_u8(*c);
+            }
+        }
+    }
+    data
+}
+
+// copies _from_ of _source_ scaled into _to_ of _destination_, taking the nearest source pixel
+pub(super) fn blit(
+    source: &[glm::Vec4],
+    from: PixelRect,
+    destination: &mut dyn RenderTarget,
+    to: PixelRect,
+) {
+    if from.width == 0 || from.height == 0 {
+        return;
+    }
+
+    let (width, height) = (destination.width(), destination.height());
+    for y in to.y..(to.y + to.height).min(height) {
+        let source_y = (y - to.y) * from.height / to.height;
+        for x in to.x..(to.x + to.width).min(width) {
+            let source_x = (x - to.x) * from.width / to.width;
+            destination.set_pixel(x, y, source[source_y * from.width + source_x]);
+        }
+    }
+}
+
+// vertex after the viewport transform, in window coordinates
+#[derive(Copy, Clone, Debug)]
+pub(super) struct WindowVertex {
+    pub position: glm::Vec2,
+    pub varyings: Varyings,
+}
+
+impl WindowVertex {
+    pub fn from_clip(clip: glm::Vec4, varyings: Varyings, viewport: &PixelRect) -> Self {
+        let w = if clip.w == 0.0 { 1.0 } else { clip.w };
+        WindowVertex {
+            position: glm::vec2(
+                viewport.x as f32 + (clip.x / w + 1.0) * 0.5 * viewport.width as f32,
+                viewport.y as f32 + (clip.y / w + 1.0) * 0.5 * viewport.height as f32,
+            ),
+            varyings,
+        }
+    }
+}
+
+// pixels of _target_ inside the viewport, which is as far as clip space reaches
+pub(super) fn clip_rect(target: &dyn RenderTarget, viewport: &PixelRect) -> PixelRect {
+    let x = viewport.x.min(target.width());
+    let y = viewport.y.min(target.height());
+    PixelRect {
+        x,
+        y,
+        width: (viewport.x + viewport.width).min(target.width()) - x,
+        height: (viewport.y + viewport.height).min(target.height()) - y,
+    }
+}
+
+// fills the pixels whose centers fall inside the triangle. pixels on an edge shared by two
+// triangles are only drawn by one of them (top-left rule), so translucent quads don't get a
+// darker seam along their diagonal
+pub(super) fn triangle(
+    target: &mut dyn RenderTarget,
+    clip: &PixelRect,
+    vertices: [WindowVertex; 3],
+    shade: &mut dyn FnMut(&Varyings) -> glm::Vec4,
+) {
+    let [a, b, c] = vertices;
+    // make the winding counter clockwise so edge functions are positive inside
+    let (b, c) = if edge(&a.position, &b.position, &c.position) < 0.0 {
+        (c, b)
+    } else {
+        (b, c)
+    };
+    let area = edge(&a.position, &b.position, &c.position);
+    if area == 0.0 {
+        return;
+    }
+
+    let min = a.position.inf(&b.position).inf(&c.position);
+    let max = a.position.sup(&b.position).sup(&c.position);
+    let x0 = (min.x.floor().max(0.0) as usize).max(clip.x);
+    let y0 = (min.y.floor().max(0.0) as usize).max(clip.y);
+    let x1 = (max.x.ceil().max(0.0) as usize)
+        .saturating_add(1)
+        .min(clip.x + clip.width);
+    let y1 = (max.y.ceil().max(0.0) as usize)
+        .saturating_add(1)
+        .min(clip.y + clip.height);
+
+    let edges = [(b, c), (c, a), (a, b)];
+    for y in y0..y1 {
+        for x in x0..x1 {
+            let p = glm::vec2(x as f32 + 0.5, y as f32 + 0.5);
+
+            let mut weights = [0.0; 3];
+            let inside = edges
+                .iter()
+                .zip(weights.iter_mut())
+                .all(|((from, to), weight)| {
+                    *weight = edge(&from.position, &to.position, &p);
+                    *weight > 0.0 || (*weight == 0.0 && is_top_left(&from.position, &to.position))
+                });
+            if !inside {
+                continue;
+            }
+
+            let weights = weights.map(|w| w / area);
+            let mut varyings = [0.0; MAX_VARYINGS];
+            for (i, varying) in varyings.iter_mut().enumerate() {
+                *varying = a.varyings[i] * weights[0]
+                    + b.varyings[i] * weights[1]
+                    + c.varyings[i] * weights[2];
+            }
+
+            let color = shade(&varyings);
+            target.blend(x, y, color);
+        }
+    }
+}
+
+// one pixel wide line from _a_ to _b_, the end pixel is left for the next segment like GL does
+pub(super) fn line(
+    target: &mut dyn RenderTarget,
+    clip: &PixelRect,
+    a: WindowVertex,
+    b: WindowVertex,
+    shade: &mut dyn FnMut(&Varyings) -> glm::Vec4,
+) {
+    let delta = b.position - a.position;
+    let steps = delta.x.abs().max(delta.y.abs()).round() as usize;
+
+    for step in 0..steps {
+        let t = step as f32 / steps as f32;
+        let position = a.position + delta * t;
+
+        let mut varyings = [0.0; MAX_VARYINGS];
+        for (i, varying) in varyings.iter_mut().enumerate() {
+            *varying = a.varyings[i] + (b.varyings[i] - a.varyings[i]) * t;
+        }
+
+        let (x, y) = (position.x.floor(), position.y.floor());
+        let inside = x >= clip.x as f32
+            && y >= clip.y as f32
+            && x < (clip.x + clip.width) as f32
+            && y < (clip.y + clip.height) as f32;
+        if inside {
+            let color = shade(&varyings);
+            target.blend(x as usize, y as usize, color);
+        }
+    }
+}
+
+// twice the signed area of (a, b, p); positive when p is to the left of a -> b
+fn edge(a: &glm::Vec2, b: &glm::Vec2, p: &glm::Vec2) -> f32 {
+    (b.x - a.x) * (p.y - a.y) - (b.y - a.y) * (p.x - a.x)
+}
+
+// with counter clockwise winding and y pointing up, top edges go left and left edges go down
+fn is_top_left(from: &glm::Vec2, to: &glm::Vec2) -> bool {
+    (from.y == to.y && to.x < from.x) || to.y < from.y
+}
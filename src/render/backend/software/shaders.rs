@@ -0,0 +1,209 @@
+use std::collections::HashMap;
+
+use crate::render::backend::Uniform;
+
+// attribute locations a vertex can read from
+pub(super) const MAX_ATTRIBUTES: usize = 8;
+// floats passed from the vertex to the fragment stage, interpolated across primitives
+pub(super) const MAX_VARYINGS: usize = 8;
+
+pub(super) type Attributes = [glm::Vec4; MAX_ATTRIBUTES];
+pub(super) type Varyings = [f32; MAX_VARYINGS];
+
+// the shaders in assets/shaders rewritten in rust. GLSL can't be run on the cpu, so programs are
+// matched to one of these through the asset they were loaded from
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(super) enum CpuProgram {
+    // shaders/texture2d.glsl
+    Sprite,
+    // shaders/shapes/circle2d.glsl
+    Circle,
+    // shaders/batch_texture2d.glsl
+    Batch,
+    // shaders/tile_array2d.glsl
+    TileArray,
+    // shaders/triangle.glsl
+    VertexColor,
+}
+
+// samplers a fragment can read from, resolved by the backend from the bound textures
+pub(super) trait Samplers {
+    fn sample_2d(&self, unit: u32, uv: glm::Vec2) -> glm::Vec4;
+    fn sample_array(&self, unit: u32, uv: glm::Vec2, layer: f32) -> glm::Vec4;
+}
+
+// values set with set_uniform; like in GL, the ones never set read as zero
+#[derive(Default)]
+pub(super) struct Uniforms {
+    values: HashMap<String, Uniform>,
+}
+
+impl Uniforms {
+    pub fn set(&mut self, name: String, value: Uniform) {
+        self.values.insert(name, value);
+    }
+
+    fn float(&self, name: &str) -> f32 {
+        match self.values.get(name) {
+            Some(Uniform::Float(value)) => *value,
+            _ => 0.0,
+        }
+    }
+
+    fn int(&self, name: &str) -> i32 {
+        match self.values.get(name) {
+            Some(Uniform::Int(value)) => *value,
+            _ => 0,
+        }
+    }
+
+    fn vec3(&self, name: &str) -> glm::Vec3 {
+        match self.values.get(name) {
+            Some(Uniform::Vec3(value)) => *value,
+            _ => glm::Vec3::zeros(),
+        }
+    }
+
+    fn vec4(&self, name: &str) -> glm::Vec4 {
+        match self.values.get(name) {
+            Some(Uniform::Vec4(value)) => *value,
+            _ => glm::Vec4::zeros(),
+        }
+    }
+
+    fn mat4(&self, name: &str) -> glm::Mat4 {
+        match self.values.get(name) {
+            Some(Uniform::Mat4(value)) => *value,
+            _ => glm::Mat4::zeros(),
+        }
+    }
+}
+
+impl CpuProgram {
+    pub fn for_asset(name: &str) -> Option<CpuProgram> {
+        match name {
+            "shaders/texture2d.glsl" => Some(CpuProgram::Sprite),
+            "shaders/shapes/circle2d.glsl" => Some(CpuProgram::Circle),
+            "shaders/batch_texture2d.glsl" => Some(CpuProgram::Batch),
+            "shaders/tile_array2d.glsl" => Some(CpuProgram::TileArray),
+            "shaders/triangle.glsl" => Some(CpuProgram::VertexColor),
+            _ => None,
+        }
+    }
+
+    // returns gl_Position and the outputs of the vertex stage
+    pub fn vertex(&self, uniforms: &Uniforms, attributes: &Attributes) -> (glm::Vec4, Varyings) {
+        let mut out = [0.0; MAX_VARYINGS];
+        let position = match self {
+            CpuProgram::Sprite => {
+                let vertex = attributes[0];
+                let sub_tex_coords = uniforms.vec4("subTexCoords");
+                out[0] = sub_tex_coords.x + vertex.z * sub_tex_coords.z;
+                out[1] = sub_tex_coords.y + vertex.w * sub_tex_coords.w;
+
+                let mut model_pos =
+                    uniforms.mat4("model") * glm::vec4(vertex.x, vertex.y, 0.0, 1.0);
+                // Pixel centers are on half-integer boundaries. Add 0.5 for pixel-perfect corners.
+                model_pos.x += uniforms.float("displacement");
+                model_pos.y += uniforms.float("displacement");
+                uniforms.mat4("projection") * model_pos
+            }
+            CpuProgram::Circle => {
+                let vertex = attributes[0];
+                out[0] = vertex.x;
+                out[1] = vertex.y;
+
+                let mut model_pos =
+                    uniforms.mat4("model") * glm::vec4(vertex.x, vertex.y, 0.0, 1.0);
+                model_pos.x += 0.5;
+                model_pos.y += 0.5;
+                uniforms.mat4("projection") * model_pos
+            }
+            CpuProgram::Batch => {
+                out[..4].copy_from_slice(attributes[1].as_slice());
+                out[4] = attributes[2].x;
+                out[5] = attributes[2].y;
+                out[6] = attributes[3].x;
+                let position = attributes[0];
+                uniforms.mat4("projection") * glm::vec4(position.x, position.y, 0.0, 1.0)
+            }
+            CpuProgram::TileArray => {
+                let (position, tex_coords) = (attributes[0], attributes[1]);
+                out[0] = tex_coords.x;
+                out[1] = tex_coords.y;
+                out[2] = attributes[2].x;
+                out[3..7].copy_from_slice(attributes[3].as_slice());
+                uniforms.mat4("projection") * glm::vec4(position.x, position.y, 0.0, 1.0)
+            }
+            CpuProgram::VertexColor => {
+                out[..3].copy_from_slice(&attributes[1].as_slice()[..3]);
+                let position = attributes[0];
+                glm::vec4(position.x, position.y, position.z, 1.0)
+            }
+        };
+
+        (position, out)
+    }
+
+    // color written for a fragment with the interpolated outputs of the vertex stage
+    pub fn fragment(
+        &self,
+        uniforms: &Uniforms,
+        samplers: &dyn Samplers,
+        varyings: &Varyings,
+    ) -> glm::Vec4 {
+        match self {
+            CpuProgram::Sprite => {
+                let color = uniforms.vec3("spriteColor");
+                let texel = samplers.sample_2d(
+                    uniforms.int("image") as u32,
+                    glm::vec2(varyings[0], varyings[1]),
+                );
+                glm::vec4(color.x, color.y, color.z, 1.0).component_mul(&texel)
+            }
+            CpuProgram::Circle => {
+                // the GL shader measures from the center of the screen with a hardcoded
+                // resolution; here the ring is centered on the quad being drawn instead
+                let uv = glm::vec2(varyings[0], varyings[1]);
+                let dist = glm::distance(&glm::vec2(0.5, 0.5), &uv);
+
+                let fade = 0.0;
+                let thickness = 0.1 / 2.0;
+                let mut fade_factor = smoothstep(0.5, 0.5 + fade, 1.0 - dist);
+                fade_factor *= smoothstep(0.5 - thickness, 0.5 - thickness + fade, dist);
+
+                glm::vec4(0.1, 0.2, 0.7, fade_factor)
+            }
+            CpuProgram::Batch => {
+                let color = glm::vec4(varyings[0], varyings[1], varyings[2], varyings[3]);
+                // the slot is the same on every vertex of a quad, round away interpolation error
+                let slot = varyings[6].round() as i32;
+                let texel = samplers.sample_2d(
+                    uniforms.int(&format!("u_Textures[{}]", slot)) as u32,
+                    glm::vec2(varyings[4], varyings[5]),
+                );
+                color.component_mul(&texel)
+            }
+            CpuProgram::TileArray => {
+                let color = glm::vec4(varyings[3], varyings[4], varyings[5], varyings[6]);
+                let texel = samplers.sample_array(
+                    uniforms.int("tiles") as u32,
+                    glm::vec2(varyings[0], varyings[1]),
+                    varyings[2],
+                );
+                color.component_mul(&texel)
+            }
+            CpuProgram::VertexColor => glm::vec4(varyings[0], varyings[1], varyings[2], 1.0),
+        }
+    }
+}
+
+// GLSL smoothstep; an empty edge range acts as a step
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    if edge0 == edge1 {
+        return if x < edge0 { 0.0 } else { 1.0 };
+    }
+
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
@@ -0,0 +1,221 @@
+use super::raster::RenderTarget;
+use crate::render::backend::PixelData;
+use crate::render::image::ImagePixels;
+use crate::render::texture::{
+    PixelRect, TextureDescriptor, TextureFilter, TextureFormat, TextureWrap,
+};
+
+// what sampling outside of the texture returns with ClampToBorder (GL's default border color)
+const BORDER_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.0];
+
+// texels are kept as they would be stored by GL: 8 bit formats are quantized, sRGB ones stay
+// encoded until they are sampled. layers of an array texture follow each other, so a texture
+// with _layers_ layers is stored like a width x (height * layers) one
+pub(super) struct SoftwareTexture {
+    width: usize,
+    height: usize,
+    layers: usize,
+    format: TextureFormat,
+    descriptor: TextureDescriptor,
+    texels: Vec<glm::Vec4>,
+}
+
+impl Default for SoftwareTexture {
+    fn default() -> Self {
+        SoftwareTexture {
+            width: 0,
+            height: 0,
+            layers: 0,
+            format: TextureFormat::RGBA8,
+            descriptor: TextureDescriptor::default(),
+            texels: Vec::new(),
+        }
+    }
+}
+
+impl SoftwareTexture {
+    pub fn specify(
+        &mut self,
+        width: usize,
+        height: usize,
+        layers: usize,
+        format: TextureFormat,
+        data: PixelData,
+    ) {
+        self.width = width;
+        self.height = height;
+        self.layers = layers;
+        self.format = format;
+        self.texels = vec![glm::vec4(0.0, 0.0, 0.0, 0.0); width * height * layers];
+
+        if !matches!(data, PixelData::None) {
+            let all_layers = PixelRect {
+                x: 0,
+                y: 0,
+                width,
+                height: height * layers,
+            };
+            self.write(all_layers, data);
+        }
+    }
+
+    pub fn set_descriptor(&mut self, descriptor: &TextureDescriptor) {
+        self.descriptor = *descriptor;
+    }
+
+    // same unpacking GL does: missing green and blue read as 0, missing alpha as 1
+    pub fn write(&mut self, rect: PixelRect, data: PixelData) {
+        let channels = self.format.channels();
+        for y in 0..rect.height {
+            for x in 0..rect.width {
+                let first = (y * rect.width + x) * channels;
+                let mut texel = glm::vec4(0.0, 0.0, 0.0, 1.0);
+                for channel in 0..channels {
+                    texel[channel] = component(&data, first + channel);
+                }
+
+                let texel = self.quantize(texel);
+                let index = (rect.y + y) * self.width + rect.x + x;
+                if let Some(stored) = self.texels.get_mut(index) {
+                    *stored = texel;
+                }
+            }
+        }
+    }
+
+    // level 0 (the first layer of array textures) in the format's own channel count
+    pub fn read(&self) -> ImagePixels {
+        let channels = self.format.channels();
+        let components = self.texels[..self.width * self.height]
+            .iter()
+            .flat_map(|texel| (0..channels).map(move |channel| texel[channel]));
+
+        if self.format.is_float() {
+            ImagePixels::F32(components.collect())
+        } else {
+            ImagePixels::U8(components.map(|c| (c * 255.0).round() as u8).collect())
+        }
+    }
+
+    // only the magnification filter is emulated; there are no mipmaps to pick a level from
+    pub fn sample(&self, uv: glm::Vec2, layer: f32) -> glm::Vec4 {
+        if self.texels.is_empty() {
+            return glm::vec4(0.0, 0.0, 0.0, 1.0);
+        }
+
+        let layer = (layer.round().max(0.0) as usize).min(self.layers - 1);
+        let x = uv.x * self.width as f32;
+        let y = uv.y * self.height as f32;
+
+        match self.descriptor.mag_filter {
+            TextureFilter::Nearest => self.texel(x.floor() as i64, y.floor() as i64, layer),
+            TextureFilter::Linear => {
+                let (x, y) = (x - 0.5, y - 0.5);
+                let (x0, y0) = (x.floor(), y.floor());
+                let (tx, ty) = (x - x0, y - y0);
+                let (x0, y0) = (x0 as i64, y0 as i64);
+
+                let top = glm::lerp(
+                    &self.texel(x0, y0, layer),
+                    &self.texel(x0 + 1, y0, layer),
+                    tx,
+                );
+                let bottom = glm::lerp(
+                    &self.texel(x0, y0 + 1, layer),
+                    &self.texel(x0 + 1, y0 + 1, layer),
+                    tx,
+                );
+                glm::lerp(&top, &bottom, ty)
+            }
+        }
+    }
+
+    fn texel(&self, x: i64, y: i64, layer: usize) -> glm::Vec4 {
+        let x = wrap(x, self.width, self.descriptor.wrap_s);
+        let y = wrap(y, self.height, self.descriptor.wrap_t);
+        let texel = match (x, y) {
+            (Some(x), Some(y)) => self.texels[(layer * self.height + y) * self.width + x],
+            _ => glm::Vec4::from(BORDER_COLOR),
+        };
+
+        if self.format.is_srgb() {
+            glm::vec4(
+                srgb_to_linear(texel.x),
+                srgb_to_linear(texel.y),
+                srgb_to_linear(texel.z),
+                texel.w,
+            )
+        } else {
+            texel
+        }
+    }
+
+    fn quantize(&self, texel: glm::Vec4) -> glm::Vec4 {
+        if self.format.is_float() {
+            texel
+        } else {
+            texel.map(|c| (c.clamp(0.0, 1.0) * 255.0).round() / 255.0)
+        }
+    }
+}
+
+// rendering to a texture writes its first layer; rows count from the bottom like window
+// coordinates do, which is also how GL lays out texture rows
+impl RenderTarget for SoftwareTexture {
+    fn width(&self) -> usize {
+        self.width
+    }
+
+    fn height(&self) -> usize {
+        self.height
+    }
+
+    fn pixel(&self, x: usize, y: usize) -> glm::Vec4 {
+        self.texels[y * self.width + x]
+    }
+
+    fn set_pixel(&mut self, x: usize, y: usize, color: glm::Vec4) {
+        self.texels[y * self.width + x] = self.quantize(color);
+    }
+}
+
+fn component(data: &PixelData, index: usize) -> f32 {
+    match data {
+        PixelData::None => 0.0,
+        PixelData::U8(data) => data.get(index).map_or(0.0, |&c| c as f32 / 255.0),
+        PixelData::F32(data) => data.get(index).copied().unwrap_or(0.0),
+    }
+}
+
+// texel index for coordinate _i_ of a _size_ texels wide axis, None when it falls on the border
+fn wrap(i: i64, size: usize, mode: TextureWrap) -> Option<usize> {
+    let size = size as i64;
+    let wrapped = match mode {
+        TextureWrap::Repeat => i.rem_euclid(size),
+        TextureWrap::MirroredRepeat => {
+            let i = i.rem_euclid(2 * size);
+            if i < size {
+                i
+            } else {
+                2 * size - 1 - i
+            }
+        }
+        TextureWrap::ClampToEdge => i.clamp(0, size - 1),
+        TextureWrap::ClampToBorder => {
+            if i < 0 || i >= size {
+                return None;
+            }
+            i
+        }
+    };
+
+    Some(wrapped as usize)
+}
+
+fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
@@ -1,79 +1,341 @@
+use std::marker::PhantomData;
+use std::time::Duration;
+
 use gl;
 
-pub type VertexBuffer = Buffer<{ gl::ARRAY_BUFFER }>;
-pub type ElementBuffer = Buffer<{ gl::ELEMENT_ARRAY_BUFFER }>;
-//pub type SSVertexBuffer = Buffer<{gl::SHADER_STORAGE_BUFFER}>;
+use super::backend::{self, BufferUsage, FenceId, FenceStatus};
+use super::debug::ObjectKind;
+use super::stats;
+
+pub type VertexBuffer<T> = Buffer<{ gl::ARRAY_BUFFER }, T>;
+pub type ElementBuffer<T = gl::types::GLuint> = Buffer<{ gl::ELEMENT_ARRAY_BUFFER }, T>;
+// only usable on GL 4.3+ contexts, see ComputeProgram::is_supported
+pub type ShaderStorageBuffer<T> = Buffer<{ gl::SHADER_STORAGE_BUFFER }, T>;
+// destination of asynchronous pixel readback, see RenderBackend::read_pixels_to_pack_buffer
+pub type PixelPackBuffer = Buffer<{ gl::PIXEL_PACK_BUFFER }, u8>;
+pub type StreamingVertexBuffer<T> = StreamingBuffer<{ gl::ARRAY_BUFFER }, T>;
 
-pub struct Buffer<const B: gl::types::GLuint> {
+// how long a single wait on a fence blocks; waits are retried until the fence signals
+const FENCE_TIMEOUT: Duration = Duration::from_millis(100);
+
+// owns a GL buffer object holding elements of type T; the GL object is deleted on drop.
+// len is the amount of elements last written, capacity the amount of elements allocated on the GPU
+pub struct Buffer<const B: gl::types::GLuint, T> {
     vbo: gl::types::GLuint,
+    len: usize,
+    capacity: usize,
+    _element: PhantomData<T>,
 }
 
-impl<const B: gl::types::GLuint> Buffer<B> {
-    pub fn new() -> Buffer<{ B }> {
-        let mut vbo: gl::types::GLuint = 0;
-        println!("creado vbo {}", B);
-        unsafe {
-            gl::GenBuffers(1, &mut vbo);
+impl<const B: gl::types::GLuint, T> Buffer<B, T> {
+    pub fn new() -> Buffer<{ B }, T> {
+        Buffer {
+            vbo: backend::with(|backend| backend.create_buffer()),
+            len: 0,
+            capacity: 0,
+            _element: PhantomData,
         }
-        println!("creado vbo id {}", vbo);
+    }
+
+    pub fn id(&self) -> gl::types::GLuint {
+        self.vbo
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
 
-        Buffer { vbo }
+    pub fn size_in_bytes(&self) -> usize {
+        self.capacity * std::mem::size_of::<T>()
+    }
+
+    // names the buffer in GL debug messages and debuggers; it must have been bound once
+    pub fn set_label(&self, label: &str) {
+        backend::with(|backend| backend.set_label(ObjectKind::Buffer, self.vbo, label));
     }
 
     pub fn bind(&self) {
-        unsafe {
-            gl::BindBuffer(B, self.vbo);
-        }
+        backend::with(|backend| backend.bind_buffer(B, self.vbo));
     }
 
     pub fn unbind(&self) {
-        unsafe {
-            gl::BindBuffer(B, 0);
-        }
+        backend::with(|backend| backend.bind_buffer(B, 0));
     }
 
-    pub fn upload_data_static_draw<T>(&self, data: &[T]) {
-        unsafe {
-            gl::BufferData(
-                B,
-                (data.len() * std::mem::size_of::<T>()) as gl::types::GLsizeiptr,
-                data.as_ptr() as *const gl::types::GLvoid,
-                gl::STATIC_DRAW,
-            )
-        }
+    // binds the buffer to binding point _index_ of an indexed target (shader storage, uniform),
+    // matching `layout(binding = index)` in the shader
+    pub fn bind_base(&self, index: u32) {
+        backend::with(|backend| backend.bind_buffer_base(B, index, self.vbo));
     }
 
-    pub fn upload_data_dynamic_draw<T>(&self, data: &Vec<T>) {
-        unsafe {
-            gl::BufferData(
-                B,
-                (data.capacity() * std::mem::size_of::<T>()) as gl::types::GLsizeiptr,
-                std::ptr::null() as *const _,
-                gl::DYNAMIC_DRAW,
-            )
+    // (re)allocates the buffer to fit exactly _data_ and uploads it; buffer must be bound
+    pub fn upload_data_static_draw(&mut self, data: &[T]) {
+        self.allocate(data.len(), Some(as_bytes(data)), BufferUsage::StaticDraw);
+        self.len = data.len();
+        stats::record_upload(std::mem::size_of_val(data));
+    }
+
+    // (re)allocates room for _capacity_ elements without uploading anything; buffer must be bound
+    pub fn upload_data_dynamic_draw(&mut self, capacity: usize) {
+        self.allocate(capacity, None, BufferUsage::DynamicDraw);
+        self.len = 0;
+    }
+
+    // writes _data_ starting at element _offset_; fails instead of writing past the allocated size.
+    // buffer must be bound
+    pub fn upload_subdata(&mut self, offset: usize, data: &[T]) -> Result<(), String> {
+        let end = offset + data.len();
+        if end > self.capacity {
+            return Err(format!(
+                "buffer {} overflow: writing elements {}..{} but capacity is {}",
+                self.vbo, offset, end, self.capacity
+            ));
         }
+
+        backend::with(|backend| {
+            backend.buffer_sub_data(B, offset * std::mem::size_of::<T>(), as_bytes(data))
+        });
+        self.len = self.len.max(end);
+        stats::record_upload(std::mem::size_of_val(data));
+
+        Ok(())
+    }
+
+    // (re)allocates room for _capacity_ elements meant to be written by the GPU (compute shaders)
+    // and read back or drawn from; buffer must be bound
+    pub fn upload_data_dynamic_copy(&mut self, capacity: usize) {
+        self.allocate(capacity, None, BufferUsage::DynamicCopy);
+        self.len = capacity;
     }
 
-    pub fn upload_subdata_dynamic_draw<T>(&self, data: &[T], amount_of_verts: usize) {
+    // (re)allocates room for _capacity_ elements the GPU writes once and we read back once,
+    // like frames captured through a pixel pack buffer; buffer must be bound
+    pub fn upload_data_stream_read(&mut self, capacity: usize) {
+        self.allocate(capacity, None, BufferUsage::StreamRead);
+        self.len = capacity;
+    }
+
+    fn allocate(&mut self, capacity: usize, data: Option<&[u8]>, usage: BufferUsage) {
+        backend::with(|backend| {
+            backend.buffer_data(B, capacity * std::mem::size_of::<T>(), data, usage)
+        });
+        self.capacity = capacity;
+    }
+}
+
+impl<const B: gl::types::GLuint, T: Copy> Buffer<B, T> {
+    // copies the first len() elements back from the GPU; this stalls until pending writes are
+    // done, so it is meant for tooling and debugging rather than per frame use. buffer must be bound
+    pub fn download(&self) -> Vec<T> {
+        let mut bytes = vec![0u8; self.len * std::mem::size_of::<T>()];
+        backend::with(|backend| backend.read_buffer(B, 0, &mut bytes));
+
+        let mut data: Vec<T> = Vec::with_capacity(self.len);
         unsafe {
-            //     println!("subdata upload {}, deref {:p}", amount_of_verts * std::mem::size_of::<T>(), (data.get(0).expect("msg").as_ptr() as *const f32)  );
-            gl::BufferSubData(
-                B,
-                0,
-                (amount_of_verts * std::mem::size_of::<T>()) as gl::types::GLsizeiptr,
-                data.as_ptr() as *const gl::types::GLvoid,
+            // bytes has no alignment guarantees for T, copy them into place instead of casting
+            std::ptr::copy_nonoverlapping(
+                bytes.as_ptr(),
+                data.as_mut_ptr() as *mut u8,
+                bytes.len(),
             );
+            data.set_len(self.len);
         }
+
+        data
     }
 }
-/*
-impl<const B:gl::types::GLuint> Drop for Buffer<B> {
+
+impl<const B: gl::types::GLuint, T> Default for Buffer<B, T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const B: gl::types::GLuint, T> Drop for Buffer<B, T> {
     fn drop(&mut self) {
-        unsafe {
-            gl::DeleteBuffers(1, &self.vbo);
+        backend::with(|backend| backend.delete_buffer(self.vbo));
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum StreamingMode {
+    // re-specify the whole data store before each write so the driver can hand us fresh memory
+    // instead of waiting for draws still reading the old one
+    Orphaning,
+    // split the buffer in _regions_ and write them round robin with unsynchronized mapping;
+    // a fence per region makes sure we never write where the GPU may still be reading
+    RingBuffer { regions: usize },
+}
+
+// buffer meant to be rewritten every frame (e.g. batched quads) without stalling on the driver.
+// each call to stream() returns the element offset the data landed at, which has to be passed
+// as base vertex/offset to the draw call that consumes it
+pub struct StreamingBuffer<const B: gl::types::GLuint, T> {
+    buffer: Buffer<B, T>,
+    mode: StreamingMode,
+    region_capacity: usize,
+    current_region: usize,
+    fences: Vec<Option<FenceId>>,
+}
+
+impl<const B: gl::types::GLuint, T> StreamingBuffer<B, T> {
+    // creates the buffer and allocates room for _region_capacity_ elements per region
+    pub fn new(region_capacity: usize, mode: StreamingMode) -> StreamingBuffer<{ B }, T> {
+        let regions = match mode {
+            StreamingMode::Orphaning => 1,
+            StreamingMode::RingBuffer { regions } => regions.max(1),
+        };
+
+        let mut buffer = Buffer::new();
+        buffer.bind();
+        buffer.allocate(
+            region_capacity * regions,
+            None,
+            BufferUsage::StreamDraw,
+        );
+        buffer.unbind();
+
+        StreamingBuffer {
+            buffer,
+            mode,
+            region_capacity,
+            current_region: regions - 1,
+            fences: vec![None; regions],
         }
     }
-} */
+
+    pub fn mode(&self) -> StreamingMode {
+        self.mode
+    }
+
+    pub fn region_capacity(&self) -> usize {
+        self.region_capacity
+    }
+
+    pub fn set_label(&self, label: &str) {
+        self.buffer.set_label(label);
+    }
+
+    pub fn bind(&self) {
+        self.buffer.bind();
+    }
+
+    pub fn unbind(&self) {
+        self.buffer.unbind();
+    }
+
+    // writes _data_ into the next free region and returns the element offset it was written at.
+    // buffer must be bound
+    pub fn stream(&mut self, data: &[T]) -> Result<usize, String> {
+        if data.len() > self.region_capacity {
+            return Err(format!(
+                "streaming buffer {} overflow: writing {} elements but regions hold {}",
+                self.buffer.id(),
+                data.len(),
+                self.region_capacity
+            ));
+        }
+
+        let element_size = std::mem::size_of::<T>();
+
+        match self.mode {
+            StreamingMode::Orphaning => {
+                self.buffer.allocate(
+                    self.region_capacity,
+                    None,
+                    BufferUsage::StreamDraw,
+                );
+                self.buffer.upload_subdata(0, data)?;
+
+                Ok(0)
+            }
+            StreamingMode::RingBuffer { .. } => {
+                self.current_region = (self.current_region + 1) % self.fences.len();
+                self.wait_for_region(self.current_region)?;
+
+                let offset = self.current_region * self.region_capacity;
+                if data.is_empty() {
+                    return Ok(offset);
+                }
+
+                let bytes = as_bytes(data);
+                backend::with(|backend| {
+                    backend.write_buffer_unsynchronized(B, offset * element_size, bytes)
+                })
+                .map_err(|e| {
+                    format!(
+                        "could not write region {} of streaming buffer {}: {}",
+                        self.current_region,
+                        self.buffer.id(),
+                        e
+                    )
+                })?;
+                self.buffer.len = offset + data.len();
+                stats::record_upload(bytes.len());
+
+                Ok(offset)
+            }
+        }
+    }
+
+    // must be called after issuing the draws that read the last streamed region,
+    // so the next time we come around to it we know whether the GPU is done with it
+    pub fn fence(&mut self) {
+        if let StreamingMode::RingBuffer { .. } = self.mode {
+            backend::with(|backend| {
+                if let Some(old) = self.fences[self.current_region].take() {
+                    backend.delete_fence(old);
+                }
+                self.fences[self.current_region] = Some(backend.fence());
+            });
+        }
+    }
+
+    // blocks until the GPU is done reading _region_. a slow frame only makes us wait longer,
+    // only a failed wait is an error
+    fn wait_for_region(&mut self, region: usize) -> Result<(), String> {
+        let fence = match self.fences[region].take() {
+            Some(fence) => fence,
+            None => return Ok(()),
+        };
+
+        let status = backend::with(|backend| {
+            let mut status = backend.wait_fence(fence, FENCE_TIMEOUT);
+            while status == FenceStatus::TimedOut {
+                status = backend.wait_fence(fence, FENCE_TIMEOUT);
+            }
+            backend.delete_fence(fence);
+            status
+        });
+
+        match status {
+            FenceStatus::Failed => Err(format!(
+                "error waiting for region {} of streaming buffer {}",
+                region,
+                self.buffer.id()
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl<const B: gl::types::GLuint, T> Drop for StreamingBuffer<B, T> {
+    fn drop(&mut self) {
+        backend::with(|backend| {
+            for fence in self.fences.iter().flatten() {
+                backend.delete_fence(*fence);
+            }
+        });
+    }
+}
 
 pub struct VertexArray {
     vao: gl::types::GLuint,
@@ -81,31 +343,42 @@ pub struct VertexArray {
 
 impl VertexArray {
     pub fn new() -> VertexArray {
-        let mut vao: gl::types::GLuint = 0;
-        unsafe {
-            gl::GenVertexArrays(1, &mut vao);
+        VertexArray {
+            vao: backend::with(|backend| backend.create_vertex_array()),
         }
+    }
+
+    pub fn id(&self) -> gl::types::GLuint {
+        self.vao
+    }
 
-        VertexArray { vao }
+    // names the vertex array in GL debug messages and debuggers; it must have been bound once
+    pub fn set_label(&self, label: &str) {
+        backend::with(|backend| backend.set_label(ObjectKind::VertexArray, self.vao, label));
     }
 
     pub fn bind(&self) {
-        unsafe {
-            gl::BindVertexArray(self.vao);
-        }
+        backend::with(|backend| backend.bind_vertex_array(self.vao));
     }
 
     pub fn unbind(&self) {
-        unsafe {
-            gl::BindVertexArray(0);
-        }
+        backend::with(|backend| backend.bind_vertex_array(0));
+    }
+}
+
+impl Default for VertexArray {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
-/* impl Drop for VertexArray {
+impl Drop for VertexArray {
     fn drop(&mut self) {
-        unsafe {
-            gl::DeleteVertexArrays(1, &mut self.vao);
-        }
+        backend::with(|backend| backend.delete_vertex_array(self.vao));
     }
-} */
+}
+
+// views the elements of _data_ as the bytes they are made of, which is what gets sent to the GPU
+fn as_bytes<T>(data: &[T]) -> &[u8] {
+    unsafe { std::slice::from_raw_parts(data.as_ptr() as *const u8, std::mem::size_of_val(data)) }
+}
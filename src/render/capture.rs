@@ -0,0 +1,504 @@
+use std::collections::VecDeque;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use super::backend::{self, FenceId, FenceStatus};
+use super::buffer::PixelPackBuffer;
+use super::image::{Image, ImagePixels};
+
+// frames being read back at the same time. by the time we come around to a buffer again the GPU
+// is long done writing it, so recording doesn't wait on the driver
+const PACK_BUFFERS: usize = 3;
+// how long we block on a readback when its buffer is needed again, or when recording stops
+const READBACK_TIMEOUT: Duration = Duration::from_millis(100);
+// 1 is the best and slowest color quantization, 30 the fastest
+const GIF_QUANTIZE_SPEED: i32 = 10;
+// recorded frames waiting for the encoder. gif encoding is slower than recording at 30 fps, so
+// frames that don't fit are dropped rather than piling up in memory
+const ENCODE_QUEUE: usize = 8;
+// browsers play anything faster than 2 hundredths of a second at 10 fps
+const MIN_GIF_DELAY_CS: u16 = 2;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RecordingFormat {
+    // numbered PNG files in a directory of their own
+    PngSequence,
+    Gif,
+}
+
+// work for the encoder thread. images arrive as GL reads them, bottom row first
+enum EncodeJob {
+    Png {
+        path: PathBuf,
+        image: Image,
+    },
+    StartGif {
+        path: PathBuf,
+        width: usize,
+        height: usize,
+    },
+    GifFrame {
+        image: Image,
+        delay: Duration,
+    },
+    FinishGif,
+}
+
+// readback issued into pack_buffers[buffer], ready once _fence_ is signaled
+struct PendingFrame {
+    buffer: usize,
+    fence: FenceId,
+    captured_at: Instant,
+}
+
+struct Recording {
+    format: RecordingFormat,
+    // the gif file, or the directory the png sequence is written to
+    path: PathBuf,
+    pack_buffers: Vec<PixelPackBuffer>,
+    next_buffer: usize,
+    pending: VecDeque<PendingFrame>,
+    last_capture: Option<Instant>,
+    last_encoded: Option<Instant>,
+    frames: usize,
+    // frames dropped because the encoder was behind
+    dropped: usize,
+}
+
+// screenshots and recordings of what gets rendered to the window. reading pixels and encoding
+// them is kept off the frame: recordings read back through pixel pack buffers collected a few
+// frames later, and all encoding happens on a thread of its own.
+// capture_frame() has to be called once per frame, after drawing and before swapping buffers
+pub struct FrameCapture {
+    width: usize,
+    height: usize,
+    directory: PathBuf,
+    frame_interval: Duration,
+    screenshot_requested: bool,
+    recording: Option<Recording>,
+    jobs: Option<SyncSender<EncodeJob>>,
+    encoder: Option<JoinHandle<()>>,
+}
+
+impl FrameCapture {
+    // captures the (0, 0, width, height) rectangle of the window into files under _directory_
+    pub fn new(width: usize, height: usize, directory: impl AsRef<Path>) -> FrameCapture {
+        let (jobs, receiver) = mpsc::sync_channel(ENCODE_QUEUE);
+        let encoder = thread::Builder::new()
+            .name("frame capture encoder".to_string())
+            .spawn(move || encode(receiver))
+            .expect("could not start the frame capture encoder thread");
+
+        FrameCapture {
+            width,
+            height,
+            directory: directory.as_ref().to_path_buf(),
+            frame_interval: Duration::from_secs(1) / 30,
+            screenshot_requested: false,
+            recording: None,
+            jobs: Some(jobs),
+            encoder: Some(encoder),
+        }
+    }
+
+    // minimum time between two recorded frames, 1/30 s by default
+    pub fn set_frame_interval(&mut self, interval: Duration) {
+        self.frame_interval = interval;
+    }
+
+    // saves the next captured frame as <directory>/screenshot-<timestamp>.png
+    pub fn request_screenshot(&mut self) {
+        self.screenshot_requested = true;
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    // records to <directory>/recording-<timestamp>.gif, or to numbered files in a
+    // <directory>/recording-<timestamp> directory for png sequences
+    pub fn start_recording(&mut self, format: RecordingFormat) -> Result<(), String> {
+        if self.recording.is_some() {
+            return Err("already recording".to_string());
+        }
+        fs::create_dir_all(&self.directory).map_err(|e| {
+            format!(
+                "could not create capture directory {}: {}",
+                self.directory.display(),
+                e
+            )
+        })?;
+
+        let name = format!("recording-{}", timestamp());
+        let path = match format {
+            RecordingFormat::PngSequence => {
+                let path = self.directory.join(&name);
+                fs::create_dir_all(&path)
+                    .map_err(|e| format!("could not create directory {}: {}", path.display(), e))?;
+                path
+            }
+            RecordingFormat::Gif => {
+                if self.width > u16::MAX as usize || self.height > u16::MAX as usize {
+                    return Err(format!(
+                        "{}x{} is too large for a gif",
+                        self.width, self.height
+                    ));
+                }
+
+                let path = self.directory.join(format!("{}.gif", name));
+                self.send(EncodeJob::StartGif {
+                    path: path.clone(),
+                    width: self.width,
+                    height: self.height,
+                });
+                path
+            }
+        };
+
+        let pack_buffers = (0..PACK_BUFFERS)
+            .map(|i| {
+                let mut buffer = PixelPackBuffer::new();
+                buffer.bind();
+                buffer.upload_data_stream_read(self.width * self.height * 4);
+                buffer.set_label(&format!("frame capture {}", i));
+                buffer.unbind();
+                buffer
+            })
+            .collect();
+
+        println!("recording {}", path.display());
+        self.recording = Some(Recording {
+            format,
+            path,
+            pack_buffers,
+            next_buffer: 0,
+            pending: VecDeque::new(),
+            last_capture: None,
+            last_encoded: None,
+            frames: 0,
+            dropped: 0,
+        });
+
+        Ok(())
+    }
+
+    // collects the frames still being read back and closes the recording
+    pub fn stop_recording(&mut self) {
+        let mut recording = match self.recording.take() {
+            Some(recording) => recording,
+            None => return,
+        };
+
+        while !recording.pending.is_empty() {
+            self.collect(&mut recording, READBACK_TIMEOUT);
+        }
+        if recording.format == RecordingFormat::Gif {
+            self.send(EncodeJob::FinishGif);
+        }
+
+        println!(
+            "recorded {} frames to {}, dropped {} the encoder couldn't keep up with",
+            recording.frames,
+            recording.path.display(),
+            recording.dropped
+        );
+    }
+
+    pub fn toggle_recording(&mut self, format: RecordingFormat) -> Result<(), String> {
+        if self.is_recording() {
+            self.stop_recording();
+            Ok(())
+        } else {
+            self.start_recording(format)
+        }
+    }
+
+    pub fn capture_frame(&mut self) {
+        if self.screenshot_requested {
+            self.screenshot_requested = false;
+            self.screenshot();
+        }
+
+        let mut recording = match self.recording.take() {
+            Some(recording) => recording,
+            None => return,
+        };
+
+        // hand over everything that finished reading back without waiting on what didn't
+        while !recording.pending.is_empty() {
+            if !self.collect(&mut recording, Duration::ZERO) {
+                break;
+            }
+        }
+
+        let now = Instant::now();
+        let due = recording
+            .last_capture
+            .is_none_or(|last| now - last >= self.frame_interval);
+        if due {
+            // every buffer is still in flight, the oldest one has to be done before it's reused
+            if recording.pending.len() == recording.pack_buffers.len() {
+                self.collect(&mut recording, READBACK_TIMEOUT);
+            }
+
+            let buffer = recording.next_buffer;
+            recording.next_buffer = (buffer + 1) % recording.pack_buffers.len();
+            recording.pack_buffers[buffer].bind();
+            let fence = backend::with(|backend| {
+                backend.read_pixels_to_pack_buffer(self.width, self.height);
+                backend.fence()
+            });
+            recording.pack_buffers[buffer].unbind();
+
+            recording.pending.push_back(PendingFrame {
+                buffer,
+                fence,
+                captured_at: now,
+            });
+            recording.last_capture = Some(now);
+        }
+
+        self.recording = Some(recording);
+    }
+
+    fn screenshot(&mut self) {
+        if let Err(e) = fs::create_dir_all(&self.directory) {
+            println!(
+                "could not create capture directory {}: {}",
+                self.directory.display(),
+                e
+            );
+            return;
+        }
+
+        let pixels = backend::with(|backend| backend.read_pixels(self.width, self.height));
+        let path = self
+            .directory
+            .join(format!("screenshot-{}.png", timestamp()));
+        println!("saving screenshot {}", path.display());
+        let image = self.image(pixels);
+        self.send(EncodeJob::Png { path, image });
+    }
+
+    // waits up to _timeout_ for the oldest pending readback and hands it to the encoder;
+    // returns whether it was done. frames that can't be read back are dropped
+    fn collect(&mut self, recording: &mut Recording, timeout: Duration) -> bool {
+        let frame = match recording.pending.front() {
+            Some(frame) => frame,
+            None => return false,
+        };
+
+        let status = backend::with(|backend| backend.wait_fence(frame.fence, timeout));
+        if status == FenceStatus::TimedOut && timeout == Duration::ZERO {
+            return false;
+        }
+
+        let frame = recording.pending.pop_front().unwrap();
+        backend::with(|backend| backend.delete_fence(frame.fence));
+        if status != FenceStatus::Signaled {
+            println!(
+                "dropping frame {} of {}: readback {:?}",
+                recording.frames,
+                recording.path.display(),
+                status
+            );
+            return true;
+        }
+
+        let buffer = &recording.pack_buffers[frame.buffer];
+        buffer.bind();
+        let pixels = buffer.download();
+        buffer.unbind();
+        let image = self.image(pixels);
+
+        let job = match recording.format {
+            RecordingFormat::PngSequence => EncodeJob::Png {
+                path: recording
+                    .path
+                    .join(format!("frame-{:05}.png", recording.frames)),
+                image,
+            },
+            RecordingFormat::Gif => EncodeJob::GifFrame {
+                image,
+                delay: recording
+                    .last_encoded
+                    .map_or(self.frame_interval, |last| frame.captured_at - last),
+            },
+        };
+        // a dropped gif frame is made up for by a longer delay on the next one
+        if self.try_send(job) {
+            recording.last_encoded = Some(frame.captured_at);
+            recording.frames += 1;
+        } else {
+            recording.dropped += 1;
+        }
+
+        true
+    }
+
+    fn image(&self, pixels: Vec<u8>) -> Image {
+        Image {
+            width: self.width,
+            height: self.height,
+            channels: 4,
+            pixels: ImagePixels::U8(pixels),
+        }
+    }
+
+    // queues _job_, waiting for room if the encoder is behind
+    fn send(&self, job: EncodeJob) {
+        let sent = self
+            .jobs
+            .as_ref()
+            .is_some_and(|jobs| jobs.send(job).is_ok());
+        if !sent {
+            println!("frame capture encoder is gone, dropping capture");
+        }
+    }
+
+    // queues _job_ only if there is room right away; returns whether it was queued
+    fn try_send(&self, job: EncodeJob) -> bool {
+        match self.jobs.as_ref().map(|jobs| jobs.try_send(job)) {
+            Some(Ok(())) => true,
+            Some(Err(TrySendError::Full(_))) => false,
+            _ => {
+                println!("frame capture encoder is gone, dropping capture");
+                false
+            }
+        }
+    }
+}
+
+impl Drop for FrameCapture {
+    // the encoder thread finishes writing what was queued before we return
+    fn drop(&mut self) {
+        self.stop_recording();
+        self.jobs = None;
+        if let Some(encoder) = self.encoder.take() {
+            let _ = encoder.join();
+        }
+    }
+}
+
+// milliseconds since the unix epoch, which keeps capture file names unique and sorted
+fn timestamp() -> String {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis())
+        .to_string()
+}
+
+fn encode(jobs: Receiver<EncodeJob>) {
+    let mut gif: Option<(PathBuf, gif::Encoder<BufWriter<File>>)> = None;
+
+    for job in jobs {
+        let result = match job {
+            EncodeJob::Png { path, mut image } => {
+                image.flip_vertically();
+                write_png(&path, &image)
+            }
+            EncodeJob::StartGif {
+                path,
+                width,
+                height,
+            } => start_gif(&path, width, height).map(|encoder| gif = Some((path, encoder))),
+            EncodeJob::GifFrame { mut image, delay } => match &mut gif {
+                Some((path, encoder)) => {
+                    image.flip_vertically();
+                    write_gif_frame(encoder, image, delay)
+                        .map_err(|e| format!("could not write {}: {}", path.display(), e))
+                }
+                None => Ok(()),
+            },
+            EncodeJob::FinishGif => match gif.take() {
+                Some((path, encoder)) => finish_gif(encoder)
+                    .map_err(|e| format!("could not write {}: {}", path.display(), e)),
+                None => Ok(()),
+            },
+        };
+
+        if let Err(e) = result {
+            println!("frame capture: {}", e);
+        }
+    }
+}
+
+fn write_png(path: &Path, image: &Image) -> Result<(), String> {
+    let data = match &image.pixels {
+        ImagePixels::U8(data) => data,
+        ImagePixels::F32(_) => return Err("only 8 bit images can be saved as png".to_string()),
+    };
+    let color = match image.channels {
+        1 => png::ColorType::Grayscale,
+        2 => png::ColorType::GrayscaleAlpha,
+        3 => png::ColorType::Rgb,
+        4 => png::ColorType::Rgba,
+        channels => return Err(format!("can't save {} channel images as png", channels)),
+    };
+
+    let file =
+        File::create(path).map_err(|e| format!("could not create {}: {}", path.display(), e))?;
+    let mut encoder = png::Encoder::new(
+        BufWriter::new(file),
+        image.width as u32,
+        image.height as u32,
+    );
+    encoder.set_color(color);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let mut writer = encoder
+        .write_header()
+        .map_err(|e| format!("could not write {}: {}", path.display(), e))?;
+    writer
+        .write_image_data(data)
+        .map_err(|e| format!("could not write {}: {}", path.display(), e))?;
+    writer
+        .finish()
+        .map_err(|e| format!("could not write {}: {}", path.display(), e))
+}
+
+fn start_gif(
+    path: &Path,
+    width: usize,
+    height: usize,
+) -> Result<gif::Encoder<BufWriter<File>>, String> {
+    let file =
+        File::create(path).map_err(|e| format!("could not create {}: {}", path.display(), e))?;
+    let mut encoder = gif::Encoder::new(BufWriter::new(file), width as u16, height as u16, &[])
+        .map_err(|e| format!("could not write {}: {}", path.display(), e))?;
+    encoder
+        .set_repeat(gif::Repeat::Infinite)
+        .map_err(|e| format!("could not write {}: {}", path.display(), e))?;
+
+    Ok(encoder)
+}
+
+fn write_gif_frame(
+    encoder: &mut gif::Encoder<BufWriter<File>>,
+    image: Image,
+    delay: Duration,
+) -> Result<(), String> {
+    let mut data = match image.pixels {
+        ImagePixels::U8(data) if image.channels == 4 => data,
+        _ => return Err("gif frames have to be RGBA8".to_string()),
+    };
+
+    let mut frame = gif::Frame::from_rgba_speed(
+        image.width as u16,
+        image.height as u16,
+        &mut data,
+        GIF_QUANTIZE_SPEED,
+    );
+    // gif delays are in hundredths of a second
+    frame.delay = ((delay.as_millis() / 10).min(u16::MAX as u128) as u16).max(MIN_GIF_DELAY_CS);
+    encoder.write_frame(&frame).map_err(|e| e.to_string())
+}
+
+fn finish_gif(encoder: gif::Encoder<BufWriter<File>>) -> Result<(), String> {
+    let mut writer = encoder.into_inner().map_err(|e| e.to_string())?;
+    writer.flush().map_err(|e| e.to_string())
+}
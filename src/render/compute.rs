@@ -0,0 +1,95 @@
+use std::ops::BitOr;
+
+use super::{backend, shader::Shader, GlProgram};
+use crate::resources::Resources;
+
+// wraps the GLbitfield passed to glMemoryBarrier; combine with |
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct MemoryBarrier(gl::types::GLbitfield);
+
+impl MemoryBarrier {
+    pub const SHADER_STORAGE: MemoryBarrier = MemoryBarrier(gl::SHADER_STORAGE_BARRIER_BIT);
+    pub const VERTEX_ATTRIB_ARRAY: MemoryBarrier =
+        MemoryBarrier(gl::VERTEX_ATTRIB_ARRAY_BARRIER_BIT);
+    pub const ELEMENT_ARRAY: MemoryBarrier = MemoryBarrier(gl::ELEMENT_ARRAY_BARRIER_BIT);
+    pub const UNIFORM: MemoryBarrier = MemoryBarrier(gl::UNIFORM_BARRIER_BIT);
+    pub const TEXTURE_FETCH: MemoryBarrier = MemoryBarrier(gl::TEXTURE_FETCH_BARRIER_BIT);
+    pub const SHADER_IMAGE_ACCESS: MemoryBarrier =
+        MemoryBarrier(gl::SHADER_IMAGE_ACCESS_BARRIER_BIT);
+    pub const BUFFER_UPDATE: MemoryBarrier = MemoryBarrier(gl::BUFFER_UPDATE_BARRIER_BIT);
+    pub const ALL: MemoryBarrier = MemoryBarrier(gl::ALL_BARRIER_BITS);
+}
+
+impl BitOr for MemoryBarrier {
+    type Output = MemoryBarrier;
+
+    fn bitor(self, rhs: MemoryBarrier) -> MemoryBarrier {
+        MemoryBarrier(self.0 | rhs.0)
+    }
+}
+
+// makes writes done by previous dispatches visible to the kind of access given in _barriers_,
+// e.g. SHADER_STORAGE | VERTEX_ATTRIB_ARRAY before drawing particles simulated on the GPU
+pub fn memory_barrier(barriers: MemoryBarrier) {
+    backend::with(|backend| backend.memory_barrier(barriers.0));
+}
+
+pub struct ComputeProgram {
+    program: GlProgram,
+    local_size: [u32; 3],
+}
+
+impl ComputeProgram {
+    // compute shaders need a 4.3 context (or the function pointers loaded through an extension);
+    // callers should check this and keep their cpu path around when it returns false
+    pub fn is_supported() -> bool {
+        backend::with(|backend| backend.supports_compute())
+    }
+
+    // builds the program from the `!comp` section of a single .glsl file
+    pub fn from_res(res: &Resources, name: &str) -> Result<ComputeProgram, String> {
+        if !ComputeProgram::is_supported() {
+            return Err(format!(
+                "Compute shaders are not supported by this context, can't load {}",
+                name
+            ));
+        }
+
+        let shader = Shader::compute_from_single_source(res, name)?;
+        let program = GlProgram::link(name, &[shader])?;
+
+        let local_size = backend::with(|backend| backend.compute_work_group_size(program.id()));
+
+        Ok(ComputeProgram {
+            program,
+            local_size,
+        })
+    }
+
+    // exposes the underlying program for uniform setting
+    pub fn program(&self) -> &GlProgram {
+        &self.program
+    }
+
+    // the `layout(local_size_x = ...)` declared in the shader
+    pub fn local_size(&self) -> [u32; 3] {
+        self.local_size
+    }
+
+    pub fn dispatch(&self, groups_x: u32, groups_y: u32, groups_z: u32) {
+        self.program.set_used();
+        backend::with(|backend| backend.dispatch_compute([groups_x, groups_y, groups_z]));
+    }
+
+    // dispatches enough work groups to cover _invocations_ in each dimension,
+    // shaders need to discard the excess invocations of the last group themselves
+    pub fn dispatch_invocations(&self, invocations_x: u32, invocations_y: u32, invocations_z: u32) {
+        let groups = |invocations: u32, size: u32| invocations.div_ceil(size);
+
+        self.dispatch(
+            groups(invocations_x, self.local_size[0]),
+            groups(invocations_y, self.local_size[1]),
+            groups(invocations_z, self.local_size[2]),
+        );
+    }
+}
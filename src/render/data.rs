@@ -1,16 +1,15 @@
-use gl;
+use super::backend;
 
 pub trait AttributedVertex {
     unsafe fn vertex_attrib_pointer(stride: usize, location: usize, offset: usize) {
-        gl::EnableVertexAttribArray(location as gl::types::GLuint);
-        gl::VertexAttribPointer(
-            location as gl::types::GLuint,
-            Self::amount_of_components(), // the number of components per generic vertex attribute
-            gl::FLOAT,                    // data type
-            gl::FALSE,                    // normalized (int-to-float conversion)
-            stride as gl::types::GLint,
-            offset as *const gl::types::GLvoid,
-        );
+        backend::with(|backend| {
+            backend.vertex_attrib_pointer(
+                location as u32,
+                Self::amount_of_components(),
+                stride,
+                offset,
+            )
+        });
     }
 
     fn amount_of_components() -> i32 {
@@ -20,6 +19,12 @@ pub trait AttributedVertex {
     }
 }
 
+// vertex layouts that can describe themselves to the bound vertex array, which lets generic
+// code like Mesh set up attributes. implemented by #[derive(VertexAttribPointers)]
+pub trait Vertex {
+    fn vertex_attrib_pointers();
+}
+
 #[allow(non_camel_case_types)]
 pub type _f32x2 = glm::Vec2;
 //pub struct f32x2(glm::Vec2);
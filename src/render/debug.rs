@@ -0,0 +1,188 @@
+use std::ffi::c_void;
+
+use gl::types::{GLchar, GLenum, GLsizei, GLuint};
+
+// wraps a gl call (inside an unsafe block) so that debug builds check glGetError right after it
+// and report which call failed and from where. release builds compile down to the bare call
+macro_rules! gl_call {
+    ($call:expr) => {{
+        let result = $call;
+        #[cfg(debug_assertions)]
+        $crate::render::debug::check_error(stringify!($call), function_name!(), file!(), line!());
+        result
+    }};
+}
+
+// path of the function the macro is expanded in, e.g. blue::render::texture::Texture::create
+macro_rules! function_name {
+    () => {{
+        fn f() {}
+        fn type_name_of<T>(_: T) -> &'static str {
+            std::any::type_name::<T>()
+        }
+        let name = type_name_of(f);
+        &name[..name.len() - 3]
+    }};
+}
+
+// kinds of objects that can be given a label with label_object
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ObjectKind {
+    Buffer,
+    Texture,
+    Program,
+    Shader,
+    VertexArray,
+    Framebuffer,
+    Query,
+}
+
+impl ObjectKind {
+    fn identifier(&self) -> GLenum {
+        match self {
+            ObjectKind::Buffer => gl::BUFFER,
+            ObjectKind::Texture => gl::TEXTURE,
+            ObjectKind::Program => gl::PROGRAM,
+            ObjectKind::Shader => gl::SHADER,
+            ObjectKind::VertexArray => gl::VERTEX_ARRAY,
+            ObjectKind::Framebuffer => gl::FRAMEBUFFER,
+            ObjectKind::Query => gl::QUERY,
+        }
+    }
+}
+
+// installs a KHR_debug message callback printing driver messages. only done in debug builds and
+// when the context exposes KHR_debug (core in 4.3); returns whether the callback is active.
+// the context should be created with the debug flag for drivers to report anything useful
+pub fn enable_debug_output() -> bool {
+    if !cfg!(debug_assertions) || !gl::DebugMessageCallback::is_loaded() {
+        return false;
+    }
+
+    unsafe {
+        gl::Enable(gl::DEBUG_OUTPUT);
+        // report messages from inside the offending call, so breakpoints in the callback
+        // land on a useful stack
+        gl::Enable(gl::DEBUG_OUTPUT_SYNCHRONOUS);
+        gl::DebugMessageCallback(Some(debug_message_callback), std::ptr::null());
+        // notifications are mostly buffer placement chatter
+        gl::DebugMessageControl(
+            gl::DONT_CARE,
+            gl::DONT_CARE,
+            gl::DEBUG_SEVERITY_NOTIFICATION,
+            0,
+            std::ptr::null(),
+            gl::FALSE,
+        );
+    }
+
+    true
+}
+
+// names an object so it shows up in debug messages and external debuggers (RenderDoc, Nsight).
+// buffers and textures must have been bound at least once before being labeled
+pub fn label_object(kind: ObjectKind, id: GLuint, label: &str) {
+    if !gl::ObjectLabel::is_loaded() {
+        return;
+    }
+
+    unsafe {
+        gl::ObjectLabel(
+            kind.identifier(),
+            id,
+            label.len() as GLsizei,
+            label.as_ptr() as *const GLchar,
+        );
+    }
+}
+
+// drains the GL error queue, printing every error along with the call that raised it
+pub fn check_error(call: &str, function: &str, file: &str, line: u32) {
+    loop {
+        let error = unsafe { gl::GetError() };
+        if error == gl::NO_ERROR {
+            break;
+        }
+
+        eprintln!(
+            "[gl error] {} after `{}` in {} ({}:{})",
+            error_name(error),
+            call,
+            function,
+            file,
+            line
+        );
+    }
+}
+
+fn error_name(error: GLenum) -> &'static str {
+    match error {
+        gl::INVALID_ENUM => "GL_INVALID_ENUM",
+        gl::INVALID_VALUE => "GL_INVALID_VALUE",
+        gl::INVALID_OPERATION => "GL_INVALID_OPERATION",
+        gl::INVALID_FRAMEBUFFER_OPERATION => "GL_INVALID_FRAMEBUFFER_OPERATION",
+        gl::OUT_OF_MEMORY => "GL_OUT_OF_MEMORY",
+        gl::STACK_UNDERFLOW => "GL_STACK_UNDERFLOW",
+        gl::STACK_OVERFLOW => "GL_STACK_OVERFLOW",
+        _ => "unknown GL error",
+    }
+}
+
+extern "system" fn debug_message_callback(
+    source: GLenum,
+    gltype: GLenum,
+    id: GLuint,
+    severity: GLenum,
+    length: GLsizei,
+    message: *const GLchar,
+    _user_param: *mut c_void,
+) {
+    let message = unsafe { std::slice::from_raw_parts(message as *const u8, length as usize) };
+    let message = String::from_utf8_lossy(message);
+
+    let source = match source {
+        gl::DEBUG_SOURCE_API => "api",
+        gl::DEBUG_SOURCE_WINDOW_SYSTEM => "window system",
+        gl::DEBUG_SOURCE_SHADER_COMPILER => "shader compiler",
+        gl::DEBUG_SOURCE_THIRD_PARTY => "third party",
+        gl::DEBUG_SOURCE_APPLICATION => "application",
+        _ => "other",
+    };
+    let gltype = match gltype {
+        gl::DEBUG_TYPE_ERROR => "error",
+        gl::DEBUG_TYPE_DEPRECATED_BEHAVIOR => "deprecated behavior",
+        gl::DEBUG_TYPE_UNDEFINED_BEHAVIOR => "undefined behavior",
+        gl::DEBUG_TYPE_PORTABILITY => "portability",
+        gl::DEBUG_TYPE_PERFORMANCE => "performance",
+        gl::DEBUG_TYPE_MARKER => "marker",
+        _ => "other",
+    };
+
+    match severity {
+        gl::DEBUG_SEVERITY_HIGH | gl::DEBUG_SEVERITY_MEDIUM => eprintln!(
+            "[gl {} {}] ({}) #{}: {}",
+            source,
+            gltype,
+            severity_name(severity),
+            id,
+            message
+        ),
+        _ => println!(
+            "[gl {} {}] ({}) #{}: {}",
+            source,
+            gltype,
+            severity_name(severity),
+            id,
+            message
+        ),
+    }
+}
+
+fn severity_name(severity: GLenum) -> &'static str {
+    match severity {
+        gl::DEBUG_SEVERITY_HIGH => "high",
+        gl::DEBUG_SEVERITY_MEDIUM => "medium",
+        gl::DEBUG_SEVERITY_LOW => "low",
+        _ => "notification",
+    }
+}
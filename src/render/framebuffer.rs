@@ -0,0 +1,83 @@
+use super::backend;
+use super::debug::ObjectKind;
+use super::texture::{PixelRect, Texture, TextureDescriptor};
+
+// offscreen framebuffer drawing into a texture of its own, which can be sampled or blitted to
+// the window afterwards
+pub struct Framebuffer {
+    id: gl::types::GLuint,
+    texture: Texture,
+}
+
+impl Framebuffer {
+    pub fn new(
+        width: usize,
+        height: usize,
+        descriptor: &TextureDescriptor,
+    ) -> Result<Framebuffer, String> {
+        let texture = Texture::empty(width, height, descriptor);
+        let id = backend::with(|backend| backend.create_framebuffer());
+        // built before attaching, so the framebuffer is deleted if attaching fails
+        let framebuffer = Framebuffer { id, texture };
+
+        backend::with(|backend| {
+            backend.bind_framebuffer(framebuffer.id);
+            let attached = backend.attach_texture(framebuffer.texture.id());
+            backend.bind_framebuffer(0);
+            attached
+        })?;
+
+        Ok(framebuffer)
+    }
+
+    pub fn id(&self) -> gl::types::GLuint {
+        self.id
+    }
+
+    pub fn texture(&self) -> &Texture {
+        &self.texture
+    }
+
+    pub fn width(&self) -> usize {
+        self.texture.width()
+    }
+
+    pub fn height(&self) -> usize {
+        self.texture.height()
+    }
+
+    // the whole color buffer, in window coordinates
+    pub fn rect(&self) -> PixelRect {
+        PixelRect {
+            x: 0,
+            y: 0,
+            width: self.width(),
+            height: self.height(),
+        }
+    }
+
+    // names the framebuffer and its texture in GL debug messages and debuggers
+    pub fn set_label(&self, label: &str) {
+        backend::with(|backend| backend.set_label(ObjectKind::Framebuffer, self.id, label));
+        self.texture.set_label(&format!("{} color", label));
+    }
+
+    // makes draws land in the texture, with clip space covering all of it
+    pub fn bind(&self) {
+        backend::with(|backend| {
+            backend.bind_framebuffer(self.id);
+            backend.viewport(self.rect());
+        });
+    }
+
+    // back to drawing to the window; its viewport has to be set again by the caller
+    pub fn unbind(&self) {
+        backend::with(|backend| backend.bind_framebuffer(0));
+    }
+}
+
+impl Drop for Framebuffer {
+    fn drop(&mut self) {
+        backend::with(|backend| backend.delete_framebuffer(self.id));
+    }
+}
@@ -0,0 +1,44 @@
+use super::texture::TextureFormat;
+
+// decoded pixels; HDR sources (.hdr) decode to floats, everything else to bytes
+#[derive(Clone, Debug)]
+pub enum ImagePixels {
+    U8(Vec<u8>),
+    F32(Vec<f32>),
+}
+
+// image living on the cpu side, pixels are stored row by row starting at the top left corner
+// with _channels_ components each
+#[derive(Clone, Debug)]
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub channels: usize,
+    pub pixels: ImagePixels,
+}
+
+impl Image {
+    pub fn is_hdr(&self) -> bool {
+        matches!(self.pixels, ImagePixels::F32(_))
+    }
+
+    // the texture format that keeps every channel of the image without expanding it
+    pub fn native_format(&self) -> Result<TextureFormat, String> {
+        TextureFormat::from_channels(self.channels, self.is_hdr())
+    }
+
+    pub fn flip_vertically(&mut self) {
+        let row_len = self.width * self.channels;
+        match &mut self.pixels {
+            ImagePixels::U8(data) => flip_rows(data, row_len, self.height),
+            ImagePixels::F32(data) => flip_rows(data, row_len, self.height),
+        }
+    }
+}
+
+fn flip_rows<T>(data: &mut [T], row_len: usize, height: usize) {
+    for row in 0..height / 2 {
+        let (top, bottom) = data.split_at_mut((height - row - 1) * row_len);
+        top[row * row_len..(row + 1) * row_len].swap_with_slice(&mut bottom[..row_len]);
+    }
+}
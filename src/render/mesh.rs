@@ -0,0 +1,102 @@
+use std::ops::Range;
+
+use super::backend::{self, IndexType, PrimitiveMode};
+use super::buffer::{ElementBuffer, VertexArray, VertexBuffer};
+use super::data::Vertex;
+use super::stats;
+
+// integer types a mesh can be indexed with; smaller ones save memory for meshes with few vertices
+pub trait MeshIndex: Copy {
+    const INDEX_TYPE: IndexType;
+}
+
+impl MeshIndex for u8 {
+    const INDEX_TYPE: IndexType = IndexType::U8;
+}
+
+impl MeshIndex for u16 {
+    const INDEX_TYPE: IndexType = IndexType::U16;
+}
+
+impl MeshIndex for u32 {
+    const INDEX_TYPE: IndexType = IndexType::U32;
+}
+
+// static indexed geometry: owns the vertex array together with the vertex and index buffers it
+// reads from, set up once at construction. programs, uniforms and textures are up to the caller
+pub struct Mesh<V, I = u32> {
+    vao: VertexArray,
+    vertices: VertexBuffer<V>,
+    indices: ElementBuffer<I>,
+    mode: PrimitiveMode,
+}
+
+impl<V: Vertex, I: MeshIndex> Mesh<V, I> {
+    pub fn new(vertices: &[V], indices: &[I], mode: PrimitiveMode) -> Mesh<V, I> {
+        let vao = VertexArray::new();
+        let mut vertex_buffer: VertexBuffer<V> = VertexBuffer::new();
+        let mut index_buffer: ElementBuffer<I> = ElementBuffer::new();
+
+        // the element buffer binding is part of the vertex array state, so it has to be bound
+        // while our vao is, and unbinding the vao first keeps it attached
+        vao.bind();
+        vertex_buffer.bind();
+        vertex_buffer.upload_data_static_draw(vertices);
+        index_buffer.bind();
+        index_buffer.upload_data_static_draw(indices);
+        V::vertex_attrib_pointers();
+        vertex_buffer.unbind();
+        vao.unbind();
+
+        Mesh {
+            vao,
+            vertices: vertex_buffer,
+            indices: index_buffer,
+            mode,
+        }
+    }
+
+    pub fn mode(&self) -> PrimitiveMode {
+        self.mode
+    }
+
+    // the same indices can be drawn with a different topology, e.g. a quad as an outline
+    pub fn set_mode(&mut self, mode: PrimitiveMode) {
+        self.mode = mode;
+    }
+
+    pub fn vertex_count(&self) -> usize {
+        self.vertices.len()
+    }
+
+    pub fn index_count(&self) -> usize {
+        self.indices.len()
+    }
+
+    // names the GL objects "<label> vao", "<label> vertices" and "<label> indices"
+    pub fn set_label(&self, label: &str) {
+        self.vao.set_label(&format!("{} vao", label));
+        self.vertices.set_label(&format!("{} vertices", label));
+        self.indices.set_label(&format!("{} indices", label));
+    }
+
+    pub fn draw(&self) {
+        self.draw_range(0..self.index_count());
+    }
+
+    // draws the indices in _range_, clamped to the ones the mesh has
+    pub fn draw_range(&self, range: Range<usize>) {
+        let end = range.end.min(self.index_count());
+        let start = range.start.min(end);
+        if start == end {
+            return;
+        }
+
+        self.vao.bind();
+        backend::with(|backend| {
+            backend.draw_elements(self.mode, I::INDEX_TYPE, start, end - start, 0)
+        });
+        stats::record_indexed_draw_call((end - start) as u32);
+        self.vao.unbind();
+    }
+}
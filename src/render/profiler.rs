@@ -0,0 +1,258 @@
+use std::collections::VecDeque;
+use std::time::Instant;
+
+use super::backend::{self, ObjectId};
+
+// frames of history kept per scope for the graphs and averages
+const HISTORY_LEN: usize = 120;
+// timer results are read three frames after being issued, and only once the backend reports
+// them available, so reading never stalls the pipeline; drivers queue up to three frames
+const QUERY_SLOTS: usize = 3;
+
+// timer queries of one scope, one per slot. slots alternate every frame
+struct GpuTimer {
+    queries: [ObjectId; QUERY_SLOTS],
+    pending: [bool; QUERY_SLOTS],
+}
+
+impl GpuTimer {
+    fn new() -> GpuTimer {
+        let queries =
+            backend::with(|backend| [(); QUERY_SLOTS].map(|_| backend.create_timer_query()));
+
+        GpuTimer {
+            queries,
+            pending: [false; QUERY_SLOTS],
+        }
+    }
+
+    // result in milliseconds of the query last issued in _slot_, if there is one and the GPU
+    // is done with it. a query that isn't done yet stays pending for the next time around
+    fn take_result(&mut self, slot: usize) -> Option<f32> {
+        if !self.pending[slot] {
+            return None;
+        }
+
+        let query = self.queries[slot];
+        let nanoseconds = backend::with(|backend| {
+            backend
+                .timer_query_available(query)
+                .then(|| backend.timer_query_result(query))
+        })?;
+        self.pending[slot] = false;
+
+        Some(nanoseconds as f32 / 1_000_000.0)
+    }
+}
+
+impl Drop for GpuTimer {
+    fn drop(&mut self) {
+        backend::with(|backend| {
+            for query in self.queries {
+                backend.delete_timer_query(query);
+            }
+        });
+    }
+}
+
+struct Scope {
+    name: &'static str,
+    cpu_start: Option<Instant>,
+    gpu_timer: Option<GpuTimer>,
+    // whether this scope started a gpu query this frame
+    gpu_query_issued: bool,
+    cpu_ms: f32,
+    cpu_history: VecDeque<f32>,
+    gpu_history: VecDeque<f32>,
+}
+
+impl Scope {
+    fn new(name: &'static str, gpu_timing: bool) -> Scope {
+        Scope {
+            name,
+            cpu_start: None,
+            gpu_timer: gpu_timing.then(GpuTimer::new),
+            gpu_query_issued: false,
+            cpu_ms: 0.0,
+            cpu_history: VecDeque::with_capacity(HISTORY_LEN),
+            gpu_history: VecDeque::with_capacity(HISTORY_LEN),
+        }
+    }
+}
+
+// times named scopes on the cpu (Instant) and on the gpu (timer queries) and keeps a rolling
+// history of each. usage per frame:
+//
+//  profiler.begin_frame();
+//  profiler.begin_scope("sprites"); ... draws ...; profiler.end_scope("sprites");
+//  profiler.end_frame();
+//
+// scopes can be nested on the cpu side, but only one timer query can run at a time, so scopes
+// opened inside another gpu-timed scope are only timed on the cpu.
+// a scope entered several times in a frame adds up its cpu time, its gpu time is taken from
+// the first entry only
+pub struct Profiler {
+    scopes: Vec<Scope>,
+    gpu_timing: bool,
+    active_gpu_scope: Option<&'static str>,
+    frame: usize,
+    frame_start: Instant,
+    frame_history: VecDeque<f32>,
+}
+
+impl Default for Profiler {
+    fn default() -> Self {
+        Profiler::new()
+    }
+}
+
+impl Profiler {
+    pub fn new() -> Profiler {
+        Profiler {
+            scopes: Vec::new(),
+            gpu_timing: backend::with(|backend| backend.supports_timer_queries()),
+            active_gpu_scope: None,
+            frame: 0,
+            frame_start: Instant::now(),
+            frame_history: VecDeque::with_capacity(HISTORY_LEN),
+        }
+    }
+
+    pub fn gpu_timing(&self) -> bool {
+        self.gpu_timing
+    }
+
+    pub fn begin_frame(&mut self) {
+        self.frame_start = Instant::now();
+        let slot = self.frame % QUERY_SLOTS;
+
+        // collect what the gpu measured the last time this slot was used
+        for scope in &mut self.scopes {
+            scope.cpu_ms = 0.0;
+            scope.gpu_query_issued = false;
+            if let Some(gpu_ms) = scope.gpu_timer.as_mut().and_then(|t| t.take_result(slot)) {
+                push_sample(&mut scope.gpu_history, gpu_ms);
+            }
+        }
+    }
+
+    pub fn begin_scope(&mut self, name: &'static str) {
+        let slot = self.frame % QUERY_SLOTS;
+        let gpu_available = self.gpu_timing && self.active_gpu_scope.is_none();
+        let scope = self.scope_mut(name);
+
+        scope.cpu_start = Some(Instant::now());
+
+        if gpu_available && !scope.gpu_query_issued {
+            // a slot still waiting on its result is skipped, reusing it would throw the result away
+            if let Some(timer) = scope.gpu_timer.as_mut().filter(|t| !t.pending[slot]) {
+                backend::with(|backend| backend.begin_timer_query(timer.queries[slot]));
+                timer.pending[slot] = true;
+                scope.gpu_query_issued = true;
+                self.active_gpu_scope = Some(name);
+            }
+        }
+    }
+
+    pub fn end_scope(&mut self, name: &'static str) {
+        if self.active_gpu_scope == Some(name) {
+            backend::with(|backend| backend.end_timer_query());
+            self.active_gpu_scope = None;
+        }
+
+        let scope = self.scope_mut(name);
+        if let Some(start) = scope.cpu_start.take() {
+            scope.cpu_ms += start.elapsed().as_secs_f32() * 1000.0;
+        }
+    }
+
+    pub fn end_frame(&mut self) {
+        for scope in &mut self.scopes {
+            let cpu_ms = scope.cpu_ms;
+            push_sample(&mut scope.cpu_history, cpu_ms);
+        }
+        push_sample(
+            &mut self.frame_history,
+            self.frame_start.elapsed().as_secs_f32() * 1000.0,
+        );
+
+        self.frame += 1;
+    }
+
+    // average cpu and gpu milliseconds of _name_ over the kept history
+    pub fn scope_averages(&self, name: &str) -> Option<(f32, f32)> {
+        self.scopes
+            .iter()
+            .find(|scope| scope.name == name)
+            .map(|scope| (average(&scope.cpu_history), average(&scope.gpu_history)))
+    }
+
+    pub fn draw_ui(&self, ui: &imgui::Ui) {
+        imgui::Window::new("Profiler").build(ui, || {
+            let frame_times: Vec<f32> = self.frame_history.iter().copied().collect();
+            ui.text(format!(
+                "frame (cpu): {:.3} ms avg",
+                average(&self.frame_history)
+            ));
+            imgui::PlotLines::new(ui, "##frame", &frame_times)
+                .scale_min(0.0)
+                .graph_size([300.0, 40.0])
+                .build();
+
+            if !self.gpu_timing {
+                ui.text("gpu timer queries not available");
+            }
+
+            for scope in &self.scopes {
+                ui.separator();
+                ui.text(format!(
+                    "{}: cpu {:.3} ms, gpu {:.3} ms",
+                    scope.name,
+                    average(&scope.cpu_history),
+                    average(&scope.gpu_history)
+                ));
+
+                let cpu_times: Vec<f32> = scope.cpu_history.iter().copied().collect();
+                imgui::PlotLines::new(ui, format!("cpu##{}", scope.name), &cpu_times)
+                    .scale_min(0.0)
+                    .graph_size([300.0, 30.0])
+                    .build();
+
+                if scope.gpu_timer.is_some() {
+                    let gpu_times: Vec<f32> = scope.gpu_history.iter().copied().collect();
+                    imgui::PlotLines::new(ui, format!("gpu##{}", scope.name), &gpu_times)
+                        .scale_min(0.0)
+                        .graph_size([300.0, 30.0])
+                        .build();
+                }
+            }
+        });
+    }
+
+    fn scope_mut(&mut self, name: &'static str) -> &mut Scope {
+        let index = match self.scopes.iter().position(|scope| scope.name == name) {
+            Some(index) => index,
+            None => {
+                self.scopes.push(Scope::new(name, self.gpu_timing));
+                self.scopes.len() - 1
+            }
+        };
+
+        &mut self.scopes[index]
+    }
+}
+
+fn push_sample(history: &mut VecDeque<f32>, sample: f32) {
+    if history.len() == HISTORY_LEN {
+        history.pop_front();
+    }
+    history.push_back(sample);
+}
+
+fn average(history: &VecDeque<f32>) -> f32 {
+    if history.is_empty() {
+        return 0.0;
+    }
+
+    history.iter().sum::<f32>() / history.len() as f32
+}
@@ -1,14 +1,11 @@
 use super::{
-    buffer::VertexBuffer,
-    buffer::{ElementBuffer, VertexArray},
-    data::AttributedVertex,
-    subtexture::Subtexture,
-    texture::Texture,
-    GlProgram,
+    backend::PrimitiveMode, data::AttributedVertex, mesh::Mesh, stats, subtexture::Subtexture,
+    texture::Texture, GlProgram,
 };
 use crate::resources::Resources;
 
 pub mod batch_renderer;
+pub mod tile_renderer;
 
 #[derive(VertexAttribPointers, Copy, Clone, Debug)]
 #[repr(C, packed)]
@@ -19,10 +16,9 @@ struct SpriteVertex {
 
 pub struct SpriteRenderer {
     program: GlProgram,
-    vao: VertexArray,
-    vbo: VertexBuffer,
+    quad: Mesh<SpriteVertex>,
+    outline: Mesh<SpriteVertex>,
 
-    quad_veo: ElementBuffer,
     circle_program: GlProgram,
     ortho_matrix: glm::Mat4,
 }
@@ -32,53 +28,25 @@ impl SpriteRenderer {
         res: &Resources,
         screen_dimensions: glm::Vec2,
     ) -> Result<SpriteRenderer, String> {
-        let vertices: Vec<SpriteVertex> = vec![
-            SpriteVertex {
-                postex: glm::vec4(0.0, 0.0, 0.0, 0.0),
-            }, // top
-            SpriteVertex {
-                postex: glm::vec4(0.0, 1.0, 0.0, 1.0),
-            }, // bottom right
-            SpriteVertex {
-                postex: glm::vec4(1.0, 0.0, 1.0, 0.0),
-            }, // bottom left
-            SpriteVertex {
-                postex: glm::vec4(0.0, 1.0, 0.0, 1.0),
-            },
-            SpriteVertex {
-                postex: glm::vec4(1.0, 1.0, 1.0, 1.0),
-            },
-            SpriteVertex {
-                postex: glm::vec4(1.0, 0.0, 1.0, 0.0),
-            },
-        ];
+        // corners of the unit quad, with texture coordinates matching the position
+        let vertices: Vec<SpriteVertex> = [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)]
+            .iter()
+            .map(|&(x, y)| SpriteVertex {
+                postex: glm::vec4(x, y, x, y),
+            })
+            .collect();
 
         let program = GlProgram::from_res(res, "shaders/texture2d.glsl")?;
         let circle_program = GlProgram::from_res(res, "shaders/shapes/circle2d.glsl")?;
-        let vbo: VertexBuffer = VertexBuffer::new();
-        let vao = VertexArray::new();
 
-        // let's see if we can make an EBO for rectangle rendering
-        // note: it would have to be rendered with line_strip or loop
+        let quad = Mesh::new(&vertices, &[0, 3, 1, 3, 2, 1], PrimitiveMode::Triangles);
+        // the same corners walked around, for drawing rectangle outlines
+        let outline = Mesh::new(&vertices, &[0, 1, 2, 3], PrimitiveMode::LineLoop);
 
-        let rect_indices: Vec<u32> = vec![0, 2, 4, 1];
-        let quad_veo = ElementBuffer::new();
-        program.set_used();
-
-        quad_veo.bind();
-        quad_veo.upload_data_static_draw(&rect_indices);
-        quad_veo.unbind();
-
-        vbo.bind();
-        vbo.upload_data_static_draw(&vertices);
-        vbo.unbind();
-
-        vao.bind();
-        quad_veo.bind();
-        vbo.bind();
-        SpriteVertex::vertex_attrib_pointers();
-        vbo.unbind();
-        vao.unbind();
+        program.set_label("sprite program");
+        circle_program.set_label("circle program");
+        quad.set_label("sprite quad");
+        outline.set_label("sprite rect outline");
 
         let ortho_matrix = glm::ortho(
             0.0,
@@ -88,6 +56,7 @@ impl SpriteRenderer {
             -1.0,
             1.0,
         );
+        program.set_used();
         program.set_mat4("projection\0".as_ptr(), ortho_matrix);
 
         circle_program.set_used();
@@ -96,9 +65,8 @@ impl SpriteRenderer {
         Ok(SpriteRenderer {
             program,
             circle_program,
-            vao,
-            vbo,
-            quad_veo,
+            quad,
+            outline,
             ortho_matrix,
         })
     }
@@ -163,12 +131,9 @@ impl SpriteRenderer {
         self.program.set_vector3f("spriteColor\0".as_ptr(), color);
 
         texture.bind();
-        self.vao.bind();
         // unsafe {gl::ActiveTexture(gl::TEXTURE0);}
-        unsafe {
-            gl::DrawArrays(gl::TRIANGLES, 0, 6);
-        }
-        self.vao.unbind();
+        self.quad.draw();
+        stats::record_quads(1);
     }
 
     pub fn draw_rect(&self, rect: glm::Vec4, color: glm::Vec3) {
@@ -181,11 +146,7 @@ impl SpriteRenderer {
         self.program.set_vector3f("spriteColor\0".as_ptr(), color);
         self.program.set_float("displacement\0".as_ptr(), 0.5);
 
-        self.vao.bind();
-        unsafe {
-            gl::DrawElements(gl::LINE_LOOP, 4, gl::UNSIGNED_INT, std::ptr::null());
-        }
-        self.vao.unbind();
+        self.outline.draw();
     }
 
     pub fn draw_circle(&self, rect: glm::Vec4, _color: glm::Vec3) {
@@ -196,10 +157,7 @@ impl SpriteRenderer {
         self.circle_program.set_used();
         self.circle_program.set_mat4("model\0".as_ptr(), model);
 
-        self.vao.bind();
-        unsafe {
-            gl::DrawArrays(gl::TRIANGLES, 0, 6);
-        }
-        self.vao.unbind();
+        self.quad.draw();
+        stats::record_quads(1);
     }
 }
@@ -1,21 +1,38 @@
 
 
+use std::time::{Duration, Instant};
+
 use crate::{
     render::{
-        buffer::VertexBuffer,
+        backend::{self, IndexType, ObjectId, PrimitiveMode, TextureTarget},
         buffer::{ElementBuffer, VertexArray},
+        buffer::{StreamingMode, StreamingVertexBuffer},
         data::*,
+        stats,
         texture::Texture,
         GlProgram,
     },
     resources::Resources,
 };
 const MAX_QUADS: usize = 1024;
-
-struct BatchStats {
-    draw_calls: i32,
-    quads_rendered: i32,
-    texture_slots_used: i32,
+// textures a single draw can sample from, has to match u_Textures in batch_texture2d.glsl.
+// slot 0 is always the white texture used by untextured quads
+const MAX_TEXTURE_SLOTS: usize = 8;
+// three regions are enough for the driver to keep two frames in flight while we write the third
+const DEFAULT_STREAMING_MODE: StreamingMode = StreamingMode::RingBuffer { regions: 3 };
+// weight of the newest sample in the smoothed timings, 0.05 roughly averages the last 20 frames
+const TIMING_SMOOTHING: f32 = 0.05;
+
+// what the last scene (begin_scene..end_scene) submitted. the same draws are also added to the
+// renderer-wide counters in render::stats
+#[derive(Copy, Clone, Debug)]
+pub struct BatchStats {
+    pub draw_calls: i32,
+    pub quads_rendered: i32,
+    pub texture_slots_used: i32,
+    // cpu time spent streaming the vertices and issuing the draw in end_scene
+    pub upload_time: Duration,
+    pub end_scene_time: Duration,
 }
 
 impl Default for BatchStats {
@@ -24,6 +41,8 @@ impl Default for BatchStats {
             draw_calls: (0),
             quads_rendered: (0),
             texture_slots_used: (0),
+            upload_time: Duration::ZERO,
+            end_scene_time: Duration::ZERO,
         }
     }
 }
@@ -31,15 +50,19 @@ impl Default for BatchStats {
 pub struct BatchRenderer {
     program: GlProgram,
     vao: VertexArray,
-    vbo: VertexBuffer,
+    vbo: StreamingVertexBuffer<QuadVertex>,
     vertices: Vec<QuadVertex>,
 
     index_buffer: ElementBuffer,
     ortho_matrix: glm::Mat4,
 
     white_texture: Texture,
+    // textures used by the quads batched since the last flush, by slot
+    texture_slots: Vec<ObjectId>,
 
     stats: BatchStats,
+    average_upload_ms: f32,
+    average_end_scene_ms: f32,
 }
 
 #[derive(VertexAttribPointers, Copy, Clone, Debug)]
@@ -57,16 +80,26 @@ struct QuadVertex {
 
 impl BatchRenderer {
     pub fn from_res(
+        res: &Resources,
+        screen_dimensions: glm::Vec2,
+        max_quads: u32,
+    ) -> Result<BatchRenderer, String> {
+        BatchRenderer::with_streaming_mode(res, screen_dimensions, max_quads, DEFAULT_STREAMING_MODE)
+    }
+
+    pub fn with_streaming_mode(
         res: &Resources,
         screen_dimensions: glm::Vec2,
         _max_quads: u32,
+        streaming_mode: StreamingMode,
     ) -> Result<BatchRenderer, String> {
         let vertices: Vec<QuadVertex> = Vec::with_capacity(MAX_QUADS * 4);
 
         let program = GlProgram::from_res(res, "shaders/batch_texture2d.glsl")?;
 
         program.set_used();
-        let vbo: VertexBuffer = VertexBuffer::new();
+        let vbo: StreamingVertexBuffer<QuadVertex> =
+            StreamingVertexBuffer::new(vertices.capacity(), streaming_mode);
         let vao = VertexArray::new();
 
         // let's see if we can make an EBO for rectangle rendering
@@ -88,11 +121,7 @@ impl BatchRenderer {
                 ]
             })
             .collect();
-        let index_buffer = ElementBuffer::new();
-
-        vbo.bind();
-        vbo.upload_data_dynamic_draw(&vertices);
-        vbo.unbind();
+        let mut index_buffer: ElementBuffer = ElementBuffer::new();
 
         index_buffer.bind();
         index_buffer.upload_data_static_draw(&rect_indices);
@@ -113,6 +142,12 @@ impl BatchRenderer {
 
         let white_texture = Texture::with_white_new().expect("Error creating white texture");
 
+        program.set_label("batch program");
+        vao.set_label("batch vao");
+        vbo.set_label("batch vertices");
+        index_buffer.set_label("batch indices");
+        white_texture.set_label("batch white texture");
+
         let ortho_matrix = glm::ortho(
             0.0,
             screen_dimensions.x,
@@ -122,44 +157,65 @@ impl BatchRenderer {
             1.0,
         );
         program.set_mat4("projection\0".as_ptr(), ortho_matrix);
+        for slot in 0..MAX_TEXTURE_SLOTS {
+            let name = format!("u_Textures[{}]\0", slot);
+            program.set_integer(name.as_ptr(), slot as i32);
+        }
 
         Ok(BatchRenderer {
             program,
             vao,
             vbo,
             index_buffer,
+            texture_slots: vec![white_texture.id()],
             white_texture,
             ortho_matrix,
             vertices,
             stats: BatchStats::default(),
+            average_upload_ms: 0.0,
+            average_end_scene_ms: 0.0,
         })
     }
 
+    pub fn streaming_mode(&self) -> StreamingMode {
+        self.vbo.mode()
+    }
+
+    // smoothed cpu time spent writing the batch into the vertex buffer
+    pub fn average_upload_ms(&self) -> f32 {
+        self.average_upload_ms
+    }
+
+    pub fn stats(&self) -> &BatchStats {
+        &self.stats
+    }
+
+    // smoothed cpu time of the whole end_scene call (upload + draw submission)
+    pub fn average_end_scene_ms(&self) -> f32 {
+        self.average_end_scene_ms
+    }
+
     pub fn begin_scene(&mut self) {
         self.stats = BatchStats::default();
         self.vertices.clear();
+        self.texture_slots.truncate(1);
     }
 
-    pub fn end_scene(&self) {
-        self.program.set_used();
-
-        self.vbo.bind();
+    pub fn end_scene(&mut self) -> Result<(), String> {
+        let end_scene_start = Instant::now();
+        self.flush()?;
+        self.stats.end_scene_time = end_scene_start.elapsed();
 
-        self.vbo
-            .upload_subdata_dynamic_draw(&self.vertices, (self.stats.quads_rendered * 4) as usize);
-
-        self.index_buffer.bind();
-        self.vao.bind();
+        self.average_upload_ms = smooth(
+            self.average_upload_ms,
+            self.stats.upload_time.as_secs_f32() * 1000.0,
+        );
+        self.average_end_scene_ms = smooth(
+            self.average_end_scene_ms,
+            self.stats.end_scene_time.as_secs_f32() * 1000.0,
+        );
 
-        unsafe {
-            gl::DrawElements(
-                gl::TRIANGLES,
-                self.stats.quads_rendered * 6,
-                gl::UNSIGNED_INT,
-                std::ptr::null(),
-            );
-        }
-        self.vao.unbind();
+        Ok(())
     }
 
     pub fn draw_quad(
@@ -169,7 +225,7 @@ impl BatchRenderer {
         color: glm::Vec4,
         scale: f32,
         _sub_tex_rect: glm::Vec4,
-    ) {
+    ) -> Result<(), String> {
         let quad_positions = [
             glm::vec3(0.0, 0.0, 0.0),
             glm::vec3(1.0, 0.0, 0.0) * scale,
@@ -188,6 +244,9 @@ impl BatchRenderer {
         ); */
 
         let _real_position = model * glm::vec4(position.x, position.y, position.z, 0.0);
+        if self.vertices.len() + 4 > MAX_QUADS * 4 {
+            self.flush()?;
+        }
         for i in quad_positions {
             let new_quad_vertex = QuadVertex {
                 pos: position + i, //glm::vec3(real_position.x * i.x, real_position.y * i.y, real_position.z * i.z),
@@ -200,5 +259,187 @@ impl BatchRenderer {
         }
 
         self.stats.quads_rendered += 1;
+
+        Ok(())
+    }
+
+    // draws the _sub_tex_rect_ region of _texture_ (normalized x, y, width, height as in
+    // Subtexture) stretched over _corners_, given in the order top left, top right, bottom
+    // right, bottom left of the region. lets callers rotate, scale and skew quads freely
+    pub fn draw_transformed_quad(
+        &mut self,
+        texture: &Texture,
+        corners: [glm::Vec2; 4],
+        sub_tex_rect: glm::Vec4,
+        color: glm::Vec4,
+    ) -> Result<(), String> {
+        let texture_id = self.texture_slot(texture)?;
+        let tex_coords = [
+            glm::vec2(sub_tex_rect.x, sub_tex_rect.y),
+            glm::vec2(sub_tex_rect.x + sub_tex_rect.z, sub_tex_rect.y),
+            glm::vec2(
+                sub_tex_rect.x + sub_tex_rect.z,
+                sub_tex_rect.y + sub_tex_rect.w,
+            ),
+            glm::vec2(sub_tex_rect.x, sub_tex_rect.y + sub_tex_rect.w),
+        ];
+
+        for (corner, tex_coords) in corners.iter().zip(tex_coords) {
+            self.vertices.push(QuadVertex {
+                pos: glm::vec3(corner.x, corner.y, 0.0),
+                color,
+                tex_coords,
+                texture_id,
+            });
+        }
+
+        self.stats.quads_rendered += 1;
+
+        Ok(())
+    }
+
+    // slot _texture_ is bound to in the current batch, flushing first when the batch has no
+    // room left for the quad or the texture
+    fn texture_slot(&mut self, texture: &Texture) -> Result<f32, String> {
+        let full = self.vertices.len() + 4 > MAX_QUADS * 4;
+        let slot = self.texture_slots.iter().position(|id| *id == texture.id());
+        if full || (slot.is_none() && self.texture_slots.len() == MAX_TEXTURE_SLOTS) {
+            self.flush()?;
+        }
+
+        let slot = match self.texture_slots.iter().position(|id| *id == texture.id()) {
+            Some(slot) => slot,
+            None => {
+                self.texture_slots.push(texture.id());
+                self.texture_slots.len() - 1
+            }
+        };
+        Ok(slot as f32)
+    }
+
+    // draws everything batched so far and starts over with an empty batch
+    fn flush(&mut self) -> Result<(), String> {
+        if self.vertices.is_empty() {
+            return Ok(());
+        }
+
+        let upload_start = Instant::now();
+        self.program.set_used();
+
+        self.vbo.bind();
+        let base_vertex = self.vbo.stream(&self.vertices)?;
+        self.stats.upload_time += upload_start.elapsed();
+
+        backend::with(|backend| {
+            for (slot, texture) in self.texture_slots.iter().enumerate() {
+                backend.active_texture(slot as u32);
+                backend.bind_texture(TextureTarget::Texture2D, *texture);
+                stats::record_texture_bind();
+            }
+            backend.active_texture(0);
+        });
+        self.stats.texture_slots_used = self
+            .stats
+            .texture_slots_used
+            .max(self.texture_slots.len() as i32);
+
+        self.index_buffer.bind();
+        self.vao.bind();
+
+        let quads = self.vertices.len() / 4;
+        backend::with(|backend| {
+            backend.draw_elements(
+                PrimitiveMode::Triangles,
+                IndexType::U32,
+                0,
+                quads * 6,
+                base_vertex as i32,
+            )
+        });
+        self.vao.unbind();
+        self.vbo.fence();
+
+        self.stats.draw_calls += 1;
+        stats::record_draw_call(self.vertices.len() as u32, quads as u32);
+        self.vertices.clear();
+        self.texture_slots.truncate(1);
+
+        Ok(())
+    }
+}
+
+fn smooth(average: f32, sample: f32) -> f32 {
+    average + (sample - average) * TIMING_SMOOTHING
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::render::backend::RecordingBackend;
+
+    fn renderer() -> BatchRenderer {
+        // test binaries run from target/<profile>/deps, build.rs copies the assets one level up
+        let res = Resources::from_relative_exe_path(Path::new("../assets")).unwrap();
+        BatchRenderer::from_res(&res, glm::vec2(800.0, 450.0), MAX_QUADS as u32).unwrap()
+    }
+
+    #[test]
+    fn quads_sharing_a_texture_are_drawn_at_once() {
+        let log = RecordingBackend::install();
+        let mut batch = renderer();
+        let texture = Texture::new(16, 16);
+        log.clear();
+
+        batch.begin_scene();
+        for i in 0..500 {
+            batch
+                .draw_quad(
+                    &texture,
+                    glm::vec3(i as f32, 0.0, 0.0),
+                    glm::vec4(1.0, 1.0, 1.0, 1.0),
+                    16.0,
+                    glm::vec4(0.0, 0.0, 1.0, 1.0),
+                )
+                .unwrap();
+        }
+        batch.end_scene().unwrap();
+
+        assert_eq!(log.draw_calls(), 1);
+        assert_eq!(batch.stats().quads_rendered, 500);
+    }
+
+    #[test]
+    fn running_out_of_texture_slots_starts_a_new_draw() {
+        let log = RecordingBackend::install();
+        let mut batch = renderer();
+        // slot 0 is taken by the white texture, so this is one texture more than fits
+        let textures: Vec<Texture> = (0..MAX_TEXTURE_SLOTS)
+            .map(|_| Texture::new(16, 16))
+            .collect();
+        log.clear();
+
+        batch.begin_scene();
+        for (i, texture) in textures.iter().enumerate() {
+            let x = i as f32 * 16.0;
+            let corners = [
+                glm::vec2(x, 0.0),
+                glm::vec2(x + 16.0, 0.0),
+                glm::vec2(x + 16.0, 16.0),
+                glm::vec2(x, 16.0),
+            ];
+            batch
+                .draw_transformed_quad(
+                    texture,
+                    corners,
+                    glm::vec4(0.0, 0.0, 1.0, 1.0),
+                    glm::vec4(1.0, 1.0, 1.0, 1.0),
+                )
+                .unwrap();
+        }
+        batch.end_scene().unwrap();
+
+        assert_eq!(log.draw_calls(), 2);
     }
 }
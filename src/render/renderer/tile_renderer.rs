@@ -0,0 +1,170 @@
+use crate::{
+    render::{
+        backend::{self, IndexType, PrimitiveMode},
+        buffer::{ElementBuffer, VertexArray},
+        buffer::{StreamingMode, StreamingVertexBuffer},
+        data::*,
+        stats,
+        texture_array::TextureArray,
+        GlProgram,
+    },
+    resources::Resources,
+};
+
+// tiles are drawn from a TextureArray by layer index, so a whole map goes out in one draw call
+// with the tileset bound once
+pub struct TileRenderer {
+    program: GlProgram,
+    vao: VertexArray,
+    vbo: StreamingVertexBuffer<TileVertex>,
+    index_buffer: ElementBuffer,
+    vertices: Vec<TileVertex>,
+    max_tiles: usize,
+}
+
+#[derive(VertexAttribPointers, Copy, Clone, Debug)]
+#[repr(C, packed)]
+struct TileVertex {
+    #[location = 0]
+    pos: glm::Vec2,
+    #[location = 1]
+    tex_coords: glm::Vec2,
+    #[location = 2]
+    layer: f32,
+    #[location = 3]
+    color: glm::Vec4,
+}
+
+impl TileRenderer {
+    pub fn from_res(
+        res: &Resources,
+        screen_dimensions: glm::Vec2,
+        max_tiles: usize,
+    ) -> Result<TileRenderer, String> {
+        let program = GlProgram::from_res(res, "shaders/tile_array2d.glsl")?;
+        program.set_used();
+
+        let vertices: Vec<TileVertex> = Vec::with_capacity(max_tiles * 4);
+        let vbo: StreamingVertexBuffer<TileVertex> = StreamingVertexBuffer::new(
+            vertices.capacity(),
+            StreamingMode::RingBuffer { regions: 3 },
+        );
+        let vao = VertexArray::new();
+
+        // two triangles per tile, same layout as the batch renderer
+        let indices: Vec<gl::types::GLuint> = (0..max_tiles as u32)
+            .flat_map(|tile| {
+                let x = tile * 4;
+                [x, x + 1, x + 2, x + 2, x + 3, x]
+            })
+            .collect();
+        let mut index_buffer: ElementBuffer = ElementBuffer::new();
+        index_buffer.bind();
+        index_buffer.upload_data_static_draw(&indices);
+        index_buffer.unbind();
+
+        vao.bind();
+        index_buffer.bind();
+        vbo.bind();
+        TileVertex::vertex_attrib_pointers();
+        vbo.unbind();
+        vao.unbind();
+
+        program.set_label("tile program");
+        vao.set_label("tile vao");
+        vbo.set_label("tile vertices");
+        index_buffer.set_label("tile indices");
+
+        let ortho_matrix = glm::ortho(
+            0.0,
+            screen_dimensions.x,
+            screen_dimensions.y,
+            0.0,
+            -1.0,
+            1.0,
+        );
+        program.set_mat4("projection\0".as_ptr(), ortho_matrix);
+        program.set_integer("tiles\0".as_ptr(), 0);
+
+        Ok(TileRenderer {
+            program,
+            vao,
+            vbo,
+            index_buffer,
+            vertices,
+            max_tiles,
+        })
+    }
+
+    pub fn begin_scene(&mut self) {
+        self.vertices.clear();
+    }
+
+    // queues tile _layer_ with its top left corner at _position_, _scale_ times its size in pixels
+    pub fn draw_tile(
+        &mut self,
+        tileset: &TextureArray,
+        layer: u32,
+        position: glm::Vec2,
+        scale: f32,
+        color: glm::Vec4,
+    ) -> Result<(), String> {
+        if self.vertices.len() / 4 >= self.max_tiles {
+            return Err(format!(
+                "tile batch is full ({} tiles), tile {} dropped",
+                self.max_tiles, layer
+            ));
+        }
+
+        let size = glm::vec2(tileset.tile_width_f(), tileset.tile_height_f()) * scale;
+        let corners = [
+            glm::vec2(0.0, 0.0),
+            glm::vec2(1.0, 0.0),
+            glm::vec2(1.0, 1.0),
+            glm::vec2(0.0, 1.0),
+        ];
+
+        for corner in corners {
+            self.vertices.push(TileVertex {
+                pos: position + corner.component_mul(&size),
+                tex_coords: corner,
+                layer: layer as f32,
+                color,
+            });
+        }
+
+        Ok(())
+    }
+
+    // draws every tile queued since begin_scene with _tileset_ bound
+    pub fn end_scene(&mut self, tileset: &TextureArray) -> Result<(), String> {
+        if self.vertices.is_empty() {
+            return Ok(());
+        }
+
+        self.program.set_used();
+        self.vbo.bind();
+        let base_vertex = self.vbo.stream(&self.vertices)?;
+
+        backend::with(|backend| backend.active_texture(0));
+        tileset.bind();
+        self.index_buffer.bind();
+        self.vao.bind();
+        backend::with(|backend| {
+            backend.draw_elements(
+                PrimitiveMode::Triangles,
+                IndexType::U32,
+                0,
+                self.vertices.len() / 4 * 6,
+                base_vertex as i32,
+            )
+        });
+        self.vao.unbind();
+        self.vbo.fence();
+
+        let tiles = self.vertices.len() / 4;
+        stats::record_draw_call((tiles * 4) as u32, tiles as u32);
+
+        Ok(())
+    }
+}
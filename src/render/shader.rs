@@ -1,7 +1,6 @@
+use super::backend::{self, ShaderStage};
 use crate::resources::Resources;
 
-
-
 use std::ffi::{CStr, CString};
 
 pub struct Shader {
@@ -41,14 +40,6 @@ impl Shader {
             ),
         };
 
-        // returns next ocurrence of _index_ or string length if none found
-        fn find_after(source: &str, index: usize) -> usize {
-            source
-                .match_indices("\n!")
-                .find_map(|(i, _)| (i > index).then(|| i))
-                .unwrap_or(source.as_bytes().len())
-        }
-
         // todo: this is all very inefficient; no need for CString at beginning of function and then this
         let vert_shader_string = str_source
             [vertex_index + vertex_directive.len()..find_after(str_source, vertex_index)]
@@ -65,16 +56,43 @@ impl Shader {
         Ok(vec![vertex_shader, frag_shader])
     }
 
-    fn from_source(source: &CStr, kind: u32) -> Result<Shader, String> {
-        let shader_id = shader_from_source(source, kind)?;
+    // loads the `!comp` section of a single file shader; compute shaders are not linked with any
+    // other stage so they get their own entry point
+    pub fn compute_from_single_source(res: &Resources, name: &str) -> Result<Shader, String> {
+        let source = res
+            .load_cstring(name)
+            .map_err(|e| format!("Error loading shader file {}: {:?}", name, e))?;
+        let str_source = source.to_str().map_err(|e| e.to_string())?;
+
+        let comp_directive = "!comp";
+        let comp_index = str_source.find(comp_directive).ok_or(format!(
+            "Error loading compute shader code for {}; directive not found",
+            name
+        ))?;
+
+        let comp_shader_string = str_source
+            [comp_index + comp_directive.len()..find_after(str_source, comp_index)]
+            .to_string();
+
+        Shader::comp_shader_from_source(
+            &CString::new(comp_shader_string).map_err(|e| e.to_string())?,
+        )
+    }
+
+    fn from_source(source: &CStr, stage: ShaderStage) -> Result<Shader, String> {
+        let shader_id = backend::with(|backend| backend.compile_shader(stage, source))?;
         Ok(Shader { id: shader_id })
     }
     pub fn vert_shader_from_source(source: &CStr) -> Result<Shader, String> {
-        Shader::from_source(source, gl::VERTEX_SHADER)
+        Shader::from_source(source, ShaderStage::Vertex)
     }
 
     pub fn frag_shader_from_source(source: &CStr) -> Result<Shader, String> {
-        Shader::from_source(source, gl::FRAGMENT_SHADER)
+        Shader::from_source(source, ShaderStage::Fragment)
+    }
+
+    pub fn comp_shader_from_source(source: &CStr) -> Result<Shader, String> {
+        Shader::from_source(source, ShaderStage::Compute)
     }
 
     pub fn id(&self) -> gl::types::GLuint {
@@ -97,44 +115,14 @@ impl Shader {
 }
 impl Drop for Shader {
     fn drop(&mut self) {
-        unsafe {
-            gl::DeleteShader(self.id);
-        }
+        backend::with(|backend| backend.delete_shader(self.id));
     }
 }
 
-fn shader_from_source(source: &CStr, kind: gl::types::GLuint) -> Result<gl::types::GLuint, String> {
-    let id = unsafe { gl::CreateShader(kind) };
-    unsafe {
-        gl::ShaderSource(id, 1, &source.as_ptr(), std::ptr::null());
-        gl::CompileShader(id);
-    }
-
-    let mut success: gl::types::GLint = 1;
-    unsafe {
-        gl::GetShaderiv(id, gl::COMPILE_STATUS, &mut success);
-    }
-
-    if success == 0 {
-        let mut len: gl::types::GLint = 0;
-        unsafe {
-            gl::GetShaderiv(id, gl::INFO_LOG_LENGTH, &mut len);
-        }
-
-        // todo: review this, looks pretty dumb
-        let error: CString = super::create_whitespace_cstring_with_len(len as usize);
-
-        unsafe {
-            gl::GetShaderInfoLog(
-                id,
-                len,
-                std::ptr::null_mut(),
-                error.as_ptr() as *mut gl::types::GLchar,
-            );
-        }
-
-        return Err(error.to_string_lossy().into_owned());
-    }
-
-    Ok(id)
+// returns next ocurrence of _index_ or string length if none found
+fn find_after(source: &str, index: usize) -> usize {
+    source
+        .match_indices("\n!")
+        .find_map(|(i, _)| (i > index).then(|| i))
+        .unwrap_or(source.as_bytes().len())
 }
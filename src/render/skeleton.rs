@@ -0,0 +1,840 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use super::renderer::batch_renderer::BatchRenderer;
+use super::subtexture::Subtexture;
+use super::texture::Texture;
+use crate::assets::{Assets, Handle};
+use crate::resources;
+use crate::time::Time;
+use crate::tween::Tweenable;
+
+// subset of the Spine 3.8 json format: bones, slots, region attachments of the default skin,
+// and animations keyframing bone rotation, translation and scale plus slot attachments.
+// meshes, constraints, other skins and events are ignored.
+// http://esotericsoftware.com/spine-json-format
+#[derive(Deserialize)]
+struct SpineJson {
+    #[serde(default)]
+    skeleton: SpineSkeleton,
+    bones: Vec<SpineBone>,
+    #[serde(default)]
+    slots: Vec<SpineSlot>,
+    #[serde(default)]
+    skins: Option<serde_json::Value>,
+    #[serde(default)]
+    animations: HashMap<String, SpineAnimation>,
+}
+
+#[derive(Deserialize, Default)]
+struct SpineSkeleton {
+    #[serde(default)]
+    spine: String,
+    // where the images of the attachments are, relative to the json
+    #[serde(default)]
+    images: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SpineBone {
+    name: String,
+    parent: Option<String>,
+    #[serde(default)]
+    x: f32,
+    #[serde(default)]
+    y: f32,
+    #[serde(default)]
+    rotation: f32,
+    #[serde(default = "one")]
+    scale_x: f32,
+    #[serde(default = "one")]
+    scale_y: f32,
+}
+
+#[derive(Deserialize)]
+struct SpineSlot {
+    name: String,
+    bone: String,
+    color: Option<String>,
+    attachment: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SpineAttachment {
+    #[serde(rename = "type", default)]
+    kind: Option<String>,
+    // image of the region, defaults to the attachment's name
+    name: Option<String>,
+    path: Option<String>,
+    #[serde(default)]
+    x: f32,
+    #[serde(default)]
+    y: f32,
+    #[serde(default)]
+    rotation: f32,
+    #[serde(default = "one")]
+    scale_x: f32,
+    #[serde(default = "one")]
+    scale_y: f32,
+    #[serde(default)]
+    width: f32,
+    #[serde(default)]
+    height: f32,
+    color: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct SpineAnimation {
+    #[serde(default)]
+    bones: HashMap<String, SpineBoneTimelines>,
+    #[serde(default)]
+    slots: HashMap<String, SpineSlotTimelines>,
+}
+
+#[derive(Deserialize)]
+struct SpineBoneTimelines {
+    #[serde(default)]
+    rotate: Vec<SpineKeyframe>,
+    #[serde(default)]
+    translate: Vec<SpineKeyframe>,
+    #[serde(default)]
+    scale: Vec<SpineKeyframe>,
+}
+
+#[derive(Deserialize)]
+struct SpineSlotTimelines {
+    #[serde(default)]
+    attachment: Vec<SpineAttachmentKeyframe>,
+}
+
+// rotate keys have an angle, translate and scale keys x and y
+#[derive(Deserialize)]
+struct SpineKeyframe {
+    #[serde(default)]
+    time: f32,
+    angle: Option<f32>,
+    x: Option<f32>,
+    y: Option<f32>,
+    // "stepped", or the first control point coordinate of a bezier with the others in c2..c4
+    curve: Option<serde_json::Value>,
+    c2: Option<f32>,
+    c3: Option<f32>,
+    c4: Option<f32>,
+}
+
+#[derive(Deserialize)]
+struct SpineAttachmentKeyframe {
+    #[serde(default)]
+    time: f32,
+    name: Option<String>,
+}
+
+fn one() -> f32 {
+    1.0
+}
+
+// how a keyframe's value changes on the way to the next one
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Curve {
+    Linear,
+    // holds the value until the next keyframe
+    Stepped,
+    // cubic bezier from (0, 0) to (1, 1) with these two control points, x is time and y progress
+    Bezier(glm::Vec2, glm::Vec2),
+}
+
+impl Curve {
+    pub fn apply(self, t: f32) -> f32 {
+        match self {
+            Curve::Linear => t,
+            Curve::Stepped => 0.0,
+            Curve::Bezier(first, second) => {
+                let bezier = |t: f32, p1: f32, p2: f32| {
+                    let u = 1.0 - t;
+                    3.0 * u * u * t * p1 + 3.0 * u * t * t * p2 + t * t * t
+                };
+
+                // x always grows with t, so the t that gets to our time can be bisected
+                let (mut low, mut high) = (0.0, 1.0);
+                for _ in 0..20 {
+                    let middle = (low + high) / 2.0;
+                    if bezier(middle, first.x, second.x) < t {
+                        low = middle;
+                    } else {
+                        high = middle;
+                    }
+                }
+
+                bezier((low + high) / 2.0, first.y, second.y)
+            }
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+struct Keyframe<T> {
+    // seconds
+    time: f32,
+    value: T,
+    curve: Curve,
+}
+
+#[derive(Clone, Debug)]
+struct BoneData {
+    name: String,
+    parent: Option<usize>,
+    position: glm::Vec2,
+    // degrees, counterclockwise
+    rotation: f32,
+    scale: glm::Vec2,
+}
+
+#[derive(Clone, Debug)]
+struct SlotData {
+    name: String,
+    bone: usize,
+    color: glm::Vec4,
+    attachment: Option<String>,
+}
+
+#[derive(Clone, Debug)]
+struct RegionAttachment {
+    // image the attachment shows, see Skeleton::new
+    image: String,
+    position: glm::Vec2,
+    rotation: f32,
+    scale: glm::Vec2,
+    size: glm::Vec2,
+    color: glm::Vec4,
+}
+
+#[derive(Clone, Debug, Default)]
+struct BoneTimelines {
+    // relative to the setup pose: rotation and translation are added to it, scale multiplies it
+    rotate: Vec<Keyframe<f32>>,
+    translate: Vec<Keyframe<glm::Vec2>>,
+    scale: Vec<Keyframe<glm::Vec2>>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct SkeletonAnimation {
+    bones: HashMap<usize, BoneTimelines>,
+    // (seconds, attachment) per slot
+    attachments: HashMap<usize, Vec<(f32, Option<String>)>>,
+    duration: Duration,
+}
+
+impl SkeletonAnimation {
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+}
+
+// bones, slots and animations of a skeleton, shared by every Skeleton posed from it
+pub struct SkeletonData {
+    images_path: String,
+    bones: Vec<BoneData>,
+    // in draw order, back to front
+    slots: Vec<SlotData>,
+    // by slot and attachment name
+    attachments: HashMap<(usize, String), RegionAttachment>,
+    animations: HashMap<String, SkeletonAnimation>,
+}
+
+impl SkeletonData {
+    pub fn from_spine_json(json: &str) -> Result<SkeletonData, String> {
+        let spine: SpineJson =
+            serde_json::from_str(json).map_err(|e| format!("invalid spine skeleton: {}", e))?;
+        if spine.skeleton.spine.starts_with('4') {
+            return Err(format!(
+                "spine {} exports aren't supported, export as 3.8",
+                spine.skeleton.spine
+            ));
+        }
+
+        let mut bones: Vec<BoneData> = Vec::with_capacity(spine.bones.len());
+        for bone in spine.bones {
+            // spine lists parents before their children
+            let parent = match &bone.parent {
+                Some(parent) => Some(
+                    bones
+                        .iter()
+                        .position(|other| other.name == *parent)
+                        .ok_or_else(|| format!("bone {} has an unknown parent", bone.name))?,
+                ),
+                None => None,
+            };
+
+            bones.push(BoneData {
+                name: bone.name,
+                parent,
+                position: glm::vec2(bone.x, bone.y),
+                rotation: bone.rotation,
+                scale: glm::vec2(bone.scale_x, bone.scale_y),
+            });
+        }
+        let bone_index = |name: &str| {
+            bones
+                .iter()
+                .position(|bone| bone.name == name)
+                .ok_or_else(|| format!("unknown bone {}", name))
+        };
+
+        let slots = spine
+            .slots
+            .into_iter()
+            .map(|slot| {
+                Ok(SlotData {
+                    bone: bone_index(&slot.bone)?,
+                    color: parse_color(slot.color.as_deref())?,
+                    attachment: slot.attachment,
+                    name: slot.name,
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        let slot_index = |name: &str| {
+            slots
+                .iter()
+                .position(|slot| slot.name == name)
+                .ok_or_else(|| format!("unknown slot {}", name))
+        };
+
+        let mut attachments = HashMap::new();
+        for (slot, slot_attachments) in default_skin(spine.skins)? {
+            let slot = slot_index(&slot)?;
+            for (name, attachment) in slot_attachments {
+                let attachment: SpineAttachment = serde_json::from_value(attachment)
+                    .map_err(|e| format!("invalid attachment {}: {}", name, e))?;
+                match attachment.kind.as_deref() {
+                    None | Some("region") => {}
+                    // not drawn
+                    Some(_) => continue,
+                }
+
+                let image = attachment
+                    .path
+                    .or(attachment.name)
+                    .unwrap_or_else(|| name.clone());
+                attachments.insert(
+                    (slot, name),
+                    RegionAttachment {
+                        image,
+                        position: glm::vec2(attachment.x, attachment.y),
+                        rotation: attachment.rotation,
+                        scale: glm::vec2(attachment.scale_x, attachment.scale_y),
+                        size: glm::vec2(attachment.width, attachment.height),
+                        color: parse_color(attachment.color.as_deref())?,
+                    },
+                );
+            }
+        }
+
+        let mut animations = HashMap::new();
+        for (name, animation) in spine.animations {
+            let mut parsed = SkeletonAnimation::default();
+            let mut duration: f32 = 0.0;
+
+            for (bone, timelines) in animation.bones {
+                let angle = |key: &SpineKeyframe| key.angle.unwrap_or(0.0);
+                let translation =
+                    |key: &SpineKeyframe| glm::vec2(key.x.unwrap_or(0.0), key.y.unwrap_or(0.0));
+                let scale =
+                    |key: &SpineKeyframe| glm::vec2(key.x.unwrap_or(1.0), key.y.unwrap_or(1.0));
+
+                let bone_timelines = BoneTimelines {
+                    rotate: keyframes(&timelines.rotate, angle)?,
+                    translate: keyframes(&timelines.translate, translation)?,
+                    scale: keyframes(&timelines.scale, scale)?,
+                };
+                let last_keys = [
+                    bone_timelines.rotate.last().map(|key| key.time),
+                    bone_timelines.translate.last().map(|key| key.time),
+                    bone_timelines.scale.last().map(|key| key.time),
+                ];
+                duration = last_keys.iter().flatten().fold(duration, |a, b| a.max(*b));
+
+                parsed.bones.insert(bone_index(&bone)?, bone_timelines);
+            }
+
+            for (slot, timelines) in animation.slots {
+                let keys: Vec<_> = timelines
+                    .attachment
+                    .into_iter()
+                    .map(|key| (key.time, key.name))
+                    .collect();
+                if let Some((time, _)) = keys.last() {
+                    duration = duration.max(*time);
+                }
+
+                parsed.attachments.insert(slot_index(&slot)?, keys);
+            }
+
+            parsed.duration = Duration::try_from_secs_f32(duration)
+                .map_err(|e| format!("animation {} has an invalid duration: {}", name, e))?;
+            animations.insert(name, parsed);
+        }
+
+        Ok(SkeletonData {
+            images_path: spine.skeleton.images,
+            bones,
+            slots,
+            attachments,
+            animations,
+        })
+    }
+
+    // folder the exporter put attachment images in, relative to the json
+    pub fn images_path(&self) -> &str {
+        &self.images_path
+    }
+
+    // names of the images region attachments show
+    pub fn images(&self) -> Vec<&str> {
+        let mut images: Vec<&str> = self
+            .attachments
+            .values()
+            .map(|attachment| attachment.image.as_str())
+            .collect();
+        images.sort_unstable();
+        images.dedup();
+        images
+    }
+
+    pub fn animation(&self, name: &str) -> Option<&SkeletonAnimation> {
+        self.animations.get(name)
+    }
+
+    pub fn animation_names(&self) -> impl Iterator<Item = &str> {
+        self.animations.keys().map(|name| name.as_str())
+    }
+}
+
+// textures of a skeleton's attachments by image name
+pub struct SkeletonImages {
+    textures: HashMap<String, Handle<Texture>>,
+}
+
+impl SkeletonImages {
+    pub fn new(textures: HashMap<String, Handle<Texture>>) -> SkeletonImages {
+        SkeletonImages { textures }
+    }
+
+    // loads <image>.png for every image the attachments of _data_ show, from the images folder
+    // set in the json loaded as _resource_name_. images are Texture assets, so skeletons sharing
+    // them share the textures
+    pub fn load(
+        assets: &mut Assets,
+        resource_name: &str,
+        data: &SkeletonData,
+    ) -> Result<SkeletonImages, String> {
+        let images_path = data
+            .images_path()
+            .trim_start_matches("./")
+            .trim_end_matches('/');
+
+        let mut textures = HashMap::new();
+        for image in data.images() {
+            let image_name = [images_path, &format!("{}.png", image)]
+                .iter()
+                .filter(|part| !part.is_empty())
+                .copied()
+                .collect::<Vec<_>>()
+                .join("/");
+            let texture =
+                assets.load::<Texture>(&resources::relative_to(resource_name, &image_name))?;
+
+            textures.insert(image.to_string(), texture);
+        }
+
+        Ok(SkeletonImages::new(textures))
+    }
+
+    pub fn regions(&self) -> HashMap<String, Subtexture> {
+        self.textures
+            .iter()
+            .map(|(name, texture)| {
+                let region =
+                    Subtexture::from_texture(texture, glm::vec2(0.0, 0.0), glm::vec2(1.0, 1.0));
+                (name.clone(), region)
+            })
+            .collect()
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+struct BonePose {
+    position: glm::Vec2,
+    rotation: f32,
+    scale: glm::Vec2,
+}
+
+// a posed instance of SkeletonData:
+//
+//  let data = assets.load::<SkeletonData>("hero/hero.json")?;
+//  let images = SkeletonImages::load(&mut assets, "hero/hero.json", &data)?;
+//  let mut hero = Skeleton::new(data.clone(), images.regions());
+//  hero.set_animation("walk", true)?;
+//  ...
+//  hero.update(&time);
+//  hero.draw(&mut batch_renderer)?;
+//
+// skeletons are authored with y going up; _position_ is where the root ends up on screen, with
+// y going down like everything else
+pub struct Skeleton {
+    data: Handle<SkeletonData>,
+    regions: HashMap<String, Subtexture>,
+    pose: Vec<BonePose>,
+    // bone to skeleton space
+    world: Vec<glm::Mat3>,
+    slot_attachments: Vec<Option<String>>,
+    // name of the animation playing
+    animation: Option<String>,
+    looping: bool,
+    time: Duration,
+    pub position: glm::Vec2,
+    pub scale: f32,
+    pub color: glm::Vec4,
+}
+
+impl Skeleton {
+    // _regions_ maps the images attachments show to where they are, e.g. SkeletonImages::regions
+    // or frames of a packed sprite sheet
+    pub fn new(data: Handle<SkeletonData>, regions: HashMap<String, Subtexture>) -> Skeleton {
+        let mut skeleton = Skeleton {
+            world: vec![glm::Mat3::identity(); data.bones.len()],
+            data,
+            regions,
+            pose: Vec::new(),
+            slot_attachments: Vec::new(),
+            animation: None,
+            looping: false,
+            time: Duration::ZERO,
+            position: glm::vec2(0.0, 0.0),
+            scale: 1.0,
+            color: glm::vec4(1.0, 1.0, 1.0, 1.0),
+        };
+        skeleton.set_to_setup_pose();
+        skeleton
+    }
+
+    pub fn set_to_setup_pose(&mut self) {
+        self.pose = self
+            .data
+            .bones
+            .iter()
+            .map(|bone| BonePose {
+                position: bone.position,
+                rotation: bone.rotation,
+                scale: bone.scale,
+            })
+            .collect();
+        self.slot_attachments = self
+            .data
+            .slots
+            .iter()
+            .map(|slot| slot.attachment.clone())
+            .collect();
+        self.update_world_transforms();
+    }
+
+    // starts _name_ from its beginning
+    pub fn set_animation(&mut self, name: &str, looping: bool) -> Result<(), String> {
+        if self.data.animation(name).is_none() {
+            return Err(format!("skeleton has no animation {}", name));
+        }
+
+        self.animation = Some(name.to_string());
+        self.looping = looping;
+        self.time = Duration::ZERO;
+        // timelines only key what they change, the rest goes back to how it was set up
+        self.set_to_setup_pose();
+        self.apply();
+        Ok(())
+    }
+
+    pub fn is_finished(&self) -> bool {
+        match self.current_animation() {
+            Some(animation) => !self.looping && self.time >= animation.duration,
+            None => true,
+        }
+    }
+
+    pub fn update(&mut self, time: &Time) {
+        self.advance(time.delta());
+    }
+
+    pub fn advance(&mut self, dt: Duration) {
+        let duration = match self.current_animation() {
+            Some(animation) => animation.duration,
+            None => return,
+        };
+
+        self.time += dt;
+        if self.time >= duration {
+            self.time = if self.looping && !duration.is_zero() {
+                Duration::from_secs_f64(self.time.as_secs_f64() % duration.as_secs_f64())
+            } else {
+                duration
+            };
+        }
+
+        self.apply();
+    }
+
+    // skeleton space to screen, flipping y
+    pub fn transform(&self) -> glm::Mat3 {
+        let transform = glm::translate2d(&glm::Mat3::identity(), &self.position);
+        glm::scale2d(&transform, &glm::vec2(self.scale, -self.scale))
+    }
+
+    // where _bone_ is on screen
+    pub fn bone_position(&self, bone: &str) -> Option<glm::Vec2> {
+        let bone = self
+            .data
+            .bones
+            .iter()
+            .position(|other| other.name == bone)?;
+        let position = self.transform() * self.world[bone] * glm::vec3(0.0, 0.0, 1.0);
+        Some(position.xy())
+    }
+
+    // submits every visible region attachment, back to front
+    pub fn draw(&self, batch: &mut BatchRenderer) -> Result<(), String> {
+        let transform = self.transform();
+
+        for (slot_index, slot) in self.data.slots.iter().enumerate() {
+            let name = match &self.slot_attachments[slot_index] {
+                Some(name) => name,
+                None => continue,
+            };
+            let attachment = match self.data.attachments.get(&(slot_index, name.clone())) {
+                Some(attachment) => attachment,
+                None => continue,
+            };
+            let region = match self.regions.get(&attachment.image) {
+                Some(region) => region,
+                None => continue,
+            };
+
+            let local = glm::translate2d(&glm::Mat3::identity(), &attachment.position);
+            let local = glm::rotate2d(&local, attachment.rotation.to_radians());
+            let local = glm::scale2d(&local, &attachment.scale);
+            let to_screen = transform * self.world[slot.bone] * local;
+
+            let half = attachment.size / 2.0;
+            // top left, top right, bottom right, bottom left of the image, with y up
+            let corners = [
+                glm::vec2(-half.x, half.y),
+                glm::vec2(half.x, half.y),
+                glm::vec2(half.x, -half.y),
+                glm::vec2(-half.x, -half.y),
+            ]
+            .map(|corner| (to_screen * glm::vec3(corner.x, corner.y, 1.0)).xy());
+
+            let color = self
+                .color
+                .component_mul(&slot.color)
+                .component_mul(&attachment.color);
+            batch.draw_transformed_quad(
+                region.texture(),
+                corners,
+                region.get_normalized_rect(),
+                color,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    fn current_animation(&self) -> Option<&SkeletonAnimation> {
+        self.data.animation(self.animation.as_deref()?)
+    }
+
+    // poses the skeleton at the current time of the animation
+    fn apply(&mut self) {
+        // a handle of its own, so the animation doesn't keep self borrowed while posing
+        let data = self.data.clone();
+        let animation = match self
+            .animation
+            .as_deref()
+            .and_then(|name| data.animation(name))
+        {
+            Some(animation) => animation,
+            None => return,
+        };
+        let time = self.time.as_secs_f32();
+
+        for (bone, setup) in self.data.bones.iter().enumerate() {
+            let mut pose = BonePose {
+                position: setup.position,
+                rotation: setup.rotation,
+                scale: setup.scale,
+            };
+
+            if let Some(timelines) = animation.bones.get(&bone) {
+                if let Some(rotation) = sample_rotation(&timelines.rotate, time) {
+                    pose.rotation += rotation;
+                }
+                if let Some(translation) = sample(&timelines.translate, time) {
+                    pose.position += translation;
+                }
+                if let Some(scale) = sample(&timelines.scale, time) {
+                    pose.scale = pose.scale.component_mul(&scale);
+                }
+            }
+
+            self.pose[bone] = pose;
+        }
+
+        for (slot, keys) in &animation.attachments {
+            // before the first key the slot keeps its setup attachment
+            if let Some((_, attachment)) = keys.iter().rev().find(|(key, _)| *key <= time) {
+                self.slot_attachments[*slot] = attachment.clone();
+            }
+        }
+
+        self.update_world_transforms();
+    }
+
+    fn update_world_transforms(&mut self) {
+        for (bone, data) in self.data.bones.iter().enumerate() {
+            let pose = self.pose[bone];
+            let local = glm::translate2d(&glm::Mat3::identity(), &pose.position);
+            let local = glm::rotate2d(&local, pose.rotation.to_radians());
+            let local = glm::scale2d(&local, &pose.scale);
+
+            // parents come first, so theirs are already up to date
+            self.world[bone] = match data.parent {
+                Some(parent) => self.world[parent] * local,
+                None => local,
+            };
+        }
+    }
+}
+
+fn keyframes<T>(
+    keys: &[SpineKeyframe],
+    value: impl Fn(&SpineKeyframe) -> T,
+) -> Result<Vec<Keyframe<T>>, String> {
+    keys.iter()
+        .map(|key| {
+            let curve = match &key.curve {
+                None => Curve::Linear,
+                Some(serde_json::Value::String(curve)) if curve == "stepped" => Curve::Stepped,
+                Some(serde_json::Value::Number(c1)) => Curve::Bezier(
+                    glm::vec2(c1.as_f64().unwrap_or(0.0) as f32, key.c2.unwrap_or(0.0)),
+                    glm::vec2(key.c3.unwrap_or(1.0), key.c4.unwrap_or(1.0)),
+                ),
+                Some(_) => return Err("unsupported keyframe curve".to_string()),
+            };
+
+            Ok(Keyframe {
+                time: key.time,
+                value: value(key),
+                curve,
+            })
+        })
+        .collect()
+}
+
+// value of the timeline at _time_, None before its first key
+fn sample<T: Tweenable>(keys: &[Keyframe<T>], time: f32) -> Option<T> {
+    let (from, to) = surrounding_keys(keys, time)?;
+    match to {
+        Some(to) => {
+            let t = from.curve.apply((time - from.time) / (to.time - from.time));
+            Some(T::lerp(from.value, to.value, t))
+        }
+        None => Some(from.value),
+    }
+}
+
+// like sample, but turning the short way around
+fn sample_rotation(keys: &[Keyframe<f32>], time: f32) -> Option<f32> {
+    let (from, to) = surrounding_keys(keys, time)?;
+    match to {
+        Some(to) => {
+            let t = from.curve.apply((time - from.time) / (to.time - from.time));
+            let difference = (to.value - from.value + 180.0).rem_euclid(360.0) - 180.0;
+            Some(from.value + difference * t)
+        }
+        None => Some(from.value),
+    }
+}
+
+// the last key at or before _time_ and the one after it, if any
+fn surrounding_keys<T>(
+    keys: &[Keyframe<T>],
+    time: f32,
+) -> Option<(&Keyframe<T>, Option<&Keyframe<T>>)> {
+    let from = keys.iter().rposition(|key| key.time <= time)?;
+    let to = keys.get(from + 1).filter(|to| to.time > keys[from].time);
+    Some((&keys[from], to))
+}
+
+// "rrggbbaa"
+fn parse_color(color: Option<&str>) -> Result<glm::Vec4, String> {
+    let color = match color {
+        Some(color) => color,
+        None => return Ok(glm::vec4(1.0, 1.0, 1.0, 1.0)),
+    };
+
+    let channel = |i: usize| {
+        color
+            .get(i * 2..i * 2 + 2)
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+            .map(|value| value as f32 / 255.0)
+            .ok_or_else(|| format!("invalid color {}", color))
+    };
+    Ok(glm::vec4(
+        channel(0)?,
+        channel(1)?,
+        channel(2)?,
+        channel(3)?,
+    ))
+}
+
+// (slot name, (attachment name, attachment json) of the slot)
+type SkinAttachments = Vec<(String, Vec<(String, serde_json::Value)>)>;
+
+// attachments of the default skin by slot. 3.8 exports skins as an array of named skins, older
+// versions as an object keyed by skin name
+fn default_skin(skins: Option<serde_json::Value>) -> Result<SkinAttachments, String> {
+    let skin = match skins {
+        None => return Ok(Vec::new()),
+        Some(serde_json::Value::Array(skins)) => skins.into_iter().find_map(|skin| match skin {
+            serde_json::Value::Object(mut skin) => {
+                let is_default = matches!(
+                    skin.get("name"),
+                    Some(serde_json::Value::String(name)) if name == "default"
+                );
+                if is_default {
+                    skin.remove("attachments")
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        }),
+        Some(serde_json::Value::Object(mut skins)) => skins.remove("default"),
+        Some(_) => return Err("invalid skins".to_string()),
+    };
+
+    let slots = match skin {
+        Some(serde_json::Value::Object(slots)) => slots,
+        None => return Ok(Vec::new()),
+        Some(_) => return Err("invalid default skin".to_string()),
+    };
+
+    slots
+        .into_iter()
+        .map(|(slot, attachments)| match attachments {
+            serde_json::Value::Object(attachments) => Ok((slot, attachments.into_iter().collect())),
+            _ => Err(format!("invalid attachments of slot {}", slot)),
+        })
+        .collect()
+}
@@ -0,0 +1,231 @@
+use std::ops::RangeInclusive;
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use super::animation::{Animation, AnimationFrame, PlaybackMode};
+use super::subtexture::Subtexture;
+use super::texture::{PixelRect, Texture};
+use crate::assets::Handle;
+
+// the parts of Aseprite's "export sprite sheet" json we use; both the array and the hash
+// layouts of "frames" are accepted
+#[derive(Deserialize)]
+struct AsepriteSheet {
+    frames: AsepriteFrames,
+    meta: AsepriteMeta,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum AsepriteFrames {
+    Array(Vec<AsepriteFrame>),
+    // keyed by file name, in frame order as long as serde_json preserves it
+    Hash(serde_json::Map<String, serde_json::Value>),
+}
+
+#[derive(Deserialize)]
+struct AsepriteFrame {
+    frame: AsepriteRect,
+    #[serde(default)]
+    trimmed: bool,
+    // milliseconds
+    duration: u64,
+}
+
+#[derive(Deserialize)]
+struct AsepriteRect {
+    x: usize,
+    y: usize,
+    w: usize,
+    h: usize,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AsepriteMeta {
+    image: String,
+    #[serde(default)]
+    frame_tags: Vec<AsepriteTag>,
+}
+
+#[derive(Deserialize)]
+struct AsepriteTag {
+    name: String,
+    from: usize,
+    to: usize,
+    #[serde(default)]
+    direction: String,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct SheetFrame {
+    // in pixels from the top left corner of the sheet
+    pub rect: PixelRect,
+    pub duration: Duration,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SheetTag {
+    pub name: String,
+    pub frames: RangeInclusive<usize>,
+    pub mode: PlaybackMode,
+}
+
+// where the frames of a sprite sheet are, without the image itself
+#[derive(Clone, Debug)]
+pub struct SpriteSheetLayout {
+    // file name of the sheet image, relative to the file the layout was read from
+    pub image: String,
+    pub frames: Vec<SheetFrame>,
+    pub tags: Vec<SheetTag>,
+}
+
+impl SpriteSheetLayout {
+    pub fn from_aseprite_json(json: &str) -> Result<SpriteSheetLayout, String> {
+        let sheet: AsepriteSheet = serde_json::from_str(json)
+            .map_err(|e| format!("invalid aseprite sprite sheet: {}", e))?;
+
+        let frames = match sheet.frames {
+            AsepriteFrames::Array(frames) => frames,
+            AsepriteFrames::Hash(frames) => frames
+                .into_iter()
+                .map(|(name, frame)| {
+                    serde_json::from_value(frame)
+                        .map_err(|e| format!("invalid sprite sheet frame {}: {}", name, e))
+                })
+                .collect::<Result<_, _>>()?,
+        };
+
+        let frames = frames
+            .into_iter()
+            .enumerate()
+            .map(|(i, frame)| {
+                // trimmed frames would need an offset to be drawn where the artist put them
+                if frame.trimmed {
+                    return Err(format!(
+                        "frame {} is trimmed, export sprite sheets without trimming",
+                        i
+                    ));
+                }
+
+                Ok(SheetFrame {
+                    rect: PixelRect {
+                        x: frame.frame.x,
+                        y: frame.frame.y,
+                        width: frame.frame.w,
+                        height: frame.frame.h,
+                    },
+                    duration: Duration::from_millis(frame.duration),
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let tags = sheet
+            .meta
+            .frame_tags
+            .into_iter()
+            .map(|tag| {
+                if tag.from > tag.to || tag.to >= frames.len() {
+                    return Err(format!(
+                        "tag {} spans frames {}..={} but the sheet has {}",
+                        tag.name,
+                        tag.from,
+                        tag.to,
+                        frames.len()
+                    ));
+                }
+
+                let mode = match tag.direction.as_str() {
+                    "" | "forward" => PlaybackMode::Loop,
+                    "reverse" => PlaybackMode::Reverse,
+                    "pingpong" => PlaybackMode::PingPong,
+                    "pingpong_reverse" => PlaybackMode::PingPongReverse,
+                    direction => {
+                        return Err(format!(
+                            "tag {} has unknown direction {}",
+                            tag.name, direction
+                        ))
+                    }
+                };
+
+                Ok(SheetTag {
+                    name: tag.name,
+                    frames: tag.from..=tag.to,
+                    mode,
+                })
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(SpriteSheetLayout {
+            image: sheet.meta.image,
+            frames,
+            tags,
+        })
+    }
+}
+
+// atlas texture together with the frames and tags exported along with it.
+// see Assets::load::<SpriteSheet>
+pub struct SpriteSheet {
+    texture: Handle<Texture>,
+    layout: SpriteSheetLayout,
+}
+
+impl SpriteSheet {
+    pub fn new(texture: Handle<Texture>, layout: SpriteSheetLayout) -> SpriteSheet {
+        SpriteSheet { texture, layout }
+    }
+
+    pub fn texture(&self) -> &Handle<Texture> {
+        &self.texture
+    }
+
+    pub fn frames(&self) -> &[SheetFrame] {
+        &self.layout.frames
+    }
+
+    pub fn tags(&self) -> &[SheetTag] {
+        &self.layout.tags
+    }
+
+    pub fn tag(&self, name: &str) -> Option<&SheetTag> {
+        self.layout.tags.iter().find(|tag| tag.name == name)
+    }
+
+    pub fn subtexture(&self, frame: usize) -> Option<Subtexture> {
+        self.layout
+            .frames
+            .get(frame)
+            .map(|frame| Subtexture::from_pixel_rect(&self.texture, frame.rect))
+    }
+
+    // the frames of tag _name_ with the durations and direction they were exported with
+    pub fn animation(&self, name: &str) -> Result<Animation, String> {
+        let tag = self
+            .tag(name)
+            .ok_or_else(|| format!("sprite sheet has no tag {}", name))?;
+
+        let mut animation = self.animation_for_frames(tag.frames.clone())?;
+        animation.set_playback_mode(tag.mode);
+        animation.reset();
+
+        Ok(animation)
+    }
+
+    pub fn animation_for_frames(&self, frames: RangeInclusive<usize>) -> Result<Animation, String> {
+        let frames = self
+            .layout
+            .frames
+            .get(frames.clone())
+            .ok_or_else(|| format!("sprite sheet has no frames {:?}", frames))?
+            .iter()
+            .map(|frame| AnimationFrame {
+                subtexture: Subtexture::from_pixel_rect(&self.texture, frame.rect),
+                duration: frame.duration,
+            })
+            .collect();
+
+        Animation::new(frames)
+    }
+}
@@ -0,0 +1,100 @@
+use std::cell::Cell;
+
+// counters of the work submitted to GL during a frame. every renderer reports into the same
+// per-thread counters (GL contexts are per thread too), so the numbers cover the whole frame
+// no matter which renderer drew what
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct RenderStats {
+    pub draw_calls: u32,
+    pub quads: u32,
+    pub vertices: u32,
+    pub indices: u32,
+    pub texture_binds: u32,
+    pub program_switches: u32,
+    pub buffer_bytes_uploaded: usize,
+}
+
+thread_local! {
+    static CURRENT: Cell<RenderStats> = Cell::new(RenderStats::default());
+    static LAST_FRAME: Cell<RenderStats> = Cell::new(RenderStats::default());
+    static USED_PROGRAM: Cell<gl::types::GLuint> = const { Cell::new(0) };
+}
+
+fn update(f: impl FnOnce(&mut RenderStats)) {
+    CURRENT.with(|current| {
+        let mut stats = current.get();
+        f(&mut stats);
+        current.set(stats);
+    });
+}
+
+pub fn record_draw_call(vertices: u32, quads: u32) {
+    update(|stats| {
+        stats.draw_calls += 1;
+        stats.vertices += vertices;
+        stats.quads += quads;
+    });
+}
+
+// for draws that only know how many indices they read, not how many distinct vertices those
+// refer to, like Mesh
+pub fn record_indexed_draw_call(indices: u32) {
+    update(|stats| {
+        stats.draw_calls += 1;
+        stats.indices += indices;
+    });
+}
+
+// for quads drawn through generic geometry like Mesh, which only knows about its indices
+pub fn record_quads(quads: u32) {
+    update(|stats| stats.quads += quads);
+}
+
+pub fn record_texture_bind() {
+    update(|stats| stats.texture_binds += 1);
+}
+
+// only counts actual changes of program, using the same one twice in a row is free
+pub fn record_program_use(program: gl::types::GLuint) {
+    let previous = USED_PROGRAM.with(|used| used.replace(program));
+    if previous != program {
+        update(|stats| stats.program_switches += 1);
+    }
+}
+
+pub fn record_upload(bytes: usize) {
+    update(|stats| stats.buffer_bytes_uploaded += bytes);
+}
+
+// stats gathered so far in the frame being drawn
+pub fn current() -> RenderStats {
+    CURRENT.with(|current| current.get())
+}
+
+// stats of the last complete frame
+pub fn last_frame() -> RenderStats {
+    LAST_FRAME.with(|last| last.get())
+}
+
+// closes the frame: what was gathered becomes last_frame() and counters start over
+pub fn end_frame() {
+    let stats = CURRENT.with(|current| current.replace(RenderStats::default()));
+    LAST_FRAME.with(|last| last.set(stats));
+}
+
+pub fn draw_overlay(ui: &imgui::Ui, stats: &RenderStats) {
+    imgui::Window::new("Render stats")
+        .always_auto_resize(true)
+        .build(ui, || {
+            ui.text(format!("draw calls: {}", stats.draw_calls));
+            ui.text(format!("quads: {}", stats.quads));
+            ui.text(format!("vertices: {}", stats.vertices));
+            ui.text(format!("indices: {}", stats.indices));
+            ui.text(format!("texture binds: {}", stats.texture_binds));
+            ui.text(format!("program switches: {}", stats.program_switches));
+            ui.text(format!(
+                "uploaded: {:.1} KiB",
+                stats.buffer_bytes_uploaded as f32 / 1024.0
+            ));
+        });
+}
@@ -1,27 +1,35 @@
-use super::texture::Texture;
+use super::texture::{PixelRect, Texture};
+use crate::assets::Handle;
 use glm;
 
-pub struct Subtexture<'a> {
-    texture: &'a Texture,
+// a region of a texture. holds on to the texture itself, so it can be kept around without
+// borrowing from whoever loaded it
+#[derive(Clone)]
+pub struct Subtexture {
+    texture: Handle<Texture>,
     normalized_rect: glm::Vec4, // todo: should this struct be renamed/aliased into Rect? we want the data in the format of x, y, width, height and not x1, y1, x2, y2
 }
 
-impl<'a> Subtexture<'a> {
+impl Subtexture {
     pub fn get_normalized_rect(&self) -> glm::Vec4 {
         self.normalized_rect
     }
 
     pub fn texture(&self) -> &Texture {
-        self.texture
+        &self.texture
+    }
+
+    pub fn texture_handle(&self) -> &Handle<Texture> {
+        &self.texture
     }
 
     pub fn from_texture(
-        texture: &'a Texture,
+        texture: &Handle<Texture>,
         start_coords_normalized: glm::Vec2,
         dimensions_normalized: glm::Vec2,
     ) -> Subtexture {
         Subtexture {
-            texture,
+            texture: texture.clone(),
             normalized_rect: glm::vec4(
                 start_coords_normalized.x,
                 start_coords_normalized.y,
@@ -31,8 +39,23 @@ impl<'a> Subtexture<'a> {
         }
     }
 
+    // _rect_ in pixels from the top left corner, like sprite sheet tools export them
+    pub fn from_pixel_rect(texture: &Handle<Texture>, rect: PixelRect) -> Subtexture {
+        Subtexture::from_texture(
+            texture,
+            glm::vec2(
+                rect.x as f32 / texture.width_f(),
+                rect.y as f32 / texture.height_f(),
+            ),
+            glm::vec2(
+                rect.width as f32 / texture.width_f(),
+                rect.height as f32 / texture.height_f(),
+            ),
+        )
+    }
+
     pub fn from_tiles(
-        texture: &'a Texture,
+        texture: &Handle<Texture>,
         tile_index_x: u16,
         tile_index_y: u16,
         tile_dimensions: glm::Vec2,
@@ -47,7 +70,7 @@ impl<'a> Subtexture<'a> {
         );
 
         Subtexture {
-            texture,
+            texture: texture.clone(),
             normalized_rect: glm::vec4(
                 start_coords_normalized.x,
                 start_coords_normalized.y,
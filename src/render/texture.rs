@@ -1,22 +1,272 @@
-use gl::types::{GLint};
+use gl::types::GLenum;
 
+use super::backend::{self, PixelData, TextureTarget};
+use super::debug::ObjectKind;
+use super::image::{Image, ImagePixels};
+use super::stats;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TextureFilter {
+    Nearest,
+    Linear,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TextureWrap {
+    Repeat,
+    MirroredRepeat,
+    ClampToEdge,
+    ClampToBorder,
+}
+
+// internal format the texture is stored with on the GPU; sRGB variants are decoded to linear
+// when sampled, the 16F formats keep HDR values outside of 0..1
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TextureFormat {
+    R8,
+    RG8,
+    RGB8,
+    RGBA8,
+    SRGB8,
+    SRGB8Alpha8,
+    R16F,
+    RG16F,
+    RGB16F,
+    RGBA16F,
+}
+
+impl TextureFormat {
+    pub fn channels(&self) -> usize {
+        match self {
+            TextureFormat::R8 | TextureFormat::R16F => 1,
+            TextureFormat::RG8 | TextureFormat::RG16F => 2,
+            TextureFormat::RGB8 | TextureFormat::SRGB8 | TextureFormat::RGB16F => 3,
+            TextureFormat::RGBA8 | TextureFormat::SRGB8Alpha8 | TextureFormat::RGBA16F => 4,
+        }
+    }
+
+    // plain format storing _channels_ components, as floats if _hdr_
+    pub fn from_channels(channels: usize, hdr: bool) -> Result<TextureFormat, String> {
+        match (channels, hdr) {
+            (1, false) => Ok(TextureFormat::R8),
+            (2, false) => Ok(TextureFormat::RG8),
+            (3, false) => Ok(TextureFormat::RGB8),
+            (4, false) => Ok(TextureFormat::RGBA8),
+            (1, true) => Ok(TextureFormat::R16F),
+            (2, true) => Ok(TextureFormat::RG16F),
+            (3, true) => Ok(TextureFormat::RGB16F),
+            (4, true) => Ok(TextureFormat::RGBA16F),
+            _ => Err(format!("no texture format with {} channels", channels)),
+        }
+    }
+
+    pub fn is_srgb(&self) -> bool {
+        matches!(self, TextureFormat::SRGB8 | TextureFormat::SRGB8Alpha8)
+    }
+
+    pub fn is_float(&self) -> bool {
+        matches!(
+            self,
+            TextureFormat::R16F
+                | TextureFormat::RG16F
+                | TextureFormat::RGB16F
+                | TextureFormat::RGBA16F
+        )
+    }
+
+    pub fn internal_format(&self) -> GLenum {
+        match self {
+            TextureFormat::R8 => gl::R8,
+            TextureFormat::RG8 => gl::RG8,
+            TextureFormat::RGB8 => gl::RGB8,
+            TextureFormat::RGBA8 => gl::RGBA8,
+            TextureFormat::SRGB8 => gl::SRGB8,
+            TextureFormat::SRGB8Alpha8 => gl::SRGB8_ALPHA8,
+            TextureFormat::R16F => gl::R16F,
+            TextureFormat::RG16F => gl::RG16F,
+            TextureFormat::RGB16F => gl::RGB16F,
+            TextureFormat::RGBA16F => gl::RGBA16F,
+        }
+    }
+
+    // layout of the pixel data we hand to GL
+    pub fn pixel_format(&self) -> GLenum {
+        match self.channels() {
+            1 => gl::RED,
+            2 => gl::RG,
+            3 => gl::RGB,
+            _ => gl::RGBA,
+        }
+    }
+
+    // the sRGB counterpart of 8 bit color formats, other formats are returned as they are
+    pub fn to_srgb(self) -> TextureFormat {
+        match self {
+            TextureFormat::RGB8 => TextureFormat::SRGB8,
+            TextureFormat::RGBA8 => TextureFormat::SRGB8Alpha8,
+            other => other,
+        }
+    }
+}
+
+// describes how a texture is stored and sampled. defaults match what pixel art needs
+// (nearest filtering, no mipmaps); see TextureDescriptor::smooth for UI art and photos
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct TextureDescriptor {
+    pub min_filter: TextureFilter,
+    pub mag_filter: TextureFilter,
+    // None means the texture has a single level; otherwise mipmaps are generated on upload
+    // and sampled between levels with the given filter
+    pub mipmaps: Option<TextureFilter>,
+    pub wrap_s: TextureWrap,
+    pub wrap_t: TextureWrap,
+    pub format: TextureFormat,
+}
+
+impl Default for TextureDescriptor {
+    fn default() -> TextureDescriptor {
+        TextureDescriptor::pixel_art()
+    }
+}
+
+impl TextureDescriptor {
+    pub fn new() -> TextureDescriptor {
+        TextureDescriptor::default()
+    }
+
+    pub fn pixel_art() -> TextureDescriptor {
+        TextureDescriptor {
+            min_filter: TextureFilter::Nearest,
+            mag_filter: TextureFilter::Nearest,
+            mipmaps: None,
+            wrap_s: TextureWrap::MirroredRepeat,
+            wrap_t: TextureWrap::MirroredRepeat,
+            format: TextureFormat::RGBA8,
+        }
+    }
+
+    pub fn smooth() -> TextureDescriptor {
+        TextureDescriptor {
+            min_filter: TextureFilter::Linear,
+            mag_filter: TextureFilter::Linear,
+            mipmaps: Some(TextureFilter::Linear),
+            wrap_s: TextureWrap::ClampToEdge,
+            wrap_t: TextureWrap::ClampToEdge,
+            format: TextureFormat::RGBA8,
+        }
+    }
+
+    pub fn filter(self, filter: TextureFilter) -> TextureDescriptor {
+        TextureDescriptor {
+            min_filter: filter,
+            mag_filter: filter,
+            ..self
+        }
+    }
+
+    pub fn min_filter(self, min_filter: TextureFilter) -> TextureDescriptor {
+        TextureDescriptor { min_filter, ..self }
+    }
+
+    pub fn mag_filter(self, mag_filter: TextureFilter) -> TextureDescriptor {
+        TextureDescriptor { mag_filter, ..self }
+    }
+
+    pub fn mipmaps(self, mipmaps: Option<TextureFilter>) -> TextureDescriptor {
+        TextureDescriptor { mipmaps, ..self }
+    }
+
+    pub fn wrap(self, wrap: TextureWrap) -> TextureDescriptor {
+        TextureDescriptor {
+            wrap_s: wrap,
+            wrap_t: wrap,
+            ..self
+        }
+    }
+
+    pub fn wrap_s(self, wrap_s: TextureWrap) -> TextureDescriptor {
+        TextureDescriptor { wrap_s, ..self }
+    }
+
+    pub fn wrap_t(self, wrap_t: TextureWrap) -> TextureDescriptor {
+        TextureDescriptor { wrap_t, ..self }
+    }
+
+    pub fn format(self, format: TextureFormat) -> TextureDescriptor {
+        TextureDescriptor { format, ..self }
+    }
+
+    pub fn srgb(self) -> TextureDescriptor {
+        TextureDescriptor {
+            format: self.format.to_srgb(),
+            ..self
+        }
+    }
+}
+
+pub(super) fn check_data_len(
+    len: usize,
+    width: usize,
+    height: usize,
+    channels: usize,
+) -> Result<(), String> {
+    let expected_len = width * height * channels;
+    if len < expected_len {
+        return Err(format!(
+            "texture data too short for {}x{} with {} channels: got {} components, expected {}",
+            width, height, channels, len, expected_len
+        ));
+    }
+
+    Ok(())
+}
+
+// area of a texture in pixels, starting at its top left corner
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct PixelRect {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+// keeps the first _kept_row_len_ components of the first _rows_ rows
+fn crop_rows<T: Copy>(data: &[T], row_len: usize, kept_row_len: usize, rows: usize) -> Vec<T> {
+    data.chunks(row_len)
+        .take(rows)
+        .flat_map(|row| &row[..kept_row_len])
+        .copied()
+        .collect()
+}
 
-use gl;
 // https://learnopengl.com/code_viewer_gh.php?code=src/7.in_practice/3.2d_game/0.full_source/texture.h
 pub struct Texture {
     id: gl::types::GLuint,
     width: usize,
     height: usize,
+    descriptor: TextureDescriptor,
 }
 
 impl Texture {
     pub fn new(width: usize, height: usize) -> Texture {
-        let mut id = 0;
-        unsafe {
-            gl::GenTextures(1, &mut id);
+        Texture {
+            id: backend::with(|backend| backend.create_texture()),
+            width,
+            height,
+            descriptor: TextureDescriptor::default(),
         }
+    }
+
+    pub fn id(&self) -> gl::types::GLuint {
+        self.id
+    }
 
-        Texture { id, width, height }
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
     }
 
     pub fn width_f(&self) -> f32 {
@@ -26,64 +276,211 @@ impl Texture {
         self.height as f32
     }
 
+    pub fn descriptor(&self) -> &TextureDescriptor {
+        &self.descriptor
+    }
+
+    // names the texture in GL debug messages and debuggers
+    pub fn set_label(&self, label: &str) {
+        backend::with(|backend| backend.set_label(ObjectKind::Texture, self.id, label));
+    }
+
     pub fn from_data(data: Vec<u8>, width: usize, height: usize) -> Result<Texture, String> {
-        let texture = Texture::new(width, height);
-        print!("id of texture is {}", texture.id);
-        unsafe {
-            gl::BindTexture(gl::TEXTURE_2D, texture.id);
-
-            gl::ActiveTexture(gl::TEXTURE0);
-            //https://www.khronos.org/registry/OpenGL-Refpages/gl4/html/glTexParameter.xhtml#:~:text=to%20GL_REPEAT.-,Notes,-Suppose%20that%20a
-            // todo: image data should be freed here, probably
-            gl::TexImage2D(
-                gl::TEXTURE_2D,
-                0,
-                gl::RGBA as i32,
-                width as i32,
-                height as i32,
-                0,
-                gl::RGBA,
-                gl::UNSIGNED_BYTE,
-                data.as_ptr() as *const gl::types::GLvoid,
-            );
-
-            // set Texture wrap and filter modes
-            gl::TexParameteri(
-                gl::TEXTURE_2D,
-                gl::TEXTURE_WRAP_S,
-                gl::MIRRORED_REPEAT as GLint,
-            );
-            gl::TexParameteri(
-                gl::TEXTURE_2D,
-                gl::TEXTURE_WRAP_T,
-                gl::MIRRORED_REPEAT as GLint,
-            );
-
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::NEAREST as GLint);
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAX_LEVEL, 0 as GLint);
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as GLint);
-
-            //gl::GenerateMipmap(gl::TEXTURE_2D);
+        Texture::with_descriptor(&data, width, height, &TextureDescriptor::default())
+    }
+
+    // _data_ holds 8 bit components laid out as descriptor.format.channels() per pixel
+    pub fn with_descriptor(
+        data: &[u8],
+        width: usize,
+        height: usize,
+        descriptor: &TextureDescriptor,
+    ) -> Result<Texture, String> {
+        check_data_len(data.len(), width, height, descriptor.format.channels())?;
+
+        Ok(Texture::create(
+            width,
+            height,
+            descriptor,
+            PixelData::U8(data),
+        ))
+    }
+
+    // uploads _image_ as it is; its channel count has to match descriptor.format.
+    // float images can go into 8 bit formats (values get clamped) and the other way around
+    pub fn from_image(image: &Image, descriptor: &TextureDescriptor) -> Result<Texture, String> {
+        if image.channels != descriptor.format.channels() {
+            return Err(format!(
+                "can't store a {} channel image as {:?}",
+                image.channels, descriptor.format
+            ));
+        }
+
+        let data = match &image.pixels {
+            ImagePixels::U8(data) => {
+                check_data_len(data.len(), image.width, image.height, image.channels)?;
+                PixelData::U8(data)
+            }
+            ImagePixels::F32(data) => {
+                check_data_len(data.len(), image.width, image.height, image.channels)?;
+                PixelData::F32(data)
+            }
+        };
+
+        Ok(Texture::create(image.width, image.height, descriptor, data))
+    }
+
+    // texture with uninitialized contents, meant to be filled with update_region
+    pub fn empty(width: usize, height: usize, descriptor: &TextureDescriptor) -> Texture {
+        Texture::create(width, height, descriptor, PixelData::None)
+    }
+
+    fn create(
+        width: usize,
+        height: usize,
+        descriptor: &TextureDescriptor,
+        data: PixelData,
+    ) -> Texture {
+        let mut texture = Texture::new(width, height);
+        texture.descriptor = *descriptor;
+        backend::with(|backend| {
+            backend.bind_texture(TextureTarget::Texture2D, texture.id);
+
+            backend.active_texture(0);
+            texture.allocate(backend, data);
+            backend.set_sampling(TextureTarget::Texture2D, descriptor);
+
             // unbind texture
-            gl::BindTexture(gl::TEXTURE_2D, 0);
+            backend.bind_texture(TextureTarget::Texture2D, 0);
+        });
+
+        texture
+    }
+
+    // (re)specifies level 0 with the current dimensions; texture must be bound
+    fn allocate(&self, backend: &mut dyn backend::RenderBackend, data: PixelData) {
+        backend.texture_image_2d(self.width, self.height, self.descriptor.format, data);
+    }
+
+    // overwrites _rect_ with _data_, 8 bit components laid out as descriptor.format.channels()
+    // per pixel, row by row
+    pub fn update_region(&self, rect: PixelRect, data: &[u8]) -> Result<(), String> {
+        self.check_region(&rect, data.len())?;
+        self.upload_region(rect, PixelData::U8(data));
+
+        Ok(())
+    }
+
+    // same as update_region, for float data going into HDR textures
+    pub fn update_region_f32(&self, rect: PixelRect, data: &[f32]) -> Result<(), String> {
+        self.check_region(&rect, data.len())?;
+        self.upload_region(rect, PixelData::F32(data));
+
+        Ok(())
+    }
+
+    fn check_region(&self, rect: &PixelRect, data_len: usize) -> Result<(), String> {
+        if rect.x + rect.width > self.width || rect.y + rect.height > self.height {
+            return Err(format!(
+                "region {:?} out of bounds for {}x{} texture",
+                rect, self.width, self.height
+            ));
         }
 
-        Ok(texture)
+        check_data_len(
+            data_len,
+            rect.width,
+            rect.height,
+            self.descriptor.format.channels(),
+        )
+    }
+
+    fn upload_region(&self, rect: PixelRect, data: PixelData) {
+        backend::with(|backend| {
+            backend.bind_texture(TextureTarget::Texture2D, self.id);
+            backend.texture_sub_image_2d(rect, self.descriptor.format, data);
+
+            // keep the smaller levels in sync with what we just wrote
+            if self.descriptor.mipmaps.is_some() {
+                backend.generate_mipmaps(TextureTarget::Texture2D);
+            }
+            backend.bind_texture(TextureTarget::Texture2D, 0);
+        });
+    }
+
+    // copies level 0 back from the GPU. float formats come back as floats, everything else as
+    // bytes. this stalls until every pending draw into the texture is done
+    pub fn read_pixels(&self) -> Image {
+        let pixels = backend::with(|backend| {
+            backend.bind_texture(TextureTarget::Texture2D, self.id);
+            let pixels = backend.read_texture(self.width, self.height, self.descriptor.format);
+            backend.bind_texture(TextureTarget::Texture2D, 0);
+            pixels
+        });
+
+        Image {
+            width: self.width,
+            height: self.height,
+            channels: self.descriptor.format.channels(),
+            pixels,
+        }
+    }
+
+    // reallocates the texture with new dimensions keeping the overlapping top left area;
+    // anything that was not covered before is left uninitialized
+    pub fn resize(&mut self, width: usize, height: usize) {
+        if width == self.width && height == self.height {
+            return;
+        }
+
+        let old = self.read_pixels();
+        self.width = width;
+        self.height = height;
+        backend::with(|backend| {
+            backend.bind_texture(TextureTarget::Texture2D, self.id);
+            self.allocate(backend, PixelData::None);
+            backend.bind_texture(TextureTarget::Texture2D, 0);
+        });
+
+        let kept = PixelRect {
+            x: 0,
+            y: 0,
+            width: width.min(old.width),
+            height: height.min(old.height),
+        };
+        if kept.width == 0 || kept.height == 0 {
+            return;
+        }
+
+        let row_len = old.width * old.channels;
+        let kept_row_len = kept.width * old.channels;
+        let result = match &old.pixels {
+            ImagePixels::U8(data) => {
+                self.update_region(kept, &crop_rows(data, row_len, kept_row_len, kept.height))
+            }
+            ImagePixels::F32(data) => {
+                self.update_region_f32(kept, &crop_rows(data, row_len, kept_row_len, kept.height))
+            }
+        };
+        result.expect("kept region is inside the resized texture");
     }
 
     pub fn with_white_new() -> Result<Texture, String> {
-        Texture::from_data(vec![0x0], 1, 1)
+        Texture::from_data(vec![0xff; 4], 1, 1)
     }
 
     pub fn bind(&self) {
-        unsafe {
-            gl::BindTexture(gl::TEXTURE_2D, self.id);
-        }
+        backend::with(|backend| backend.bind_texture(TextureTarget::Texture2D, self.id));
+        stats::record_texture_bind();
     }
 
     pub fn unbind(&self) {
-        unsafe {
-            gl::BindTexture(gl::TEXTURE_2D, 0);
-        }
+        backend::with(|backend| backend.bind_texture(TextureTarget::Texture2D, 0));
+    }
+}
+
+impl Drop for Texture {
+    fn drop(&mut self) {
+        backend::with(|backend| backend.delete_texture(self.id));
     }
 }
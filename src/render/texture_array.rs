@@ -0,0 +1,182 @@
+use super::{
+    backend::{self, PixelData, TextureTarget},
+    debug::ObjectKind,
+    image::{Image, ImagePixels},
+    stats,
+    texture::{check_data_len, TextureDescriptor},
+};
+
+// GL_TEXTURE_2D_ARRAY where every layer is one tile of a tileset. sampling a layer never reads
+// from its neighbours, so tiles don't bleed into each other under linear filtering or mipmapping
+pub struct TextureArray {
+    id: gl::types::GLuint,
+    tile_width: usize,
+    tile_height: usize,
+    columns: usize,
+    layers: usize,
+    descriptor: TextureDescriptor,
+}
+
+impl TextureArray {
+    // slices _image_ in tiles of _tile_width_ x _tile_height_, left to right and top to bottom;
+    // the tile at column x, row y ends up in layer y * columns + x. leftover pixels at the
+    // right and bottom edges are ignored
+    pub fn from_tileset(
+        image: &Image,
+        tile_width: usize,
+        tile_height: usize,
+        descriptor: &TextureDescriptor,
+    ) -> Result<TextureArray, String> {
+        if tile_width == 0 || tile_height == 0 {
+            return Err("tile dimensions must be greater than zero".to_string());
+        }
+        if image.channels != descriptor.format.channels() {
+            return Err(format!(
+                "can't store a {} channel image as {:?}",
+                image.channels, descriptor.format
+            ));
+        }
+
+        let columns = image.width / tile_width;
+        let rows = image.height / tile_height;
+        let layers = columns * rows;
+        if layers == 0 {
+            return Err(format!(
+                "{}x{} image is smaller than a {}x{} tile",
+                image.width, image.height, tile_width, tile_height
+            ));
+        }
+
+        let data_len = match &image.pixels {
+            ImagePixels::U8(data) => data.len(),
+            ImagePixels::F32(data) => data.len(),
+        };
+        check_data_len(data_len, image.width, image.height, image.channels)?;
+
+        let slicing = TileSlicing {
+            image_width: image.width,
+            channels: image.channels,
+            tile_width,
+            tile_height,
+            columns,
+            rows,
+        };
+
+        let id = backend::with(|backend| backend.create_texture());
+        match &image.pixels {
+            ImagePixels::U8(data) => upload_layers(
+                id,
+                PixelData::U8(&slicing.slice(data)),
+                &slicing,
+                descriptor,
+            ),
+            ImagePixels::F32(data) => upload_layers(
+                id,
+                PixelData::F32(&slicing.slice(data)),
+                &slicing,
+                descriptor,
+            ),
+        }
+
+        Ok(TextureArray {
+            id,
+            tile_width,
+            tile_height,
+            columns,
+            layers,
+            descriptor: *descriptor,
+        })
+    }
+
+    // layer holding the tile at _tile_index_x_, _tile_index_y_ of the original tileset
+    pub fn layer_of(&self, tile_index_x: usize, tile_index_y: usize) -> u32 {
+        (tile_index_y * self.columns + tile_index_x) as u32
+    }
+
+    pub fn layers(&self) -> usize {
+        self.layers
+    }
+
+    pub fn tile_width_f(&self) -> f32 {
+        self.tile_width as f32
+    }
+
+    pub fn tile_height_f(&self) -> f32 {
+        self.tile_height as f32
+    }
+
+    pub fn descriptor(&self) -> &TextureDescriptor {
+        &self.descriptor
+    }
+
+    pub fn set_label(&self, label: &str) {
+        backend::with(|backend| backend.set_label(ObjectKind::Texture, self.id, label));
+    }
+
+    pub fn bind(&self) {
+        backend::with(|backend| backend.bind_texture(TextureTarget::Texture2DArray, self.id));
+        stats::record_texture_bind();
+    }
+
+    pub fn unbind(&self) {
+        backend::with(|backend| backend.bind_texture(TextureTarget::Texture2DArray, 0));
+    }
+}
+
+impl Drop for TextureArray {
+    fn drop(&mut self) {
+        backend::with(|backend| backend.delete_texture(self.id));
+    }
+}
+
+struct TileSlicing {
+    image_width: usize,
+    channels: usize,
+    tile_width: usize,
+    tile_height: usize,
+    columns: usize,
+    rows: usize,
+}
+
+impl TileSlicing {
+    // rearranges the image so each tile is contiguous, in layer order
+    fn slice<T: Copy>(&self, data: &[T]) -> Vec<T> {
+        let tile_row_len = self.tile_width * self.channels;
+        let image_row_len = self.image_width * self.channels;
+        let mut layers =
+            Vec::with_capacity(self.columns * self.rows * self.tile_height * tile_row_len);
+
+        for tile_y in 0..self.rows {
+            for tile_x in 0..self.columns {
+                for y in 0..self.tile_height {
+                    let start =
+                        (tile_y * self.tile_height + y) * image_row_len + tile_x * tile_row_len;
+                    layers.extend_from_slice(&data[start..start + tile_row_len]);
+                }
+            }
+        }
+
+        layers
+    }
+}
+
+// specifies every layer of texture _id_ and its sampling
+fn upload_layers(
+    id: backend::ObjectId,
+    data: PixelData,
+    slicing: &TileSlicing,
+    descriptor: &TextureDescriptor,
+) {
+    backend::with(|backend| {
+        backend.bind_texture(TextureTarget::Texture2DArray, id);
+        backend.texture_image_3d(
+            slicing.tile_width,
+            slicing.tile_height,
+            slicing.columns * slicing.rows,
+            descriptor.format,
+            data,
+        );
+        backend.set_sampling(TextureTarget::Texture2DArray, descriptor);
+        backend.bind_texture(TextureTarget::Texture2DArray, 0);
+    });
+}
@@ -0,0 +1,175 @@
+use super::backend;
+use super::framebuffer::Framebuffer;
+use super::texture::{PixelRect, TextureDescriptor, TextureFilter};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ScaleMode {
+    // largest whole factor that fits the window, so every virtual pixel covers the same amount
+    // of window pixels. windows smaller than the virtual screen fall back to Fit
+    Integer,
+    // as large as fits while keeping the aspect ratio; virtual pixels end up of slightly
+    // different sizes, so they are filtered linearly
+    Fit,
+}
+
+// renders the scene at a fixed low resolution (e.g. 320x180 for pixel art) and presents it
+// scaled up to the window, centered with bars on the sides that are left over:
+//
+//  screen.begin();
+//  ... draw the scene in virtual coordinates ...
+//  screen.present();
+//
+// renderers drawing between begin and present should use the virtual size for their projection
+pub struct VirtualScreen {
+    framebuffer: Framebuffer,
+    window_width: usize,
+    window_height: usize,
+    scale_mode: ScaleMode,
+    bar_color: glm::Vec4,
+}
+
+impl VirtualScreen {
+    pub fn new(
+        width: usize,
+        height: usize,
+        window_width: usize,
+        window_height: usize,
+        scale_mode: ScaleMode,
+    ) -> Result<VirtualScreen, String> {
+        let framebuffer = Framebuffer::new(width, height, &TextureDescriptor::pixel_art())?;
+        framebuffer.set_label("virtual screen");
+
+        Ok(VirtualScreen {
+            framebuffer,
+            window_width,
+            window_height,
+            scale_mode,
+            bar_color: glm::vec4(0.0, 0.0, 0.0, 1.0),
+        })
+    }
+
+    pub fn width(&self) -> usize {
+        self.framebuffer.width()
+    }
+
+    pub fn height(&self) -> usize {
+        self.framebuffer.height()
+    }
+
+    pub fn size(&self) -> glm::Vec2 {
+        glm::vec2(self.width() as f32, self.height() as f32)
+    }
+
+    pub fn framebuffer(&self) -> &Framebuffer {
+        &self.framebuffer
+    }
+
+    pub fn scale_mode(&self) -> ScaleMode {
+        self.scale_mode
+    }
+
+    pub fn set_scale_mode(&mut self, scale_mode: ScaleMode) {
+        self.scale_mode = scale_mode;
+    }
+
+    // in window pixels, which with high dpi can be more than the window size in points
+    pub fn set_window_size(&mut self, width: usize, height: usize) {
+        self.window_width = width;
+        self.window_height = height;
+    }
+
+    pub fn set_bar_color(&mut self, color: glm::Vec4) {
+        self.bar_color = color;
+    }
+
+    // window pixels per virtual pixel
+    pub fn scale(&self) -> f32 {
+        let fit = (self.window_width as f32 / self.width() as f32)
+            .min(self.window_height as f32 / self.height() as f32);
+
+        match self.scale_mode {
+            ScaleMode::Integer if fit >= 1.0 => fit.floor(),
+            _ => fit,
+        }
+    }
+
+    // where the virtual screen ends up in the window, in window coordinates (from the bottom left)
+    pub fn viewport(&self) -> PixelRect {
+        let scale = self.scale();
+        let width = ((self.width() as f32 * scale).round() as usize).min(self.window_width);
+        let height = ((self.height() as f32 * scale).round() as usize).min(self.window_height);
+
+        PixelRect {
+            x: (self.window_width - width) / 2,
+            y: (self.window_height - height) / 2,
+            width,
+            height,
+        }
+    }
+
+    // starts drawing the scene into the virtual screen
+    pub fn begin(&self) {
+        self.framebuffer.bind();
+    }
+
+    // draws the virtual screen to the window and leaves the window bound with a viewport
+    // covering all of it, for overlays drawn at window resolution
+    pub fn present(&self) {
+        let filter = match self.scale_mode {
+            ScaleMode::Integer => TextureFilter::Nearest,
+            ScaleMode::Fit => TextureFilter::Linear,
+        };
+        let window = PixelRect {
+            x: 0,
+            y: 0,
+            width: self.window_width,
+            height: self.window_height,
+        };
+
+        self.framebuffer.unbind();
+        backend::with(|backend| {
+            backend.viewport(window);
+            backend.clear(self.bar_color);
+            backend.blit_framebuffer(
+                self.framebuffer.id(),
+                self.framebuffer.rect(),
+                0,
+                self.viewport(),
+                filter,
+            );
+        });
+    }
+
+    // converts a position in window pixels measured from the top left corner, like mouse
+    // positions, to virtual coordinates. None when it falls on the bars
+    pub fn window_to_virtual(&self, position: glm::Vec2) -> Option<glm::Vec2> {
+        let viewport = self.viewport();
+        let top = (self.window_height - viewport.y - viewport.height) as f32;
+        let scale = glm::vec2(
+            viewport.width as f32 / self.width() as f32,
+            viewport.height as f32 / self.height() as f32,
+        );
+        let virtual_position = (position - glm::vec2(viewport.x as f32, top)).component_div(&scale);
+
+        let inside = virtual_position.x >= 0.0
+            && virtual_position.y >= 0.0
+            && virtual_position.x < self.width() as f32
+            && virtual_position.y < self.height() as f32;
+        if inside {
+            Some(virtual_position)
+        } else {
+            None
+        }
+    }
+
+    pub fn virtual_to_window(&self, position: glm::Vec2) -> glm::Vec2 {
+        let viewport = self.viewport();
+        let top = (self.window_height - viewport.y - viewport.height) as f32;
+        let scale = glm::vec2(
+            viewport.width as f32 / self.width() as f32,
+            viewport.height as f32 / self.height() as f32,
+        );
+
+        position.component_mul(&scale) + glm::vec2(viewport.x as f32, top)
+    }
+}
@@ -8,7 +8,13 @@ use stb_image::image::LoadResult;
 
 use stb_image::{self};
 
-use crate::render::texture::Texture;
+use crate::render::animation_controller::{AnimationController, AnimationControllerDefinition};
+use crate::render::aseprite::AsepriteFile;
+use crate::render::image::{Image, ImagePixels};
+use crate::render::skeleton::SkeletonData;
+use crate::render::sprite_sheet::{SpriteSheet, SpriteSheetLayout};
+use crate::render::texture::{Texture, TextureDescriptor};
+use crate::render::texture_array::TextureArray;
 
 #[derive(Debug)]
 pub enum Error {
@@ -17,10 +23,35 @@ pub enum Error {
     FailedToGetExePath,
 }
 
+#[derive(Clone)]
 pub struct Resources {
     root_path: PathBuf,
 }
 
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ImageChannels {
+    // keep whatever the file has, e.g. a grayscale png stays single channel
+    Native,
+    // expand (or drop) channels so every pixel has exactly this many components
+    Force(usize),
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ImageLoadOptions {
+    // images are stored top row first; flip them for code that expects GL's bottom-left origin
+    pub flip_vertically: bool,
+    pub channels: ImageChannels,
+}
+
+impl Default for ImageLoadOptions {
+    fn default() -> ImageLoadOptions {
+        ImageLoadOptions {
+            flip_vertically: false,
+            channels: ImageChannels::Native,
+        }
+    }
+}
+
 impl From<io::Error> for Error {
     fn from(other: io::Error) -> Self {
         Error::Io(other)
@@ -56,27 +87,176 @@ impl Resources {
         Ok(unsafe { ffi::CString::from_vec_unchecked(buffer) })
     }
 
-    // todo: dumb return struct
-    fn load_image(&self, resource_name: &str) -> Result<(usize, usize, Vec<u8>), &str> {
-        //unsafe {stbi_set_flip_vertically_on_load(1)};
-        match stb_image::image::load(Resources::resource_name_to_path(
-            &self.root_path,
-            resource_name,
-        )) {
-            LoadResult::ImageU8(image_data) => {
-                Ok((image_data.width, image_data.height, image_data.data))
+    pub fn load_bytes(&self, resource_name: &str) -> Result<Vec<u8>, Error> {
+        let path = Resources::resource_name_to_path(&self.root_path, resource_name);
+
+        Ok(fs::read(path)?)
+    }
+
+    pub fn load_string(&self, resource_name: &str) -> Result<String, Error> {
+        let path = Resources::resource_name_to_path(&self.root_path, resource_name);
+
+        Ok(fs::read_to_string(path)?)
+    }
+
+    // ldr images decode to bytes, hdr ones (.hdr) to floats
+    pub fn load_image(
+        &self,
+        resource_name: &str,
+        options: &ImageLoadOptions,
+    ) -> Result<Image, &str> {
+        let path = Resources::resource_name_to_path(&self.root_path, resource_name);
+        let result = match options.channels {
+            ImageChannels::Native => stb_image::image::load(path),
+            ImageChannels::Force(channels) => {
+                stb_image::image::load_with_depth(path, channels, false)
             }
-            //LoadResult::ImageF32(image_data) =>  Ok((image_data.width,image_data.height,vec![])),
-            _ => Err("Error loading image; incorrect format?"),
+        };
+
+        let mut image = match result {
+            LoadResult::ImageU8(image_data) => Image {
+                width: image_data.width,
+                height: image_data.height,
+                channels: image_data.depth,
+                pixels: ImagePixels::U8(image_data.data),
+            },
+            LoadResult::ImageF32(image_data) => Image {
+                width: image_data.width,
+                height: image_data.height,
+                channels: image_data.depth,
+                pixels: ImagePixels::F32(image_data.data),
+            },
+            LoadResult::Error(_) => {
+                return Err("Error loading image; missing file or unsupported format?")
+            }
+        };
+
+        // done here rather than with stbi_set_flip_vertically_on_load, which is global state
+        if options.flip_vertically {
+            image.flip_vertically();
         }
+
+        Ok(image)
+    }
+
+    pub fn load_texture(&self, resource_name: &str) -> Result<Texture, String> {
+        self.load_texture_with(resource_name, &TextureDescriptor::default())
+    }
+
+    // expands or drops image channels to fit descriptor.format
+    pub fn load_texture_with(
+        &self,
+        resource_name: &str,
+        descriptor: &TextureDescriptor,
+    ) -> Result<Texture, String> {
+        let options = ImageLoadOptions {
+            channels: ImageChannels::Force(descriptor.format.channels()),
+            ..ImageLoadOptions::default()
+        };
+
+        self.load_texture_with_options(resource_name, &options, descriptor)
+    }
+
+    // the texture's format is picked as in texture_from_image
+    pub fn load_texture_with_options(
+        &self,
+        resource_name: &str,
+        options: &ImageLoadOptions,
+        descriptor: &TextureDescriptor,
+    ) -> Result<Texture, String> {
+        let image = self.load_image(resource_name, options)?;
+
+        texture_from_image(&image, descriptor)
+    }
+
+    pub fn load_texture_array(
+        &self,
+        resource_name: &str,
+        tile_width: usize,
+        tile_height: usize,
+    ) -> Result<TextureArray, String> {
+        self.load_texture_array_with(
+            resource_name,
+            tile_width,
+            tile_height,
+            &TextureDescriptor::default(),
+        )
+    }
+
+    // slices a tileset image in layers of tile_width x tile_height
+    pub fn load_texture_array_with(
+        &self,
+        resource_name: &str,
+        tile_width: usize,
+        tile_height: usize,
+        descriptor: &TextureDescriptor,
+    ) -> Result<TextureArray, String> {
+        let options = ImageLoadOptions {
+            channels: ImageChannels::Force(descriptor.format.channels()),
+            ..ImageLoadOptions::default()
+        };
+        let image = self.load_image(resource_name, &options)?;
+
+        TextureArray::from_tileset(&image, tile_width, tile_height, descriptor)
+    }
+
+    // reads the json Aseprite exports next to a sprite sheet. the image it names is relative to
+    // the json (see relative_to) and is loaded along with the layout by Assets::load::<SpriteSheet>
+    pub fn load_sprite_sheet_layout(
+        &self,
+        resource_name: &str,
+    ) -> Result<SpriteSheetLayout, String> {
+        let json = self
+            .load_string(resource_name)
+            .map_err(|e| format!("error loading {}: {:?}", resource_name, e))?;
+
+        SpriteSheetLayout::from_aseprite_json(&json)
+            .map_err(|e| format!("error loading {}: {}", resource_name, e))
+    }
+
+    // reads .aseprite/.ase files directly, with every frame packed in one pixel art texture
+    pub fn load_aseprite(&self, resource_name: &str) -> Result<SpriteSheet, String> {
+        self.load_aseprite_with(resource_name, &TextureDescriptor::pixel_art())
     }
 
-    pub fn load_texture(&self, resource_name: &str) -> Result<Texture, &str> {
-        let image_data = self.load_image(resource_name)?;
-        let texture = Texture::from_data(image_data.2, image_data.0, image_data.1)
-            .expect("error loading texture");
+    // descriptor.format has to be RGBA
+    pub fn load_aseprite_with(
+        &self,
+        resource_name: &str,
+        descriptor: &TextureDescriptor,
+    ) -> Result<SpriteSheet, String> {
+        let bytes = self
+            .load_bytes(resource_name)
+            .map_err(|e| format!("error loading {}: {:?}", resource_name, e))?;
 
-        Ok(texture)
+        AsepriteFile::parse(&bytes)
+            .and_then(|file| file.to_sprite_sheet(resource_name, descriptor))
+            .map_err(|e| format!("error loading {}: {}", resource_name, e))
+    }
+
+    // clips of the controller are tags of _sheet_
+    pub fn load_animation_controller(
+        &self,
+        resource_name: &str,
+        sheet: &SpriteSheet,
+    ) -> Result<AnimationController, String> {
+        let json = self
+            .load_string(resource_name)
+            .map_err(|e| format!("error loading {}: {:?}", resource_name, e))?;
+
+        AnimationControllerDefinition::from_json(&json)
+            .and_then(|definition| definition.build(sheet))
+            .map_err(|e| format!("error loading {}: {}", resource_name, e))
+    }
+
+    // spine 3.8 json, see SkeletonData
+    pub fn load_skeleton_data(&self, resource_name: &str) -> Result<SkeletonData, String> {
+        let json = self
+            .load_string(resource_name)
+            .map_err(|e| format!("error loading {}: {:?}", resource_name, e))?;
+
+        SkeletonData::from_spine_json(&json)
+            .map_err(|e| format!("error loading {}: {}", resource_name, e))
     }
 
     fn resource_name_to_path(root_dir: &Path, location: &str) -> PathBuf {
@@ -89,3 +269,35 @@ impl Resources {
         path
     }
 }
+
+// uploads _image_ keeping descriptor.format when it can hold the image; otherwise the texture is
+// stored in the image's native format (float for hdr images, sRGB if descriptor asked for it)
+pub fn texture_from_image(
+    image: &Image,
+    descriptor: &TextureDescriptor,
+) -> Result<Texture, String> {
+    let mut descriptor = *descriptor;
+    let fits_format = image.channels == descriptor.format.channels()
+        && (!image.is_hdr() || descriptor.format.is_float());
+    if !fits_format {
+        let native_format = image
+            .native_format()
+            .map_err(|_| "Error loading image; unsupported amount of channels".to_string())?;
+        descriptor.format = if descriptor.format.is_srgb() {
+            native_format.to_srgb()
+        } else {
+            native_format
+        };
+    }
+
+    Texture::from_image(image, &descriptor)
+}
+
+// resource name of _file_ when named relative to the resource _resource_name_, like the image
+// of an exported sprite sheet
+pub fn relative_to(resource_name: &str, file: &str) -> String {
+    match resource_name.rfind('/') {
+        Some(slash) => format!("{}/{}", &resource_name[..slash], file),
+        None => file.to_string(),
+    }
+}
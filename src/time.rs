@@ -0,0 +1,107 @@
+use std::time::Duration;
+
+// game time, advanced explicitly by the main loop instead of read from the clock, so it can be
+// slowed down, paused, or stepped by hand to replay the exact same updates:
+//
+//  while game_loop.tick() {
+//      time.advance(game_loop.tick_duration());
+//      animation.update(&time);
+//  }
+//
+// "unscaled" values are the time that actually passed, for things that keep running in slow
+// motion or while paused (menus, debug overlays)
+pub struct Time {
+    time_scale: f32,
+    paused: bool,
+    delta: Duration,
+    unscaled_delta: Duration,
+    elapsed: Duration,
+    unscaled_elapsed: Duration,
+    updates: u64,
+}
+
+impl Time {
+    pub fn new() -> Time {
+        Time {
+            time_scale: 1.0,
+            paused: false,
+            delta: Duration::ZERO,
+            unscaled_delta: Duration::ZERO,
+            elapsed: Duration::ZERO,
+            unscaled_elapsed: Duration::ZERO,
+            updates: 0,
+        }
+    }
+
+    // starts the next update, _dt_ after the previous one
+    pub fn advance(&mut self, dt: Duration) {
+        self.unscaled_delta = dt;
+        self.delta = if self.paused {
+            Duration::ZERO
+        } else {
+            dt.mul_f32(self.time_scale)
+        };
+
+        self.elapsed += self.delta;
+        self.unscaled_elapsed += dt;
+        self.updates += 1;
+    }
+
+    // scaled time since the previous update; zero while paused
+    pub fn delta(&self) -> Duration {
+        self.delta
+    }
+
+    pub fn delta_seconds(&self) -> f32 {
+        self.delta.as_secs_f32()
+    }
+
+    pub fn unscaled_delta(&self) -> Duration {
+        self.unscaled_delta
+    }
+
+    // scaled time since the start
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    pub fn unscaled_elapsed(&self) -> Duration {
+        self.unscaled_elapsed
+    }
+
+    // times advance was called, paused or not
+    pub fn updates(&self) -> u64 {
+        self.updates
+    }
+
+    pub fn time_scale(&self) -> f32 {
+        self.time_scale
+    }
+
+    // 0.5 is half speed, 2.0 double. negative scales are treated as 0
+    pub fn set_time_scale(&mut self, time_scale: f32) {
+        self.time_scale = time_scale.max(0.0);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+}
+
+impl Default for Time {
+    fn default() -> Time {
+        Time::new()
+    }
+}
@@ -0,0 +1,450 @@
+use std::cell::Cell;
+use std::f32::consts::PI;
+use std::rc::Rc;
+use std::time::Duration;
+
+use crate::time::Time;
+
+// Robert Penner's easing curves; "in" starts slow, "out" ends slow
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Easing {
+    Linear,
+    QuadIn,
+    QuadOut,
+    QuadInOut,
+    CubicIn,
+    CubicOut,
+    CubicInOut,
+    QuartIn,
+    QuartOut,
+    QuartInOut,
+    QuintIn,
+    QuintOut,
+    QuintInOut,
+    SineIn,
+    SineOut,
+    SineInOut,
+    ExpoIn,
+    ExpoOut,
+    ExpoInOut,
+    CircIn,
+    CircOut,
+    CircInOut,
+    // overshoot a little past the ends
+    BackIn,
+    BackOut,
+    BackInOut,
+    ElasticIn,
+    ElasticOut,
+    ElasticInOut,
+    BounceIn,
+    BounceOut,
+    BounceInOut,
+}
+
+impl Easing {
+    // maps _t_ in 0..1 to the eased progress; 0 and 1 map to themselves, values in between can
+    // go outside 0..1 for Back and Elastic
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+
+        match self {
+            Easing::Linear => t,
+            Easing::QuadIn => t * t,
+            Easing::QuadOut => 1.0 - (1.0 - t) * (1.0 - t),
+            Easing::QuadInOut => in_out(t, |t| t * t),
+            Easing::CubicIn => t.powi(3),
+            Easing::CubicOut => 1.0 - (1.0 - t).powi(3),
+            Easing::CubicInOut => in_out(t, |t| t.powi(3)),
+            Easing::QuartIn => t.powi(4),
+            Easing::QuartOut => 1.0 - (1.0 - t).powi(4),
+            Easing::QuartInOut => in_out(t, |t| t.powi(4)),
+            Easing::QuintIn => t.powi(5),
+            Easing::QuintOut => 1.0 - (1.0 - t).powi(5),
+            Easing::QuintInOut => in_out(t, |t| t.powi(5)),
+            Easing::SineIn => 1.0 - (t * PI / 2.0).cos(),
+            Easing::SineOut => (t * PI / 2.0).sin(),
+            Easing::SineInOut => -((t * PI).cos() - 1.0) / 2.0,
+            Easing::ExpoIn => expo_in(t),
+            Easing::ExpoOut => 1.0 - expo_in(1.0 - t),
+            Easing::ExpoInOut => in_out(t, expo_in),
+            Easing::CircIn => 1.0 - (1.0 - t * t).sqrt(),
+            Easing::CircOut => (1.0 - (t - 1.0) * (t - 1.0)).sqrt(),
+            Easing::CircInOut => in_out(t, |t| 1.0 - (1.0 - t * t).sqrt()),
+            Easing::BackIn => back_in(t),
+            Easing::BackOut => 1.0 - back_in(1.0 - t),
+            Easing::BackInOut => in_out(t, back_in),
+            Easing::ElasticIn => elastic_in(t),
+            Easing::ElasticOut => 1.0 - elastic_in(1.0 - t),
+            Easing::ElasticInOut => in_out(t, elastic_in),
+            Easing::BounceIn => 1.0 - bounce_out(1.0 - t),
+            Easing::BounceOut => bounce_out(t),
+            Easing::BounceInOut => in_out(t, |t| 1.0 - bounce_out(1.0 - t)),
+        }
+    }
+}
+
+// first half eases in, second half is the same curve mirrored
+fn in_out(t: f32, ease_in: impl Fn(f32) -> f32) -> f32 {
+    if t < 0.5 {
+        ease_in(t * 2.0) / 2.0
+    } else {
+        1.0 - ease_in((1.0 - t) * 2.0) / 2.0
+    }
+}
+
+fn expo_in(t: f32) -> f32 {
+    if t == 0.0 {
+        0.0
+    } else {
+        2.0_f32.powf(10.0 * t - 10.0)
+    }
+}
+
+fn back_in(t: f32) -> f32 {
+    const OVERSHOOT: f32 = 1.70158;
+    (OVERSHOOT + 1.0) * t * t * t - OVERSHOOT * t * t
+}
+
+fn elastic_in(t: f32) -> f32 {
+    if t == 0.0 || t == 1.0 {
+        return t;
+    }
+
+    -(2.0_f32.powf(10.0 * t - 10.0)) * ((t * 10.0 - 10.75) * (2.0 * PI / 3.0)).sin()
+}
+
+fn bounce_out(t: f32) -> f32 {
+    const N: f32 = 7.5625;
+    const D: f32 = 2.75;
+
+    if t < 1.0 / D {
+        N * t * t
+    } else if t < 2.0 / D {
+        let t = t - 1.5 / D;
+        N * t * t + 0.75
+    } else if t < 2.5 / D {
+        let t = t - 2.25 / D;
+        N * t * t + 0.9375
+    } else {
+        let t = t - 2.625 / D;
+        N * t * t + 0.984375
+    }
+}
+
+// values that can be interpolated; colours are glm::Vec4 like everywhere else
+pub trait Tweenable: Copy {
+    fn lerp(from: Self, to: Self, t: f32) -> Self;
+}
+
+impl Tweenable for f32 {
+    fn lerp(from: f32, to: f32, t: f32) -> f32 {
+        from + (to - from) * t
+    }
+}
+
+impl Tweenable for glm::Vec2 {
+    fn lerp(from: glm::Vec2, to: glm::Vec2, t: f32) -> glm::Vec2 {
+        from + (to - from) * t
+    }
+}
+
+impl Tweenable for glm::Vec3 {
+    fn lerp(from: glm::Vec3, to: glm::Vec3, t: f32) -> glm::Vec3 {
+        from + (to - from) * t
+    }
+}
+
+impl Tweenable for glm::Vec4 {
+    fn lerp(from: glm::Vec4, to: glm::Vec4, t: f32) -> glm::Vec4 {
+        from + (to - from) * t
+    }
+}
+
+// anything that plays over time: tweens, delays and groups of them
+pub trait Timeline {
+    // moves forward by _dt_ and returns what is left of it once the timeline is finished, so
+    // sequences can hand it to what comes next. finished timelines return all of _dt_
+    fn advance(&mut self, dt: Duration) -> Duration;
+
+    fn is_finished(&self) -> bool;
+
+    // back to the start, values go back to their initial ones on the next advance
+    fn reset(&mut self);
+
+    fn update(&mut self, time: &Time) {
+        self.advance(time.delta());
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Repeat {
+    // plays this many more times after the first
+    Times(u32),
+    Forever,
+}
+
+// shared view of a tween's current value, for tweens moved into a Sequence or Parallel
+#[derive(Clone)]
+pub struct TweenValue<T: Tweenable>(Rc<Cell<T>>);
+
+impl<T: Tweenable> TweenValue<T> {
+    pub fn get(&self) -> T {
+        self.0.get()
+    }
+}
+
+// interpolates from one value to another:
+//
+//  let mut fade = Tween::new(0.0, 1.0, Duration::from_millis(300), Easing::QuadOut)
+//      .with_delay(Duration::from_millis(100));
+//  ...
+//  fade.update(&time);
+//  color.w = fade.value();
+pub struct Tween<T: Tweenable> {
+    from: T,
+    to: T,
+    duration: Duration,
+    easing: Easing,
+    delay: Duration,
+    repeat: Repeat,
+    // every other repetition goes back from _to_ to _from_
+    yoyo: bool,
+    elapsed: Duration,
+    value: TweenValue<T>,
+}
+
+impl<T: Tweenable> Tween<T> {
+    pub fn new(from: T, to: T, duration: Duration, easing: Easing) -> Tween<T> {
+        Tween {
+            from,
+            to,
+            duration,
+            easing,
+            delay: Duration::ZERO,
+            repeat: Repeat::Times(0),
+            yoyo: false,
+            elapsed: Duration::ZERO,
+            value: TweenValue(Rc::new(Cell::new(from))),
+        }
+    }
+
+    // waits this long before starting, only the first time when repeating
+    pub fn with_delay(mut self, delay: Duration) -> Tween<T> {
+        self.delay = delay;
+        self
+    }
+
+    pub fn with_repeat(mut self, repeat: Repeat) -> Tween<T> {
+        self.repeat = repeat;
+        self
+    }
+
+    pub fn with_yoyo(mut self, yoyo: bool) -> Tween<T> {
+        self.yoyo = yoyo;
+        self
+    }
+
+    pub fn value(&self) -> T {
+        self.value.get()
+    }
+
+    pub fn output(&self) -> TweenValue<T> {
+        self.value.clone()
+    }
+
+    // delay and every repetition; None when repeating forever, or for longer than a Duration
+    // can hold, which plays the same
+    pub fn total_duration(&self) -> Option<Duration> {
+        match self.repeat {
+            Repeat::Times(times) => times
+                .checked_add(1)
+                .and_then(|plays| self.duration.checked_mul(plays))
+                .and_then(|playing| self.delay.checked_add(playing)),
+            Repeat::Forever => None,
+        }
+    }
+
+    fn value_at(&self, repetition: u64, t: f32) -> T {
+        let t = self.easing.apply(t);
+        if self.yoyo && repetition % 2 == 1 {
+            T::lerp(self.to, self.from, t)
+        } else {
+            T::lerp(self.from, self.to, t)
+        }
+    }
+}
+
+impl<T: Tweenable> Timeline for Tween<T> {
+    fn advance(&mut self, dt: Duration) -> Duration {
+        if self.is_finished() {
+            return dt;
+        }
+
+        self.elapsed += dt;
+        let mut left = Duration::ZERO;
+        if let Some(total) = self.total_duration() {
+            if self.elapsed >= total {
+                left = self.elapsed - total;
+                self.elapsed = total;
+            }
+        }
+
+        let value = if self.elapsed < self.delay {
+            self.value_at(0, 0.0)
+        } else if self.is_finished() || self.duration.is_zero() {
+            let repetitions = match self.repeat {
+                Repeat::Times(times) => times as u64,
+                Repeat::Forever => 0,
+            };
+            self.value_at(repetitions, 1.0)
+        } else {
+            let playing = (self.elapsed - self.delay).as_secs_f64();
+            let duration = self.duration.as_secs_f64();
+            let repetition = (playing / duration).floor();
+            self.value_at(
+                repetition as u64,
+                ((playing - repetition * duration) / duration) as f32,
+            )
+        };
+        self.value.0.set(value);
+
+        left
+    }
+
+    fn is_finished(&self) -> bool {
+        match self.total_duration() {
+            Some(total) => self.elapsed >= total,
+            None => false,
+        }
+    }
+
+    fn reset(&mut self) {
+        self.elapsed = Duration::ZERO;
+        self.value.0.set(self.from);
+    }
+}
+
+// a pause in a Sequence
+pub struct Delay {
+    duration: Duration,
+    elapsed: Duration,
+}
+
+impl Delay {
+    pub fn new(duration: Duration) -> Delay {
+        Delay {
+            duration,
+            elapsed: Duration::ZERO,
+        }
+    }
+}
+
+impl Timeline for Delay {
+    fn advance(&mut self, dt: Duration) -> Duration {
+        let left = (self.elapsed + dt).saturating_sub(self.duration);
+        self.elapsed = (self.elapsed + dt).min(self.duration);
+        left
+    }
+
+    fn is_finished(&self) -> bool {
+        self.elapsed >= self.duration
+    }
+
+    fn reset(&mut self) {
+        self.elapsed = Duration::ZERO;
+    }
+}
+
+// plays timelines one after the other. time left over by one carries over to the next, so a
+// sequence ends up in the same place whatever the size of the steps it is advanced by
+//
+//  let position = slide.output();
+//  let mut intro = Sequence::new()
+//      .then(slide)
+//      .wait(Duration::from_secs(1))
+//      .then(Parallel::new().with(fade_out).with(zoom));
+#[derive(Default)]
+pub struct Sequence {
+    timelines: Vec<Box<dyn Timeline>>,
+    current: usize,
+}
+
+impl Sequence {
+    pub fn new() -> Sequence {
+        Sequence::default()
+    }
+
+    pub fn then(mut self, timeline: impl Timeline + 'static) -> Sequence {
+        self.timelines.push(Box::new(timeline));
+        self
+    }
+
+    pub fn wait(self, duration: Duration) -> Sequence {
+        self.then(Delay::new(duration))
+    }
+}
+
+impl Timeline for Sequence {
+    fn advance(&mut self, mut dt: Duration) -> Duration {
+        while let Some(timeline) = self.timelines.get_mut(self.current) {
+            dt = timeline.advance(dt);
+            if !timeline.is_finished() {
+                return Duration::ZERO;
+            }
+
+            self.current += 1;
+        }
+
+        dt
+    }
+
+    fn is_finished(&self) -> bool {
+        self.current >= self.timelines.len()
+    }
+
+    fn reset(&mut self) {
+        self.current = 0;
+        for timeline in &mut self.timelines {
+            timeline.reset();
+        }
+    }
+}
+
+// plays timelines at the same time, finished once all of them are
+#[derive(Default)]
+pub struct Parallel {
+    timelines: Vec<Box<dyn Timeline>>,
+}
+
+impl Parallel {
+    pub fn new() -> Parallel {
+        Parallel::default()
+    }
+
+    pub fn with(mut self, timeline: impl Timeline + 'static) -> Parallel {
+        self.timelines.push(Box::new(timeline));
+        self
+    }
+}
+
+impl Timeline for Parallel {
+    fn advance(&mut self, dt: Duration) -> Duration {
+        // the longest one decides how much is left over
+        self.timelines
+            .iter_mut()
+            .map(|timeline| timeline.advance(dt))
+            .min()
+            .unwrap_or(dt)
+    }
+
+    fn is_finished(&self) -> bool {
+        self.timelines.iter().all(|timeline| timeline.is_finished())
+    }
+
+    fn reset(&mut self) {
+        for timeline in &mut self.timelines {
+            timeline.reset();
+        }
+    }
+}